@@ -0,0 +1,98 @@
+//! Benchmarks for the transform pipeline
+//!
+//! Run with `cargo bench --bench transform`.
+
+use chrome2moz::parser::manifest::parse_manifest;
+use chrome2moz::transformer::{JavaScriptTransformer, ManifestTransformer};
+use chrome2moz::{convert_bytes, ConversionOptions, Extension};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use zip::write::{FileOptions, ZipWriter};
+
+const MANIFEST: &str = r#"{
+  "manifest_version": 3,
+  "name": "Benchmark Extension",
+  "version": "1.2.3.4",
+  "minimum_chrome_version": "111",
+  "background": { "service_worker": "background.js" },
+  "action": { "default_popup": "popup.html" },
+  "permissions": ["storage", "tabs", "scripting", "offscreen", "https://example.com/*"],
+  "host_permissions": ["<all_urls>"],
+  "content_scripts": [
+    { "matches": ["https://example.com/*"], "js": ["content.js"], "css": ["content.css"] }
+  ],
+  "web_accessible_resources": [
+    { "resources": ["images/*"], "matches": ["<all_urls>"], "use_dynamic_url": true }
+  ],
+  "content_security_policy": { "extension_pages": "script-src 'self'; object-src 'self'" },
+  "key": "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA"
+}"#;
+
+/// A background script making 1000 chrome.* calls
+fn large_background_script() -> String {
+    let mut code = String::from("importScripts('lib.js');\n");
+    for i in 0..1000 {
+        code.push_str(&format!(
+            "chrome.storage.local.get('key{i}', (result) => {{ chrome.runtime.sendMessage({{ i: {i}, result }}); }});\n"
+        ));
+    }
+    code
+}
+
+fn extension_zip() -> Vec<u8> {
+    let files = [
+        ("manifest.json", MANIFEST.to_string()),
+        ("background.js", large_background_script()),
+        ("lib.js", "const lib = {};\n".to_string()),
+        ("content.js", "chrome.runtime.sendMessage({ ready: true });\n".to_string()),
+        ("content.css", "body { outline: 1px solid red; }\n".to_string()),
+        ("popup.html", "<html><body><script src=\"popup.js\"></script></body></html>\n".to_string()),
+        ("popup.js", "chrome.tabs.query({ active: true }, (tabs) => console.log(tabs));\n".to_string()),
+    ];
+    
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, content) in files {
+        zip.start_file(name, FileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn bench_manifest_transform(c: &mut Criterion) {
+    let manifest = parse_manifest(MANIFEST.as_bytes()).unwrap();
+    let mut files = HashMap::new();
+    files.insert(PathBuf::from("background.js"), large_background_script().into_bytes());
+    let source = Extension::new(manifest.clone(), files);
+    let transformer = ManifestTransformer::new(&[]);
+    
+    c.bench_function("manifest_transform", |b| {
+        b.iter(|| transformer.transform(black_box(&manifest), Some(&source)).unwrap())
+    });
+}
+
+fn bench_javascript_transform(c: &mut Criterion) {
+    let code = large_background_script();
+    let path = PathBuf::from("background.js");
+    let transformer = JavaScriptTransformer::new(&[]);
+    
+    c.bench_function("javascript_transform_1000_calls", |b| {
+        b.iter(|| transformer.transform(black_box(&code), &path).unwrap())
+    });
+}
+
+fn bench_convert_bytes(c: &mut Criterion) {
+    let zip_data = extension_zip();
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    c.bench_function("convert_bytes", |b| {
+        b.iter(|| convert_bytes(black_box(&zip_data), options.clone()).unwrap())
+    });
+}
+
+criterion_group!(benches, bench_manifest_transform, bench_javascript_transform, bench_convert_bytes);
+criterion_main!(benches);
@@ -1,8 +1,54 @@
 //! JavaScript API analysis
 
-use crate::models::{Incompatibility, Severity, IncompatibilityCategory, Location};
-use crate::parser::javascript::{analyze_javascript, get_chrome_api_info};
-use std::path::PathBuf;
+use crate::models::{Extension, Incompatibility, Severity, IncompatibilityCategory, Location, Manifest};
+use crate::parser::javascript::{analyze_javascript, find_calls, find_matching_delimiter, get_chrome_api_info, split_top_level};
+use crate::utils::helpers::line_of;
+use regex::Regex;
+use lazy_static::lazy_static;
+use std::path::{Path, PathBuf};
+
+lazy_static! {
+    // String literal contents ('...', "..." or `...`)
+    static ref STRING_LITERAL: Regex = Regex::new(
+        r#""((?:[^"\\\n]|\\.)*)"|'((?:[^'\\\n]|\\.)*)'|`((?:[^`\\]|\\.)*)`"#
+    ).unwrap();
+}
+
+/// Namespaces with no Firefox equivalent at all, and how to migrate away from them
+const UNSUPPORTED_NAMESPACES: &[(&str, &str)] = &[
+    (
+        "chrome.gcm",
+        "Firefox has no gcm API. Migrate to the W3C Push API (registration.pushManager.subscribe() and a 'push' event listener in a service worker)",
+    ),
+    (
+        "chrome.system",
+        "Firefox has no system.cpu/memory/storage/display APIs. Use navigator.hardwareConcurrency and navigator.deviceMemory for rough CPU/memory hints, navigator.storage.estimate() for quota, and window.screen for displays; the system-stub.js shim makes remaining calls reject with a clear error",
+    ),
+    (
+        "chrome.fileSystem",
+        "chrome.fileSystem is a Chrome packaged-app API with no Firefox equivalent. Let the user pick files with <input type=\"file\"> (or showOpenFilePicker()/showSaveFilePicker() where the File System Access API is available) and save with downloads.download()",
+    ),
+    (
+        "chrome.printerProvider",
+        "Firefox extensions can't register printers. There is no replacement; keep the ChromeOS build for printer support",
+    ),
+    (
+        "chrome.printing",
+        "Firefox has no printing API for submitting or tracking print jobs. Open the content in a tab and call window.print() (or tabs.print()/tabs.saveAsPDF()) so the user confirms the print dialog",
+    ),
+    (
+        "chrome.printingMetrics",
+        "chrome.printingMetrics is a ChromeOS-only API with no Firefox equivalent; print history isn't exposed to Firefox extensions",
+    ),
+];
+
+fn unsupported_namespace(api_name: &str) -> Option<(&'static str, &'static str)> {
+    UNSUPPORTED_NAMESPACES.iter()
+        .find(|(namespace, _)| {
+            api_name.strip_prefix(namespace).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .copied()
+}
 
 pub fn analyze_javascript_apis(content: &str, path: &PathBuf) -> Vec<Incompatibility> {
     let mut issues = Vec::new();
@@ -11,6 +57,20 @@ pub fn analyze_javascript_apis(content: &str, path: &PathBuf) -> Vec<Incompatibi
     match analyze_javascript(content) {
         Ok(api_calls) => {
             for call in api_calls {
+                // APIs that are simply undefined in Firefox can't be shimmed into working
+                if let Some((namespace, suggestion)) = unsupported_namespace(&call.api_name) {
+                    issues.push(
+                        Incompatibility::new(
+                            Severity::Blocker,
+                            IncompatibilityCategory::ChromeOnlyApi,
+                            Location::FileLocation(path.clone(), call.line),
+                            format!("{}: the {} namespace does not exist in Firefox", call.api_name, namespace)
+                        )
+                        .with_suggestion(suggestion)
+                    );
+                    continue;
+                }
+                
                 // Check for Chrome-only APIs
                 if call.is_chrome_only {
                     let api_name = &call.api_name;
@@ -72,10 +132,794 @@ pub fn analyze_javascript_apis(content: &str, path: &PathBuf) -> Vec<Incompatibi
     issues
 }
 
+/// Check that APIs used in a script have the permissions Firefox requires for them
+pub fn analyze_api_permissions(content: &str, path: &Path, manifest: &Manifest) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    
+    // tabs.captureVisibleTab needs activeTab or <all_urls> in Firefox
+    let capture_re = Regex::new(r"\b(?:chrome|browser)\.tabs\.captureVisibleTab\s*\(").unwrap();
+    if let Some(line) = first_match_line(&capture_re, content) {
+        let has_capture_permission = manifest.permissions.iter()
+            .chain(manifest.host_permissions.iter())
+            .any(|p| matches!(p.as_str(), "activeTab" | "<all_urls>" | "*://*/*" | "http://*/*" | "https://*/*"));
+        
+        if !has_capture_permission {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::HostPermissions,
+                    Location::FileLocation(path.to_path_buf(), line),
+                    "tabs.captureVisibleTab requires the 'activeTab' permission or '<all_urls>' host permission in Firefox"
+                )
+                .with_suggestion("Add \"activeTab\" to permissions (or \"<all_urls>\" to host_permissions), otherwise the call throws at runtime")
+            );
+        }
+    }
+    
+    // scripting.insertCSS/removeCSS need the scripting permission like executeScript
+    let css_re = Regex::new(r"\b(?:chrome|browser)\.scripting\.(?:insertCSS|removeCSS)\s*\(").unwrap();
+    if let Some(line) = first_match_line(&css_re, content) {
+        if !manifest.permissions.iter().any(|p| p == "scripting") {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::HostPermissions,
+                    Location::FileLocation(path.to_path_buf(), line),
+                    "scripting.insertCSS/removeCSS require the 'scripting' permission, which the manifest does not declare"
+                )
+                .with_suggestion("The 'scripting' permission will be added; the target tab also needs a matching host permission or activeTab")
+                .auto_fixable()
+            );
+        }
+    }
+    
+    // webNavigation listeners need the webNavigation permission
+    let web_nav_re = Regex::new(r"\b(?:chrome|browser)\.webNavigation\.").unwrap();
+    if let Some(line) = first_match_line(&web_nav_re, content) {
+        if !manifest.permissions.iter().any(|p| p == "webNavigation") {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::Permissions,
+                    Location::FileLocation(path.to_path_buf(), line),
+                    "webNavigation requires the 'webNavigation' permission, which the manifest does not declare"
+                )
+                .with_suggestion("Add \"webNavigation\" to permissions, otherwise browser.webNavigation is undefined in Firefox")
+            );
+        }
+    }
+    
+    issues
+}
+
+/// Chrome's storage.sync per-item quota (QUOTA_BYTES_PER_ITEM)
+const SYNC_QUOTA_BYTES_PER_ITEM: usize = 8192;
+
+/// Detect APIs that exist in Firefox but behave differently than in Chrome
+pub fn analyze_behavior_differences(content: &str, path: &Path) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    
+    // storage.sync quotas and sync backend differ
+    for call in find_calls(content, r"\b(?:chrome|browser)\.storage\.sync\.set") {
+        let largest_literal = string_literals(call.args(content))
+            .map(|literal| literal.len())
+            .max()
+            .unwrap_or(0);
+        
+        let (severity, description) = if largest_literal > SYNC_QUOTA_BYTES_PER_ITEM {
+            (
+                Severity::Minor,
+                format!(
+                    "storage.sync.set() writes a {} byte string literal, above the {} byte per-item sync quota; Firefox rejects writes over its sync quotas",
+                    largest_literal, SYNC_QUOTA_BYTES_PER_ITEM
+                ),
+            )
+        } else {
+            (
+                Severity::Info,
+                "storage.sync is backed by Firefox Sync, whose quota enforcement differs from Chrome's (8KB per item / 100KB total)".to_string(),
+            )
+        };
+        
+        issues.push(
+            Incompatibility::new(
+                severity,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), call.line),
+                description
+            )
+            .with_suggestion("Keep storage.sync for small settings and store large data in storage.local")
+        );
+    }
+    
+    // Synchronous XMLHttpRequest: xhr.open(method, url, false)
+    for call in find_calls(content, r"[\w$\])]\.open") {
+        let args = call.arguments(content);
+        let is_http_method = args.first()
+            .map(|method| method.trim_matches(|c| c == '\'' || c == '"' || c == '`'))
+            .is_some_and(|method| {
+                matches!(method.to_ascii_uppercase().as_str(), "GET" | "POST" | "PUT" | "DELETE" | "HEAD" | "PATCH" | "OPTIONS")
+            });
+        
+        if is_http_method && args.get(2) == Some(&"false") {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::BehaviorDifference,
+                    Location::FileLocation(path.to_path_buf(), call.line),
+                    "Synchronous XMLHttpRequest (open() with async = false) is deprecated and unreliable in Firefox extension contexts"
+                )
+                .with_suggestion("Use fetch() (or an asynchronous XMLHttpRequest) instead")
+            );
+        }
+    }
+    
+    // runtime.onInstalled reason values (Chrome's 'chrome_update' is 'browser_update' in Firefox)
+    if content.contains("onInstalled") {
+        let reason_re = Regex::new(
+            r#"\.reason\s*[!=]==?\s*['"]\w+['"]|['"]\w+['"]\s*[!=]==?\s*[\w$.]*\.reason\b|OnInstalledReason\.\w+|switch\s*\(\s*[\w$.]*\.reason\s*\)"#
+        ).unwrap();
+        
+        if let Some(line) = first_match_line(&reason_re, content) {
+            let description = if content.contains("chrome_update") || content.contains("CHROME_UPDATE") {
+                "runtime.onInstalled reason 'chrome_update' is reported as 'browser_update' in Firefox"
+            } else {
+                "runtime.onInstalled reason checks: Firefox reports 'browser_update' instead of 'chrome_update', and temporary add-ons report 'install' on every load"
+            };
+            
+            issues.push(
+                Incompatibility::new(
+                    Severity::Info,
+                    IncompatibilityCategory::BehaviorDifference,
+                    Location::FileLocation(path.to_path_buf(), line),
+                    description
+                )
+                .with_suggestion("Handle both 'chrome_update' and 'browser_update', and don't assume onboarding runs exactly once during development")
+            );
+        }
+    }
+    
+    // proxy.settings: Chrome's ProxyConfig shape differs from Firefox's
+    let proxy_re = Regex::new(r"\b(?:chrome|browser)\.proxy\.settings\.(?:set|get|clear)\s*\(").unwrap();
+    if let Some(line) = first_match_line(&proxy_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Major,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "proxy.settings uses a different config shape in Firefox: Chrome's { mode, rules: { singleProxy, bypassList }, pacScript } becomes { proxyType, http, ssl, socks, passthrough, autoConfigUrl }"
+            )
+            .with_suggestion("The proxy-settings-compat.js shim translates fixed_servers and pac_script configs; verify other modes and consider proxy.onRequest for per-request logic")
+        );
+    }
+    
+    // Browser sniffing via navigator.userAgent / navigator.vendor
+    let sniff_re = Regex::new(r"\bnavigator\.(?:userAgent|vendor)\b").unwrap();
+    if let Some(line) = first_match_line(&sniff_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "navigator.userAgent/navigator.vendor browser sniffing may take Chrome code paths (or miss Firefox ones) after conversion"
+            )
+            .with_suggestion("Prefer feature detection, or browser.runtime.getBrowserInfo() when the browser really matters")
+        );
+    }
+    
+    // runtime.setUninstallURL
+    let uninstall_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.setUninstallURL\s*\(").unwrap();
+    if let Some(line) = first_match_line(&uninstall_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "runtime.setUninstallURL is supported in Firefox, but only http(s) URLs of at most 1023 characters are opened"
+            )
+            .with_suggestion("Make sure the uninstall page URL is http(s) and doesn't depend on Chrome-specific query parameters")
+        );
+    }
+    
+    // runtime.getPlatformInfo: arch enum values differ
+    let platform_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.getPlatformInfo\s*\(").unwrap();
+    if let Some(line) = first_match_line(&platform_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "runtime.getPlatformInfo values differ in Firefox: 64-bit ARM is reported as arch 'aarch64' instead of 'arm64', 'mips'/'mips64' and os 'fuchsia' never occur, and nacl_arch is missing"
+            )
+            .with_suggestion("Compare against both 'arm64' and 'aarch64', and don't rely on nacl_arch")
+        );
+    }
+    
+    // notifications.getPermissionLevel: not implemented in Firefox
+    let permission_level_re = Regex::new(r"\b(?:chrome|browser)\.notifications\.getPermissionLevel\s*\(").unwrap();
+    if let Some(line) = first_match_line(&permission_level_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "notifications.getPermissionLevel doesn't exist in Firefox, which has no per-extension notification permission level to query"
+            )
+            .with_suggestion("The notifications compatibility shim always reports 'granted'; Firefox users turn notifications off in the OS or browser settings instead")
+            .auto_fixable()
+        );
+    }
+    
+    // runtime.requestUpdateCheck: Firefox updates add-ons itself
+    let update_check_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.requestUpdateCheck\s*\(").unwrap();
+    if let Some(line) = first_match_line(&update_check_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "runtime.requestUpdateCheck doesn't exist in Firefox; AMO-listed add-ons update on Firefox's own schedule and self-hosted ones through the gecko update_url manifest"
+            )
+            .with_suggestion("Drop the update check (or guard it with a feature test) and listen for runtime.onUpdateAvailable, then call runtime.reload() to apply a pending update")
+        );
+    }
+    
+    // webNavigation: frame ids and event details differ
+    let web_nav_listener_re = Regex::new(r"\b(?:chrome|browser)\.webNavigation\.on\w+\.addListener\s*\(").unwrap();
+    if let Some(line) = first_match_line(&web_nav_listener_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "webNavigation events differ in Firefox: subframe frameId/parentFrameId values don't match Chrome's, and details lack documentId, parentDocumentId, frameType and documentLifecycle"
+            )
+            .with_suggestion("Only compare frameId against 0 for the top frame, look up frames with webNavigation.getAllFrames(), and check that url filters match the events you expect")
+        );
+    }
+    
+    // contextMenus: Firefox's menus.onClicked info shape and supported contexts
+    let clicked_re = Regex::new(r"\b(?:chrome|browser)\.contextMenus\.onClicked\.addListener\s*\(").unwrap();
+    if let Some(line) = first_match_line(&clicked_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "contextMenus.onClicked is menus.onClicked in Firefox: info gains button, modifiers, targetElementId and viewType, and tab is undefined for clicks outside a tab"
+            )
+            .with_suggestion("The context-menus-compat.js shim passes listeners Chrome's info shape; guard uses of tab")
+        );
+    }
+    let launcher_re = Regex::new(r#"\bcontexts\s*:\s*\[[^\]]*['"`]launcher['"`]"#).unwrap();
+    for call in find_calls(content, r"\b(?:chrome|browser)\.contextMenus\.(?:create|update)") {
+        if launcher_re.is_match(call.args(content)) {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Major,
+                    IncompatibilityCategory::ChromeOnlyApi,
+                    Location::FileLocation(path.to_path_buf(), call.line),
+                    "The 'launcher' menu context is Chrome-only; Firefox rejects the whole menu item"
+                )
+                .with_suggestion("Remove 'launcher' from contexts (use 'action' or 'tab' for toolbar and tab-strip menus)")
+            );
+        }
+    }
+    
+    // identity: Firefox has no access to the browser's signed-in account
+    let profile_re = Regex::new(r"\b(?:chrome|browser)\.identity\.(?:getProfileUserInfo|getAccounts)\s*\(").unwrap();
+    if let Some(line) = first_match_line(&profile_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Major,
+                IncompatibilityCategory::ChromeOnlyApi,
+                Location::FileLocation(path.to_path_buf(), line),
+                "identity.getProfileUserInfo/getAccounts don't exist in Firefox: there is no signed-in browser account to read, so the call fails"
+            )
+            .with_suggestion("Sign the user in with identity.launchWebAuthFlow() against your OAuth provider (redirect URL from identity.getRedirectURL()) and read the email from its userinfo endpoint")
+        );
+    }
+    
+    // tts: no Firefox API, shimmed onto Web Speech
+    let tts_re = Regex::new(r"\b(?:chrome|browser)\.tts\.").unwrap();
+    if let Some(line) = first_match_line(&tts_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::ChromeOnlyApi,
+                Location::FileLocation(path.to_path_buf(), line),
+                "Firefox has no tts API; the tts-compat.js shim maps speak/stop onto speechSynthesis"
+            )
+            .with_suggestion("Check voices and rates in Firefox: gender, extensionId and required/desiredEventTypes options are ignored, and speechSynthesis only works in contexts with a window")
+        );
+    }
+    
+    // Alarms: Firefox clamps periods and delays to one minute
+    for timing in short_alarm_timings(content) {
+        let description = match timing.minutes {
+            Some(_) => format!(
+                "alarms.create {}: {} is below Firefox's 1-minute minimum; the alarm silently runs once a minute instead",
+                timing.field, timing.value
+            ),
+            None => format!(
+                "alarms.create {} is computed ({}); Firefox clamps values below 1 minute",
+                timing.field, timing.value
+            ),
+        };
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), timing.line),
+                description
+            )
+            .with_suggestion("Use periods of at least 1 minute, or a setTimeout/setInterval loop while the background page is awake for shorter intervals")
+        );
+    }
+    
+    // scripting.executeScript func: the function is serialized without its closure
+    for call in find_calls(content, r"\b(?:chrome|browser)\.scripting\.executeScript") {
+        let Some((name, function)) = injected_function(content, call.args(content)) else { continue };
+        let captured = captured_identifiers(content, function);
+        if captured.is_empty() {
+            continue;
+        }
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), call.line),
+                format!(
+                    "scripting.executeScript func {} references extension-scope {} ({}); injected functions run in the page without their closure",
+                    name,
+                    if captured.len() == 1 { "variable" } else { "variables" },
+                    captured.join(", ")
+                )
+            )
+            .with_suggestion("Pass the values through args (they must be JSON-serializable), or move the code into a file injected with files")
+        );
+    }
+    
+    // Message passing: frame targeting and async responses differ subtly
+    let tabs_send_re = Regex::new(r"\b(?:chrome|browser)\.tabs\.sendMessage\s*\(").unwrap();
+    if let Some(line) = first_match_line(&tabs_send_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "tabs.sendMessage reaches every frame of the tab unless options.frameId is set, and Firefox ignores options.documentId"
+            )
+            .with_suggestion(format!("Pass {{ frameId: 0 }} to target only the top frame and don't rely on documentId. {}", ASYNC_RESPONSE_ACTION))
+        );
+    }
+    
+    let runtime_send_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.sendMessage\s*\(").unwrap();
+    if let Some(line) = first_match_line(&runtime_send_re, content) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Info,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path.to_path_buf(), line),
+                "runtime.sendMessage resolves with undefined in Firefox when the receiving listener answers asynchronously without returning true"
+            )
+            .with_suggestion(ASYNC_RESPONSE_ACTION)
+        );
+    }
+    
+    issues
+}
+
+/// Firefox's minimum alarm period and delay
+pub const MIN_ALARM_MINUTES: f64 = 1.0;
+
+/// A `periodInMinutes` / `delayInMinutes` value passed to `alarms.create`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmTiming {
+    /// Offset of the `alarms.create` call
+    pub call_start: usize,
+    pub line: usize,
+    pub field: String,
+    /// Source text of the value
+    pub value: String,
+    /// Numeric value, `None` when it isn't a literal
+    pub minutes: Option<f64>,
+}
+
+/// `alarms.create` timings below [`MIN_ALARM_MINUTES`] or not given as numeric literals
+pub fn short_alarm_timings(content: &str) -> Vec<AlarmTiming> {
+    let timing_re = Regex::new(r"\b(periodInMinutes|delayInMinutes)\s*:\s*([^,}\n]+)").unwrap();
+    
+    let mut timings = Vec::new();
+    for call in find_calls(content, r"\b(?:chrome|browser)\.alarms\.create") {
+        for caps in timing_re.captures_iter(call.args(content)) {
+            let value = caps[2].trim().to_string();
+            let minutes = value.parse::<f64>().ok();
+            if minutes.is_some_and(|m| m >= MIN_ALARM_MINUTES) {
+                continue;
+            }
+            timings.push(AlarmTiming {
+                call_start: call.start,
+                line: call.line,
+                field: caps[1].to_string(),
+                value,
+                minutes,
+            });
+        }
+    }
+    timings
+}
+
+/// Name (or `inline function`) and source text of the function passed as `func` to `executeScript`
+fn injected_function<'a>(content: &'a str, args: &'a str) -> Option<(String, &'a str)> {
+    let details = split_top_level(args, ',').into_iter().next()?;
+    let body = details.strip_prefix('{')?.strip_suffix('}')?;
+    let value = split_top_level(body, ',').into_iter()
+        .find_map(|prop| prop.strip_prefix("func")?.trim_start().strip_prefix(':'))?
+        .trim();
+    
+    let ident_re = Regex::new(r"^[A-Za-z_$][\w$]*$").unwrap();
+    if !ident_re.is_match(value) {
+        return Some(("(inline function)".to_string(), value));
+    }
+    
+    // Named function: find its declaration in the same file
+    let decl_re = Regex::new(&format!(
+        r"\bfunction\s+{0}\s*\(|\b(?:const|let|var)\s+{0}\s*=\s*(?:async\s+)?(?:function\b|\(|[A-Za-z_$][\w$]*\s*=>)",
+        regex::escape(value)
+    )).ok()?;
+    let start = decl_re.find(content)?.start();
+    let open = start + content[start..].find('{')?;
+    let close = find_matching_delimiter(content, open)?;
+    Some((value.to_string(), &content[start..=close]))
+}
+
+/// Identifiers a function uses that are declared elsewhere in the file rather than inside it
+fn captured_identifiers(content: &str, function: &str) -> Vec<String> {
+    const KEYWORDS: &[&str] = &[
+        "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete",
+        "do", "else", "false", "finally", "for", "function", "if", "in", "instanceof", "let", "new",
+        "null", "of", "return", "switch", "this", "throw", "true", "try", "typeof", "undefined", "var",
+        "void", "while", "yield",
+    ];
+    let literal_re = Regex::new(r#"'(?:[^'\\\n]|\\.)*'|"(?:[^"\\\n]|\\.)*"|`(?:[^`\\]|\\.)*`|//[^\n]*"#).unwrap();
+    let code = literal_re.replace_all(function, "\"\"");
+    
+    let local_re = Regex::new(r"\b(?:const|let|var|function|class)\s+([A-Za-z_$][\w$]*)|\bcatch\s*\(\s*([A-Za-z_$][\w$]*)").unwrap();
+    let declared_names = |text| local_re.captures_iter(text)
+        .filter_map(|caps| caps.get(1).or(caps.get(2)).map(|m| m.as_str()))
+        .collect::<Vec<&str>>();
+    let declared = declared_names(content);
+    let mut locals = declared_names(&code);
+    // Parameters: everything before the body (or the arrow)
+    let head_end = code.find("=>").into_iter().chain(code.find('{')).min().unwrap_or(0);
+    let ident_re = Regex::new(r"[A-Za-z_$][\w$]*").unwrap();
+    locals.extend(ident_re.find_iter(&code[..head_end]).map(|m| m.as_str()));
+    // Parameters of inner functions and arrows, without their default values
+    let inner_params_re = Regex::new(r"\bfunction\b[^(]*\(([^()]*)\)|\(([^()]*)\)\s*=>|([A-Za-z_$][\w$]*)\s*=>").unwrap();
+    for caps in inner_params_re.captures_iter(&code[head_end..]) {
+        let Some(params) = caps.get(1).or(caps.get(2)).or(caps.get(3)) else { continue };
+        for param in params.as_str().split(',') {
+            let name = param.split('=').next().unwrap_or_default();
+            locals.extend(ident_re.find_iter(name).map(|m| m.as_str()));
+        }
+    }
+    
+    let mut captured = Vec::new();
+    for m in ident_re.find_iter(&code[head_end..]) {
+        let name = m.as_str();
+        let start = head_end + m.start();
+        let after = code[head_end + m.end()..].trim_start();
+        let is_member = code[..start].trim_end().ends_with('.');
+        let is_key = after.starts_with(':') && !after.starts_with("::");
+        if is_member || is_key || KEYWORDS.contains(&name) || locals.contains(&name) || captured.iter().any(|c| c == name) {
+            continue;
+        }
+        if declared.contains(&name) {
+            captured.push(name.to_string());
+        }
+    }
+    captured
+}
+
+/// Manual check shared by the message-passing advisories
+const ASYNC_RESPONSE_ACTION: &str = "Manual action: make every onMessage listener that calls sendResponse asynchronously return true (or return a Promise instead); in Firefox the first listener to return a Promise answers";
+
+/// Flag `tabs.sendMessage` when no script in the extension listens for messages
+///
+/// Without a `runtime.onMessage` listener the promise rejects with
+/// "Receiving end does not exist", which Chrome code often ignores via callbacks.
+pub fn analyze_message_receivers(extension: &Extension) -> Vec<Incompatibility> {
+    let tabs_send_re = Regex::new(r"\b(?:chrome|browser)\.tabs\.sendMessage\s*\(").unwrap();
+    let receiver_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.onMessage\.addListener\b").unwrap();
+    
+    let mut js_files = extension.get_javascript_files();
+    js_files.sort();
+    let contents: Vec<(PathBuf, String)> = js_files.into_iter()
+        .filter_map(|path| extension.get_file_content(&path).map(|content| (path, content)))
+        .collect();
+    if contents.iter().any(|(_, content)| receiver_re.is_match(content)) {
+        return Vec::new();
+    }
+    
+    contents.iter()
+        .filter_map(|(path, content)| {
+            let line = first_match_line(&tabs_send_re, content)?;
+            Some(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::BehaviorDifference,
+                    Location::FileLocation(path.clone(), line),
+                    "tabs.sendMessage is called but no script registers runtime.onMessage, so Firefox rejects with \"Receiving end does not exist\""
+                )
+                .with_suggestion("Add a runtime.onMessage listener to the content script, or catch the rejection where the tab may have no receiver")
+            )
+        })
+        .collect()
+}
+
+/// Detect service-worker-only lifecycle events in a background script
+///
+/// Firefox runs the background as an event page, so `fetch`, `install` and
+/// `activate` listeners on the global scope are registered but never fire.
+pub fn analyze_service_worker_events(content: &str, path: &Path) -> Vec<Incompatibility> {
+    let listener_re = Regex::new(
+        r#"\baddEventListener\s*\(\s*['"`](fetch|install|activate)['"`]"#
+    ).unwrap();
+    
+    let mut issues = Vec::new();
+    let mut seen = Vec::new();
+    
+    for caps in listener_re.captures_iter(content) {
+        let event = caps.get(1).unwrap().as_str();
+        if seen.contains(&event) {
+            continue;
+        }
+        seen.push(event);
+        
+        let start = caps.get(0).unwrap().start();
+        let line = line_of(content, start);
+        let suggestion = if event == "fetch" {
+            "Intercept requests with webRequest (blocking listeners work in Firefox) or declarativeNetRequest rules instead"
+        } else {
+            "Move install/activate setup into runtime.onInstalled / runtime.onStartup listeners"
+        };
+        
+        issues.push(
+            Incompatibility::new(
+                Severity::Major,
+                IncompatibilityCategory::ServiceWorkerLifecycle,
+                Location::FileLocation(path.to_path_buf(), line),
+                format!(
+                    "Service worker '{}' event listener never fires in Firefox: the background runs as an event page, not a service worker",
+                    event
+                )
+            )
+            .with_suggestion(suggestion)
+        );
+    }
+    
+    issues
+}
+
+/// Detect `action.setIcon({ imageData })` in a background script
+///
+/// The ImageData is usually drawn on a DOM canvas, which a Chrome service
+/// worker never had and which breaks once the background is an event page
+/// built for workers; icons given by `path` are unaffected.
+pub fn analyze_background_set_icon(content: &str, path: &Path) -> Vec<Incompatibility> {
+    let image_data_re = Regex::new(r"\bimageData\b").unwrap();
+    
+    find_calls(content, r"\b(?:chrome|browser)\.(?:action|browserAction|pageAction)\.setIcon")
+        .into_iter()
+        .filter(|call| image_data_re.is_match(call.args(content)))
+        .map(|call| {
+            Incompatibility::new(
+                Severity::Major,
+                IncompatibilityCategory::BackgroundWorker,
+                Location::FileLocation(path.to_path_buf(), call.line),
+                "setIcon is passed imageData in the background; building ImageData from a DOM canvas fails in a worker-style background"
+            )
+            .with_suggestion("Draw the icon on an OffscreenCanvas (getContext('2d').getImageData works without a DOM), or ship precomputed icons and pass { path }")
+        })
+        .collect()
+}
+
+/// Detect hardcoded Chrome extension IDs (32 characters `a`-`p`) in string literals
+///
+/// The converted add-on is identified by its gecko ID, so messaging targets,
+/// `chrome-extension://` URLs and `runtime.id` checks built on the Chrome ID break.
+pub fn analyze_hardcoded_extension_ids(content: &str, path: &Path) -> Vec<Incompatibility> {
+    let id_re = Regex::new(r"\b[a-p]{32}\b").unwrap();
+    let mut issues = Vec::new();
+    let mut last_line = 0;
+    
+    for caps in STRING_LITERAL.captures_iter(content) {
+        let Some(literal) = caps.get(1).or_else(|| caps.get(2)).or_else(|| caps.get(3)) else { continue };
+        let Some(id) = id_re.find(literal.as_str()) else { continue };
+        
+        let line = line_of(content, literal.start());
+        if line == last_line {
+            continue;
+        }
+        last_line = line;
+        
+        let line_text = content.lines().nth(line - 1).unwrap_or_default();
+        let description = if line_text.contains("runtime.id") {
+            format!("runtime.id is compared to the hardcoded Chrome extension ID '{}'; it is the gecko ID in Firefox", id.as_str())
+        } else {
+            format!("Hardcoded Chrome extension ID '{}' will not match the extension in Firefox", id.as_str())
+        };
+        
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::ExtensionId,
+                Location::FileLocation(path.to_path_buf(), line),
+                description
+            )
+            .with_suggestion("Use runtime.id / runtime.getURL() at runtime, or the browser_specific_settings.gecko.id of the converted extension")
+        );
+    }
+    
+    issues
+}
+
+/// Flag native messaging, whose host manifest must list the Firefox extension ID
+pub fn analyze_native_messaging(content: &str, path: &Path, manifest: &Manifest, gecko_id: &str) -> Vec<Incompatibility> {
+    let native_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.(?:connectNative|sendNativeMessage)\s*\(").unwrap();
+    let Some(line) = first_match_line(&native_re, content) else { return Vec::new() };
+    
+    let mut suggestion = format!(
+        "Install a Firefox host manifest (in Firefox's NativeMessagingHosts location or registry key) whose \"allowed_extensions\" contains \"{}\"; Chrome's \"allowed_origins\" is ignored",
+        gecko_id
+    );
+    if !manifest.permissions.iter().any(|p| p == "nativeMessaging") {
+        suggestion.push_str(". Also add the \"nativeMessaging\" permission");
+    }
+    
+    vec![
+        Incompatibility::new(
+            Severity::Major,
+            IncompatibilityCategory::ExtensionId,
+            Location::FileLocation(path.to_path_buf(), line),
+            "Native messaging host manifests are keyed by extension ID and installed per browser; the Chrome host registration will not be found by Firefox"
+        )
+        .with_suggestion(suggestion)
+    ]
+}
+
+/// Flag external message listeners that rely on `externally_connectable` web page matches
+///
+/// Firefox doesn't support `externally_connectable` (it's removed during
+/// conversion), so web pages can't reach `onMessageExternal`/`onConnectExternal`;
+/// messages from other extensions still arrive.
+pub fn analyze_external_messaging(content: &str, path: &Path, manifest: &Manifest) -> Vec<Incompatibility> {
+    let external_re = Regex::new(r"\b(?:chrome|browser)\.runtime\.on(?:Message|Connect)External\.addListener\s*\(").unwrap();
+    let Some(line) = first_match_line(&external_re, content) else { return Vec::new() };
+    
+    let page_matches: Vec<&str> = manifest.extra.get("externally_connectable")
+        .and_then(|value| value.get("matches"))
+        .and_then(|matches| matches.as_array())
+        .map(|matches| matches.iter().filter_map(|m| m.as_str()).collect())
+        .unwrap_or_default();
+    if page_matches.is_empty() {
+        return Vec::new();
+    }
+    
+    vec![
+        Incompatibility::new(
+            Severity::Blocker,
+            IncompatibilityCategory::ChromeOnlyApi,
+            Location::FileLocation(path.to_path_buf(), line),
+            format!(
+                "runtime.onMessageExternal/onConnectExternal won't receive messages from {}: Firefox doesn't support externally_connectable, so web pages can't message the extension",
+                page_matches.join(", ")
+            )
+        )
+        .with_suggestion("Bridge the page and the extension with a content script on those sites: the page calls window.postMessage, the content script checks event.source/origin and forwards with runtime.sendMessage (reply the same way)")
+    ]
+}
+
+/// Contents of the string literals in a snippet of JavaScript
+fn string_literals(code: &str) -> impl Iterator<Item = &str> {
+    STRING_LITERAL.captures_iter(code)
+        .filter_map(|caps| caps.get(1).or_else(|| caps.get(2)).or_else(|| caps.get(3)))
+        .map(|m| m.as_str())
+}
+
+/// 1-based line number of the first regex match
+fn first_match_line(re: &Regex, content: &str) -> Option<usize> {
+    re.find(content).map(|m| line_of(content, m.start()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_external_messaging_from_pages_is_blocker() {
+        let code = "chrome.runtime.onMessageExternal.addListener((message, sender, sendResponse) => {\n  sendResponse({ ok: true });\n});";
+        let path = PathBuf::from("background.js");
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "externally_connectable": {"matches": ["https://app.example.com/*"]}}"#
+        ).unwrap();
+        let issues = analyze_external_messaging(code, &path, &manifest);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Blocker);
+        assert!(issues[0].description.contains("https://app.example.com/*"));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("window.postMessage"));
+        
+        // Without web page matches only other extensions message it, which Firefox supports
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        assert!(analyze_external_messaging(code, &path, &manifest).is_empty());
+    }
+    
+    #[test]
+    fn test_connect_native_guidance() {
+        let code = "const port = chrome.runtime.connectNative('com.example.host');";
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Native Host", "version": "1.0", "permissions": ["nativeMessaging"]}"#
+        ).unwrap();
+        let gecko_id = crate::transformer::manifest::ManifestTransformer::firefox_extension_id(&manifest);
+        
+        let issues = analyze_native_messaging(code, Path::new("background.js"), &manifest, &gecko_id);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        let suggestion = issues[0].suggestion.as_deref().unwrap();
+        assert!(suggestion.contains("\"native-host@converted-extension.org\""));
+        assert!(suggestion.contains("allowed_extensions"));
+    }
+    
+    #[test]
+    fn test_gcm_is_blocker() {
+        let code = r#"
+            chrome.gcm.register(['1234'], (registrationId) => {
+                console.log(registrationId);
+            });
+        "#;
+        
+        let issues = analyze_javascript_apis(code, &PathBuf::from("background.js"));
+        let blocker = issues.iter().find(|i| i.description.contains("chrome.gcm.register")).unwrap();
+        assert_eq!(blocker.severity, Severity::Blocker);
+        assert!(blocker.suggestion.as_deref().unwrap().contains("pushManager"));
+    }
+    
+    #[test]
+    fn test_system_is_blocker() {
+        let code = "chrome.system.cpu.getInfo((info) => {\n  console.log(info.numOfProcessors);\n});";
+        
+        let issues = analyze_javascript_apis(code, &PathBuf::from("background.js"));
+        let blocker = issues.iter().find(|i| i.description.contains("chrome.system.cpu.getInfo")).unwrap();
+        assert_eq!(blocker.severity, Severity::Blocker);
+        assert!(blocker.description.contains("the chrome.system namespace does not exist in Firefox"));
+        assert!(blocker.suggestion.as_deref().unwrap().contains("navigator.hardwareConcurrency"));
+    }
+    
+    #[test]
+    fn test_file_system_is_blocker() {
+        let code = "chrome.fileSystem.chooseEntry({ type: 'openFile' }, (entry) => {\n  entry.file(read);\n});";
+        
+        let issues = analyze_javascript_apis(code, &PathBuf::from("background.js"));
+        let blocker = issues.iter().find(|i| i.description.contains("chrome.fileSystem.chooseEntry")).unwrap();
+        assert_eq!(blocker.severity, Severity::Blocker);
+        assert!(blocker.suggestion.as_deref().unwrap().contains("<input type=\"file\">"));
+    }
+    
+    #[test]
+    fn test_printing_is_blocker() {
+        let code = "chrome.printing.submitJob({ job: { printerId, title: 'Receipt', ticket, contentType: 'application/pdf', document: blob } });";
+        
+        let issues = analyze_javascript_apis(code, &PathBuf::from("background.js"));
+        let blocker = issues.iter().find(|i| i.description.contains("chrome.printing.submitJob")).unwrap();
+        assert_eq!(blocker.severity, Severity::Blocker);
+        assert!(blocker.description.contains("the chrome.printing namespace"));
+        assert!(blocker.suggestion.as_deref().unwrap().contains("window.print()"));
+    }
+    
     #[test]
     fn test_detect_chrome_only_api() {
         let code = r#"
@@ -89,4 +933,368 @@ mod tests {
         
         assert!(issues.iter().any(|i| matches!(i.category, IncompatibilityCategory::ChromeOnlyApi)));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_capture_visible_tab_without_active_tab() {
+        let code = r#"
+            chrome.action.onClicked.addListener(async () => {
+                const dataUrl = await chrome.tabs.captureVisibleTab();
+            });
+        "#;
+        let path = PathBuf::from("background.js");
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["storage"]}"#
+        ).unwrap();
+        let issues = analyze_api_permissions(code, &path, &manifest);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Minor));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 3)));
+        
+        let issues = analyze_api_permissions("// capture\nchrome.tabs.captureVisibleTab();\n", &path, &manifest);
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 2)));
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["activeTab"]}"#
+        ).unwrap();
+        assert!(analyze_api_permissions(code, &path, &manifest).is_empty());
+    }
+    
+    #[test]
+    fn test_web_navigation_listener() {
+        let code = "chrome.webNavigation.onCommitted.addListener((details) => {\n  if (details.frameId !== 0) return;\n}, { url: [{ hostSuffix: 'example.com' }] });";
+        let path = PathBuf::from("background.js");
+        
+        let advisories: Vec<_> = analyze_behavior_differences(code, &path)
+            .into_iter()
+            .filter(|i| i.description.starts_with("webNavigation"))
+            .collect();
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].severity, Severity::Info);
+        assert!(advisories[0].description.contains("frameId"));
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["tabs"]}"#
+        ).unwrap();
+        let issues = analyze_api_permissions(code, &path, &manifest);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].description.contains("'webNavigation' permission"));
+        assert_eq!(issues[0].category, IncompatibilityCategory::Permissions);
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["webNavigation"]}"#
+        ).unwrap();
+        assert!(analyze_api_permissions(code, &path, &manifest).is_empty());
+    }
+    
+    #[test]
+    fn test_insert_css_requires_scripting() {
+        let code = "chrome.scripting.insertCSS({ target: { tabId }, files: ['style.css'] });";
+        let path = PathBuf::from("background.js");
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["activeTab"]}"#
+        ).unwrap();
+        let issues = analyze_api_permissions(code, &path, &manifest);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].auto_fixable);
+        
+        let transformed = crate::transformer::manifest::ManifestTransformer::new(&[])
+            .transform(&manifest, Some(&crate::models::Extension::new(
+                manifest.clone(),
+                [(PathBuf::from("background.js"), code.as_bytes().to_vec())].into_iter().collect(),
+            )))
+            .unwrap();
+        assert!(transformed.permissions.iter().any(|p| p == "scripting"));
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["scripting"]}"#
+        ).unwrap();
+        assert!(analyze_api_permissions(code, &path, &manifest).is_empty());
+    }
+    
+    #[test]
+    fn test_storage_sync_quota_advisory() {
+        let path = PathBuf::from("options.js");
+        
+        let code = "chrome.storage.sync.set({ theme: 'dark' });";
+        let issues = analyze_behavior_differences(code, &path);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Info));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("storage.local"));
+        
+        let code = format!("chrome.storage.sync.set({{ blob: '{}' }});", "x".repeat(10_000));
+        let issues = analyze_behavior_differences(&code, &path);
+        assert!(matches!(issues[0].severity, Severity::Minor));
+        assert!(issues[0].description.contains("10000 byte"));
+    }
+    
+    #[test]
+    fn test_detect_sync_xhr() {
+        let path = PathBuf::from("vendor.js");
+        let code = r#"
+            var xhr = new XMLHttpRequest();
+            xhr.open('GET', url, false);
+            var other = new XMLHttpRequest();
+            other.open("POST", buildUrl(a, b), true);
+            window.open('https://example.com', '_blank', 'noopener');
+        "#;
+        
+        let issues = analyze_behavior_differences(code, &path);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Minor));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 3)));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("fetch"));
+    }
+    
+    #[test]
+    fn test_on_installed_reason_advisory() {
+        let path = PathBuf::from("background.js");
+        let code = r#"
+            chrome.runtime.onInstalled.addListener((details) => {
+                if (details.reason === 'install') {
+                    chrome.tabs.create({ url: 'onboarding.html' });
+                }
+            });
+            chrome.runtime.setUninstallURL('https://example.com/bye');
+        "#;
+        
+        let issues = analyze_behavior_differences(code, &path);
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| matches!(i.severity, Severity::Info)));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 3)));
+        assert!(issues[0].description.contains("browser_update"));
+        assert!(issues[1].description.contains("setUninstallURL"));
+    }
+    
+    #[test]
+    fn test_service_worker_fetch_listener() {
+        let path = PathBuf::from("background.js");
+        let code = r#"
+            self.addEventListener('install', () => self.skipWaiting());
+            self.addEventListener('fetch', (event) => {
+                event.respondWith(caches.match(event.request));
+            });
+            document.addEventListener('click', () => {});
+        "#;
+        
+        let issues = analyze_service_worker_events(code, &path);
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| matches!(i.severity, Severity::Major)));
+        
+        let fetch = issues.iter().find(|i| i.description.contains("'fetch'")).unwrap();
+        assert!(matches!(fetch.location, Location::FileLocation(_, 3)));
+        assert!(fetch.suggestion.as_ref().unwrap().contains("declarativeNetRequest"));
+    }
+    
+    #[test]
+    fn test_background_set_icon_image_data() {
+        let code = r#"
+            const canvas = document.createElement('canvas');
+            const ctx = canvas.getContext('2d');
+            chrome.action.setIcon({ imageData: ctx.getImageData(0, 0, 16, 16) });
+            chrome.action.setIcon({ path: 'icons/active.png' });
+        "#;
+        
+        let issues = analyze_background_set_icon(code, Path::new("background.js"));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 4)));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("OffscreenCanvas"));
+    }
+    
+    #[test]
+    fn test_get_permission_level_advisory() {
+        let code = "chrome.notifications.getPermissionLevel((level) => {\n  if (level === 'granted') notify();\n});";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.contains("getPermissionLevel"))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 1)));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("'granted'"));
+    }
+    
+    #[test]
+    fn test_request_update_check_advisory() {
+        let code = "chrome.runtime.requestUpdateCheck((status) => {\n  if (status === 'update_available') chrome.runtime.reload();\n});";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.contains("runtime."))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Minor);
+        assert!(issues[0].description.contains("requestUpdateCheck"));
+    }
+    
+    #[test]
+    fn test_get_platform_info_advisory() {
+        let code = "chrome.runtime.getPlatformInfo((info) => {\n  if (info.arch === 'arm64') useNativeArm();\n});";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.contains("getPlatformInfo"))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Info);
+        assert!(issues[0].description.contains("'aarch64'"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 1)));
+    }
+    
+    #[test]
+    fn test_execute_script_func_closure() {
+        let code = r#"const highlightColor = settings.color;
+function highlight(selector) {
+  const nodes = document.querySelectorAll(selector);
+  nodes.forEach((node) => { node.style.background = highlightColor; });
+}
+function injectTitle() {
+  return document.title;
+}
+chrome.scripting.executeScript({ target: { tabId }, func: highlight, args: ['p'] });
+chrome.scripting.executeScript({ target: { tabId }, func: injectTitle });
+chrome.scripting.executeScript({ target: { tabId }, func: (color) => { document.body.style.color = color; }, args: [highlightColor] });"#;
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.starts_with("scripting.executeScript func"))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Minor);
+        assert!(issues[0].description.contains("func highlight references extension-scope variable (highlightColor)"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 9)));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("args"));
+    }
+    
+    #[test]
+    fn test_execute_script_func_inner_parameters_are_local() {
+        let code = r#"let node = null;
+const item = 'x';
+function mark() {
+  document.querySelectorAll('li').forEach((node, index = 0) => { node.dataset.index = index; });
+  [].map(item => item.id);
+}
+chrome.scripting.executeScript({ target: { tabId }, func: mark });"#;
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.starts_with("scripting.executeScript func"))
+            .collect();
+        
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+    
+    #[test]
+    fn test_get_profile_user_info_advisory() {
+        let code = "chrome.identity.getProfileUserInfo({ accountStatus: 'ANY' }, (info) => {\n  showEmail(info.email);\n});";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("popup.js"))
+            .into_iter()
+            .filter(|i| i.description.starts_with("identity."))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("launchWebAuthFlow"));
+    }
+    
+    #[test]
+    fn test_sub_minute_alarms() {
+        let code = "chrome.alarms.create('poll', { periodInMinutes: 0.5 });\nchrome.alarms.create('daily', { periodInMinutes: 1440 });\nchrome.alarms.create({ delayInMinutes: settings.delay });";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js"))
+            .into_iter()
+            .filter(|i| i.description.starts_with("alarms.create"))
+            .collect();
+        
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| i.severity == Severity::Minor));
+        assert!(issues[0].description.contains("periodInMinutes: 0.5"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 1)));
+        assert!(issues[1].description.contains("computed (settings.delay)"));
+    }
+    
+    #[test]
+    fn test_hardcoded_extension_id() {
+        let path = PathBuf::from("background.js");
+        let code = r#"
+            const CHROME_ID = 'abcdefghijklmnopabcdefghijklmnop';
+            if (chrome.runtime.id === "kbfnbcaeplbcioakkpcpgfkobkghlhen") {
+                console.log('production build');
+            }
+            const hash = 'a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6';
+        "#;
+        
+        let issues = analyze_hardcoded_extension_ids(code, &path);
+        assert_eq!(issues.len(), 2);
+        assert!(issues.iter().all(|i| matches!(i.severity, Severity::Minor)));
+        assert!(matches!(issues[1].location, Location::FileLocation(_, 3)));
+        assert!(issues[1].description.contains("runtime.id"));
+        
+        let issues = analyze_hardcoded_extension_ids("const a = 1;\n\n'kbfnbcaeplbcioakkpcpgfkobkghlhen';\n", &path);
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 3)));
+    }
+    
+    #[test]
+    fn test_proxy_settings_flagged() {
+        let path = PathBuf::from("background.js");
+        let code = r#"
+            chrome.proxy.settings.set({
+                value: { mode: 'fixed_servers', rules: { singleProxy: { host: '127.0.0.1', port: 8080 } } },
+                scope: 'regular'
+            });
+        "#;
+        
+        let issues = analyze_behavior_differences(code, &path);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Major));
+        assert!(issues[0].description.contains("proxyType"));
+    }
+    
+    #[test]
+    fn test_user_agent_sniffing() {
+        let path = PathBuf::from("popup.js");
+        let code = r#"
+            const isChrome = navigator.userAgent.includes('Chrome');
+            if (navigator.vendor === 'Google Inc.') { useChromeFlow(); }
+        "#;
+        
+        let issues = analyze_behavior_differences(code, &path);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Info));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 2)));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("getBrowserInfo"));
+    }
+    
+    #[test]
+    fn test_tabs_send_message_advisory() {
+        let code = "chrome.tabs.sendMessage(tab.id, { type: 'scan' }, (response) => render(response));";
+        let issues: Vec<_> = analyze_behavior_differences(code, Path::new("background.js")).into_iter()
+            .filter(|i| i.description.starts_with("tabs.sendMessage"))
+            .collect();
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Info);
+        assert!(issues[0].description.contains("frameId"));
+        let suggestion = issues[0].suggestion.as_deref().unwrap();
+        assert!(suggestion.contains("return true"));
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        let files = [(PathBuf::from("background.js"), code.as_bytes().to_vec())].into_iter().collect();
+        let receivers = analyze_message_receivers(&Extension::new(manifest, files));
+        assert_eq!(receivers.len(), 1);
+        assert!(receivers[0].description.contains("Receiving end does not exist"));
+    }
+    
+    #[test]
+    fn test_context_menus_differences() {
+        let code = "chrome.contextMenus.create({ id: 'x', title: 'X', contexts: ['page', 'launcher'] });\nchrome.contextMenus.onClicked.addListener((info, tab) => open(info.pageUrl, tab.id));";
+        let issues = analyze_behavior_differences(code, Path::new("background.js"));
+        
+        assert!(issues.iter().any(|i| i.severity == Severity::Info && i.description.contains("menus.onClicked")));
+        let launcher: Vec<_> = issues.iter().filter(|i| i.description.contains("'launcher'")).collect();
+        assert_eq!(launcher.len(), 1);
+        assert_eq!(launcher[0].severity, Severity::Major);
+    }
+}
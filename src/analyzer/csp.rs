@@ -0,0 +1,208 @@
+//! Content Security Policy checks for Firefox extension pages
+
+use crate::models::{
+    ContentSecurityPolicy, Incompatibility, IncompatibilityCategory, Location, Manifest, Severity,
+};
+
+/// Directives Firefox ignores in extension page policies, with the reason
+const IGNORED_DIRECTIVES: &[(&str, &str)] = &[
+    ("report-uri", "Firefox does not send CSP violation reports for extension pages"),
+    ("report-to", "Firefox does not send CSP violation reports for extension pages"),
+    ("plugin-types", "plugin-types was removed from CSP and is ignored"),
+    ("block-all-mixed-content", "block-all-mixed-content is deprecated and ignored"),
+    ("prefetch-src", "prefetch-src is not supported by Firefox"),
+];
+
+/// Directives whose remote hosts are fetched with the extension's host permissions
+const FETCH_DIRECTIVES: &[&str] = &["connect-src", "img-src"];
+
+/// Check the effective `extension_pages` policy for Firefox compatibility
+///
+/// Flags directives Firefox ignores, sources it rejects in Manifest V3
+/// script policies, and remote `connect-src`/`img-src` hosts that no host
+/// permission covers.
+pub fn analyze_csp(manifest: &Manifest) -> Vec<Incompatibility> {
+    let policy = match &manifest.content_security_policy {
+        Some(ContentSecurityPolicy::V2(policy)) => policy.as_str(),
+        Some(ContentSecurityPolicy::V3(csp)) => match csp.extension_pages.as_deref() {
+            Some(policy) => policy,
+            None => return Vec::new(),
+        },
+        None => return Vec::new(),
+    };
+    
+    let location = || Location::ManifestField("content_security_policy.extension_pages".to_string());
+    let mut issues = Vec::new();
+    
+    for (name, sources) in parse_policy(policy) {
+        if let Some((_, reason)) = IGNORED_DIRECTIVES.iter().find(|(directive, _)| *directive == name) {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::ContentSecurityPolicy,
+                    location(),
+                    format!("CSP directive '{}' has no effect in Firefox: {}", name, reason)
+                )
+                .with_suggestion(format!("Remove '{}' from the extension_pages policy", name))
+            );
+            continue;
+        }
+        
+        if name == "script-src" || name == "default-src" {
+            for source in sources.iter().filter(|s| is_rejected_script_source(s)) {
+                issues.push(
+                    Incompatibility::new(
+                        Severity::Major,
+                        IncompatibilityCategory::ContentSecurityPolicy,
+                        location(),
+                        format!("Firefox rejects {} in the Manifest V3 {} policy", source, name)
+                    )
+                    .with_suggestion("Bundle remote scripts with the extension and remove unsafe sources; only 'self', 'wasm-unsafe-eval' and localhost are allowed")
+                );
+            }
+        }
+        
+        if FETCH_DIRECTIVES.contains(&name) {
+            for host in sources.iter().filter_map(|s| remote_host(s)) {
+                if !has_host_permission(manifest, host) {
+                    issues.push(
+                        Incompatibility::new(
+                            Severity::Minor,
+                            IncompatibilityCategory::HostPermissions,
+                            location(),
+                            format!("{} allows {} but no host permission covers it", name, host)
+                        )
+                        .with_suggestion(format!("Add a host permission such as \"*://{}/*\" so cross-origin requests from extension pages succeed in Firefox", host))
+                    );
+                }
+            }
+        }
+    }
+    
+    issues
+}
+
+/// Firefox's `extension_pages` policy when the manifest doesn't set one
+pub const DEFAULT_EXTENSION_PAGES_POLICY: &str = "script-src 'self'; upgrade-insecure-requests;";
+
+/// The `extension_pages` policy Firefox enforces for this manifest
+pub fn effective_extension_pages_policy(manifest: &Manifest) -> &str {
+    match &manifest.content_security_policy {
+        Some(ContentSecurityPolicy::V2(policy)) => policy,
+        Some(ContentSecurityPolicy::V3(csp)) => csp.extension_pages.as_deref().unwrap_or(DEFAULT_EXTENSION_PAGES_POLICY),
+        None => DEFAULT_EXTENSION_PAGES_POLICY,
+    }
+}
+
+/// Whether `policy` lets `directive` load `source`, following CSP fallbacks
+/// (`worker-src` → `child-src` → `script-src` → `default-src`)
+pub fn policy_allows(policy: &str, directive: &str, source: &str) -> bool {
+    let fallbacks: Vec<&str> = match directive {
+        "worker-src" => vec!["worker-src", "child-src", "script-src", "default-src"],
+        other => vec![other, "default-src"],
+    };
+    let directives = parse_policy(policy);
+    match fallbacks.iter().find_map(|name| directives.iter().find(|(d, _)| d == name)) {
+        Some((_, sources)) => sources.contains(&source),
+        None => true,
+    }
+}
+
+/// Split a policy into `(directive, sources)` pairs
+fn parse_policy(policy: &str) -> Vec<(&str, Vec<&str>)> {
+    policy.split(';')
+        .filter_map(|directive| {
+            let mut tokens = directive.split_whitespace();
+            let name = tokens.next()?;
+            Some((name, tokens.collect()))
+        })
+        .collect()
+}
+
+fn is_rejected_script_source(source: &str) -> bool {
+    match source {
+        "'unsafe-eval'" | "'unsafe-inline'" => true,
+        _ => remote_host(source).is_some_and(|host| !is_localhost(host)),
+    }
+}
+
+/// Host of a source expression naming a remote origin (`https://api.example.com`, `*.cdn.com`)
+fn remote_host(source: &str) -> Option<&str> {
+    if source.starts_with('\'') || source.ends_with(':') || source == "*" {
+        return None;
+    }
+    
+    let rest = match source.split_once("://") {
+        Some((_, rest)) => rest,
+        None => source,
+    };
+    let host = rest.split(['/', ':']).next().unwrap_or(rest);
+    if host.is_empty() || (!host.contains('.') && !is_localhost(host)) {
+        return None;
+    }
+    Some(host)
+}
+
+fn is_localhost(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1")
+}
+
+fn has_host_permission(manifest: &Manifest, host: &str) -> bool {
+    let host = host.trim_start_matches("*.");
+    manifest.host_permissions.iter()
+        .chain(manifest.permissions.iter())
+        .any(|pattern| {
+            if pattern == "<all_urls>" {
+                return true;
+            }
+            let Some((_, rest)) = pattern.split_once("://") else { return false };
+            let pattern_host = rest.split('/').next().unwrap_or(rest);
+            if pattern_host == "*" {
+                return true;
+            }
+            match pattern_host.strip_prefix("*.") {
+                Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+                None => host == pattern_host,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn manifest_with_csp(policy: &str, host_permissions: &[&str]) -> Manifest {
+        let json = serde_json::json!({
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "host_permissions": host_permissions,
+            "content_security_policy": { "extension_pages": policy }
+        });
+        crate::parser::manifest::parse_manifest(json.to_string().as_bytes()).unwrap()
+    }
+    
+    #[test]
+    fn test_report_uri_advisory() {
+        let manifest = manifest_with_csp("script-src 'self'; object-src 'self'; report-uri https://example.com/csp", &[]);
+        let issues = analyze_csp(&manifest);
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Minor);
+        assert!(issues[0].description.contains("'report-uri'"));
+    }
+    
+    #[test]
+    fn test_connect_src_hosts_need_permissions() {
+        let policy = "script-src 'self' https://cdn.example.com; connect-src 'self' https://api.example.com wss://live.other.org";
+        let manifest = manifest_with_csp(policy, &["*://*.example.com/*"]);
+        let issues = analyze_csp(&manifest);
+        
+        assert!(issues.iter().any(|i| i.severity == Severity::Major && i.description.contains("https://cdn.example.com")));
+        let uncovered: Vec<_> = issues.iter()
+            .filter(|i| i.category == IncompatibilityCategory::HostPermissions)
+            .collect();
+        assert_eq!(uncovered.len(), 1);
+        assert!(uncovered[0].description.contains("live.other.org"));
+    }
+}
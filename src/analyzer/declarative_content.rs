@@ -46,21 +46,24 @@ impl DeclarativeContentAnalyzer {
             let url_filter = UrlFilter {
                 host_equals: self.extract_string_value(code, "hostEquals"),
                 host_contains: self.extract_string_value(code, "hostContains"),
-                host_prefix: None,
-                host_suffix: None,
-                path_equals: None,
-                path_contains: None,
-                path_prefix: None,
-                path_suffix: None,
-                query_equals: None,
-                query_contains: None,
-                query_prefix: None,
-                query_suffix: None,
+                host_prefix: self.extract_string_value(code, "hostPrefix"),
+                host_suffix: self.extract_string_value(code, "hostSuffix"),
+                path_equals: self.extract_string_value(code, "pathEquals"),
+                path_contains: self.extract_string_value(code, "pathContains"),
+                path_prefix: self.extract_string_value(code, "pathPrefix"),
+                path_suffix: self.extract_string_value(code, "pathSuffix"),
+                query_equals: self.extract_string_value(code, "queryEquals"),
+                query_contains: self.extract_string_value(code, "queryContains"),
+                query_prefix: self.extract_string_value(code, "queryPrefix"),
+                query_suffix: self.extract_string_value(code, "querySuffix"),
                 url_matches: self.extract_string_value(code, "urlMatches"),
-                schemes: None,
+                schemes: self.extract_string_array(code, "schemes"),
+                ..Default::default()
             };
             
-            let css = self.extract_css_selectors(code);
+            // RequestContentScript's own `css` list isn't a selector condition
+            let matcher_code = code.find("RequestContentScript").map_or(code, |index| &code[..index]);
+            let css = self.extract_css_selectors(matcher_code);
             
             conditions.push(PageCondition::PageStateMatcher {
                 page_url: url_filter,
@@ -85,8 +88,31 @@ impl DeclarativeContentAnalyzer {
             }
         }
         
+        if let Some(start) = code.find("RequestContentScript") {
+            let request = &code[start..];
+            let js = self.extract_string_array(request, "js").unwrap_or_default();
+            let css = self.extract_string_array(request, "css").unwrap_or_default();
+            if !js.is_empty() || !css.is_empty() {
+                actions.push(PageAction::RequestContentScript { js, css });
+            }
+        }
+        
         actions
     }
+    
+    /// String literals of the first `key: [...]` array in `code`
+    fn extract_string_array(&self, code: &str, key: &str) -> Option<Vec<String>> {
+        let start = code.find(&format!("{key}:"))?;
+        let after_key = code[start + key.len() + 1..].trim_start();
+        let content = after_key.strip_prefix('[')?;
+        let end = content.find(']')?;
+        let values: Vec<String> = content[..end]
+            .split(',')
+            .map(|s| s.trim().trim_matches(|c| c == '\'' || c == '"').to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        Some(values)
+    }
 
     fn extract_string_value(&self, code: &str, key: &str) -> Option<String> {
         if let Some(start) = code.find(&format!("{key}:")) {
@@ -0,0 +1,249 @@
+//! declarativeNetRequest rule limit checks
+
+use crate::models::{Extension, Incompatibility, IncompatibilityCategory, Location, Severity};
+use crate::parser::javascript::{find_calls, find_matching_delimiter, split_top_level};
+use std::path::PathBuf;
+
+/// Firefox's guaranteed minimum of rules across enabled static rulesets
+pub const MAX_STATIC_RULES: usize = 30_000;
+
+/// Firefox's limit on dynamic plus session rules
+pub const MAX_DYNAMIC_AND_SESSION_RULES: usize = 5_000;
+
+/// Share of a limit at which a warning is raised
+const WARN_RATIO: f64 = 0.8;
+
+/// Calls whose `addRules` add dynamic or session rules
+const UPDATE_RULES_CALL: &str = r"\b(?:chrome|browser)\.declarativeNetRequest\.update(?:Dynamic|Session)Rules";
+
+/// Compare static ruleset sizes and literal `addRules` arrays against Firefox's limits
+pub fn analyze_dnr_rule_limits(extension: &Extension) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    
+    let static_rules = count_static_rules(extension);
+    if let Some(severity) = limit_severity(static_rules, MAX_STATIC_RULES) {
+        issues.push(
+            Incompatibility::new(
+                severity,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::ManifestField("declarative_net_request.rule_resources".to_string()),
+                format!(
+                    "Enabled static DNR rulesets contain {} rules; Firefox guarantees only {}",
+                    static_rules, MAX_STATIC_RULES
+                )
+            )
+            .with_suggestion("Disable rarely used rulesets by default or merge redundant rules")
+        );
+    }
+    
+    let mut dynamic_rules = 0;
+    let mut first_call = None;
+    for js_path in extension.get_javascript_files() {
+        let Some(content) = extension.get_file_content(&js_path) else { continue };
+        
+        for call in find_calls(&content, UPDATE_RULES_CALL) {
+            let added = count_add_rules(call.args(&content));
+            if added > 0 {
+                dynamic_rules += added;
+                first_call.get_or_insert_with(|| (js_path.clone(), call.line));
+            }
+        }
+    }
+    
+    if let (Some(severity), Some((path, line))) = (limit_severity(dynamic_rules, MAX_DYNAMIC_AND_SESSION_RULES), first_call) {
+        issues.push(
+            Incompatibility::new(
+                severity,
+                IncompatibilityCategory::BehaviorDifference,
+                Location::FileLocation(path, line),
+                format!(
+                    "Literal addRules arrays add {} dynamic/session DNR rules; Firefox allows {} in total",
+                    dynamic_rules, MAX_DYNAMIC_AND_SESSION_RULES
+                )
+            )
+            .with_suggestion("Move stable rules into static rulesets and keep runtime rules to a minimum")
+        );
+    }
+    
+    issues
+}
+
+/// `Major` above the limit, `Minor` when approaching it
+fn limit_severity(count: usize, limit: usize) -> Option<Severity> {
+    if count > limit {
+        Some(Severity::Major)
+    } else if count as f64 >= limit as f64 * WARN_RATIO {
+        Some(Severity::Minor)
+    } else {
+        None
+    }
+}
+
+/// Number of rules in the enabled rulesets listed by the manifest
+fn count_static_rules(extension: &Extension) -> usize {
+    static_rulesets(extension, true).iter().map(|rules| rules.len()).sum()
+}
+
+/// Rules of each static ruleset listed by the manifest, optionally only enabled ones
+fn static_rulesets(extension: &Extension, enabled_only: bool) -> Vec<Vec<serde_json::Value>> {
+    let Some(resources) = extension.manifest.extra.get("declarative_net_request")
+        .and_then(|dnr| dnr.get("rule_resources"))
+        .and_then(|r| r.as_array())
+    else {
+        return Vec::new();
+    };
+    
+    resources.iter()
+        .filter(|resource| !enabled_only || resource.get("enabled").and_then(|e| e.as_bool()) != Some(false))
+        .filter_map(|resource| resource.get("path").and_then(|p| p.as_str()))
+        .filter_map(|path| extension.files.get(&PathBuf::from(path.trim_start_matches('/'))))
+        .filter_map(|bytes| serde_json::from_slice::<serde_json::Value>(bytes).ok())
+        .filter_map(|rules| rules.as_array().cloned())
+        .collect()
+}
+
+/// Extension pages that redirect rules send requests to (`redirect.extensionPath`)
+///
+/// Covers static rulesets, including disabled ones since they can be enabled
+/// at runtime, and rules added from literal `addRules` arrays. Paths are
+/// relative to the extension root, sorted and deduplicated.
+pub fn redirect_extension_paths(extension: &Extension) -> Vec<String> {
+    let mut paths: Vec<String> = static_rulesets(extension, false).into_iter()
+        .flatten()
+        .chain(inline_rules(extension))
+        .filter_map(|rule| rule.pointer("/action/redirect/extensionPath")?.as_str().map(str::to_string))
+        .map(|path| path.split(['?', '#']).next().unwrap_or_default().trim_start_matches('/').to_string())
+        .filter(|path| !path.is_empty())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Rules passed as object literals in `update{Dynamic,Session}Rules({ addRules: [...] })`
+///
+/// Elements that aren't plain literals (variables, computed values) are skipped.
+pub fn inline_rules(extension: &Extension) -> Vec<serde_json::Value> {
+    let mut rules = Vec::new();
+    for js_path in extension.get_javascript_files() {
+        let Some(content) = extension.get_file_content(&js_path) else { continue };
+        
+        for call in find_calls(&content, UPDATE_RULES_CALL) {
+            rules.extend(
+                add_rules_elements(call.args(&content))
+                    .into_iter()
+                    .filter_map(|element| json5::from_str::<serde_json::Value>(&element).ok())
+                    .filter(|rule| rule.is_object())
+            );
+        }
+    }
+    rules
+}
+
+/// Number of elements in a literal `addRules: [...]` array within call arguments
+fn count_add_rules(args: &str) -> usize {
+    add_rules_elements(args).len()
+}
+
+/// Source of each element of a literal `addRules: [...]` array within call arguments
+fn add_rules_elements(args: &str) -> Vec<String> {
+    let Some(key) = args.find("addRules") else { return Vec::new() };
+    let Some(open) = args[key..].find(['[', ',', '}']).map(|i| key + i) else { return Vec::new() };
+    if args.as_bytes()[open] != b'[' {
+        return Vec::new(); // addRules refers to a variable
+    }
+    let Some(close) = find_matching_delimiter(args, open) else { return Vec::new() };
+    
+    split_top_level(&args[open + 1..close], ',')
+        .into_iter()
+        .filter(|element| !element.is_empty())
+        .map(|element| element.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn extension_with(manifest: &str, files: Vec<(&str, String)>) -> Extension {
+        let manifest = crate::parser::manifest::parse_manifest(manifest.as_bytes()).unwrap();
+        Extension::new(
+            manifest,
+            files.into_iter().map(|(p, c)| (PathBuf::from(p), c.into_bytes())).collect(),
+        )
+    }
+    
+    #[test]
+    fn test_static_rules_over_limit() {
+        let rule = r#"{"id": 1, "priority": 1, "action": {"type": "block"}, "condition": {"urlFilter": "ads"}}"#;
+        let rules = format!("[{}]", vec![rule; MAX_STATIC_RULES + 1].join(","));
+        let extension = extension_with(
+            r#"{"manifest_version": 3, "name": "Blocker", "version": "1.0",
+                "declarative_net_request": {"rule_resources": [
+                    {"id": "main", "enabled": true, "path": "rules/main.json"},
+                    {"id": "extra", "enabled": false, "path": "rules/extra.json"}
+                ]}}"#,
+            vec![("rules/main.json", rules.clone()), ("rules/extra.json", rules)],
+        );
+        
+        let issues = analyze_dnr_rule_limits(&extension);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Major));
+        assert!(issues[0].description.contains("30001 rules"));
+    }
+    
+    #[test]
+    fn test_literal_add_rules_approaching_limit() {
+        let rules = vec!["{ id: 1, action: { type: 'block' }, condition: { urlFilter: 'a,b' } }"; 4_500].join(",\n");
+        let code = format!("chrome.declarativeNetRequest.updateDynamicRules({{ removeRuleIds: [1], addRules: [{},] }});", rules);
+        let extension = extension_with(
+            r#"{"manifest_version": 3, "name": "Blocker", "version": "1.0"}"#,
+            vec![("background.js", code)],
+        );
+        
+        let issues = analyze_dnr_rule_limits(&extension);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Minor));
+        assert!(issues[0].description.contains("4500"));
+    }
+    
+    #[test]
+    fn test_redirect_extension_paths() {
+        let extension = extension_with(
+            r#"{"manifest_version": 3, "name": "Blocker", "version": "1.0",
+                "declarative_net_request": {"rule_resources": [
+                    {"id": "main", "enabled": false, "path": "rules.json"}
+                ]}}"#,
+            vec![("rules.json", r#"[
+                {"id": 1, "action": {"type": "redirect", "redirect": {"extensionPath": "/blocked.html?reason=ads"}}, "condition": {"urlFilter": "ads"}},
+                {"id": 2, "action": {"type": "redirect", "redirect": {"url": "https://example.com"}}, "condition": {"urlFilter": "x"}},
+                {"id": 3, "action": {"type": "redirect", "redirect": {"extensionPath": "/blocked.html"}}, "condition": {"urlFilter": "y"}}
+            ]"#.to_string())],
+        );
+        
+        assert_eq!(redirect_extension_paths(&extension), vec!["blocked.html"]);
+    }
+    
+    #[test]
+    fn test_inline_add_rules_analyzed() {
+        let code = r#"chrome.runtime.onInstalled.addListener(() => {
+  chrome.declarativeNetRequest.updateDynamicRules({
+    removeRuleIds: [1, 2],
+    addRules: [
+      { id: 1, priority: 1, action: { type: 'redirect', redirect: { extensionPath: '/blocked.html' } }, condition: { urlFilter: '||ads.example.com' } },
+      { id: 2, priority: 1, action: { type: 'block' }, condition: { regexFilter: '^https://track\\.example\\.com/', resourceTypes: ['script'], }, },
+      extraRule,
+    ],
+  });
+});"#;
+        let extension = extension_with(
+            r#"{"manifest_version": 3, "name": "Blocker", "version": "1.0"}"#,
+            vec![("background.js", code.to_string()), ("blocked.html", String::new())],
+        );
+        
+        let rules = inline_rules(&extension);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1]["condition"]["regexFilter"], "^https://track\\.example\\.com/");
+        assert_eq!(redirect_extension_paths(&extension), vec!["blocked.html"]);
+    }
+}
@@ -0,0 +1,73 @@
+//! Extension API calls in HTML inline event-handler attributes
+
+use crate::models::{Extension, Incompatibility, IncompatibilityCategory, Location, Severity};
+use crate::utils::helpers::line_of;
+use regex::Regex;
+
+/// Flag `on*="…"` attributes in extension HTML that call `chrome.*`/`browser.*`
+///
+/// The MV3 content security policy blocks inline handlers, so the calls never
+/// run in Chrome or Firefox, and they aren't seen by the JavaScript passes.
+pub fn analyze_inline_handlers(extension: &Extension) -> Vec<Incompatibility> {
+    let handler_re = Regex::new(r#"(?i)\s(on[a-z]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    let api_re = Regex::new(r"\b(?:chrome|browser)\.[A-Za-z]+(?:\.[A-Za-z]+)*").unwrap();
+
+    let mut paths: Vec<_> = extension.files.keys()
+        .filter(|path| {
+            let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+            matches!(ext.as_deref(), Some("html" | "htm"))
+        })
+        .cloned()
+        .collect();
+    paths.sort();
+
+    let mut issues = Vec::new();
+    for path in paths {
+        let Some(content) = extension.get_file_content(&path) else { continue };
+
+        for caps in handler_re.captures_iter(&content) {
+            let value = caps.get(2).or_else(|| caps.get(3)).map_or("", |m| m.as_str());
+            let Some(api) = api_re.find(value) else { continue };
+            let line = line_of(&content, caps.get(0).unwrap().start());
+
+            issues.push(
+                Incompatibility::new(
+                    Severity::Major,
+                    IncompatibilityCategory::ContentSecurityPolicy,
+                    Location::FileLocation(path.clone(), line),
+                    format!(
+                        "Inline {} handler calls {}; MV3's content security policy blocks inline event handlers, so it never runs",
+                        &caps[1], api.as_str()
+                    )
+                )
+                .with_suggestion(format!(
+                    "Remove the {} attribute and register the handler with addEventListener() in an external script loaded by this page",
+                    &caps[1]
+                ))
+            );
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_inline_onclick_calling_chrome_api() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "action": {"default_popup": "popup.html"}}"#
+        ).unwrap();
+        let popup = "<html>\n<body>\n  <button onclick=\"chrome.runtime.reload()\">Reload</button>\n  <button onclick=\"toggle()\">Toggle</button>\n</body>\n</html>\n";
+        let files = [(PathBuf::from("popup.html"), popup.as_bytes().to_vec())].into_iter().collect();
+
+        let issues = analyze_inline_handlers(&Extension::new(manifest, files));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 3)));
+        assert!(issues[0].description.contains("onclick handler calls chrome.runtime.reload"));
+        assert!(issues[0].suggestion.as_ref().unwrap().contains("addEventListener"));
+    }
+}
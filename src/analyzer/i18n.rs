@@ -0,0 +1,119 @@
+//! `__MSG_name__` placeholder checks for CSS and HTML files
+
+use crate::models::{Extension, Incompatibility, IncompatibilityCategory, Location, Severity};
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Predefined messages both browsers provide
+const PREDEFINED_MESSAGES: &[&str] = &[
+    "@@extension_id",
+    "@@ui_locale",
+    "@@bidi_dir",
+    "@@bidi_reversed_dir",
+    "@@bidi_start_edge",
+    "@@bidi_end_edge",
+];
+
+/// Check that `__MSG_*__` placeholders in CSS/HTML resolve against the default locale
+///
+/// Message names are case-insensitive. `__MSG_@@extension_id__` resolves to
+/// Firefox's per-install internal UUID rather than a fixed ID, so it is
+/// flagged even though it resolves.
+pub fn analyze_message_placeholders(extension: &Extension) -> Vec<Incompatibility> {
+    let placeholder_re = Regex::new(r"__MSG_(@@\w+?|[A-Za-z0-9_]+?)__").unwrap();
+    let known: HashSet<String> = extension.default_locale_messages()
+        .map(|messages| messages.keys().map(|k| k.to_ascii_lowercase()).collect())
+        .unwrap_or_default();
+    
+    let mut paths: Vec<&PathBuf> = extension.files.keys()
+        .filter(|path| {
+            let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+            matches!(ext.as_deref(), Some("css" | "html" | "htm"))
+        })
+        .collect();
+    paths.sort();
+    
+    let mut issues = Vec::new();
+    for path in paths {
+        let Some(content) = extension.get_file_content(path) else { continue };
+        let mut seen = HashSet::new();
+        
+        for (index, line) in content.lines().enumerate() {
+            for caps in placeholder_re.captures_iter(line) {
+                let key = &caps[1];
+                if !seen.insert(key.to_ascii_lowercase()) {
+                    continue;
+                }
+                let location = Location::FileLocation(path.clone(), index + 1);
+                
+                if key == "@@extension_id" {
+                    issues.push(
+                        Incompatibility::new(
+                            Severity::Minor,
+                            IncompatibilityCategory::ExtensionId,
+                            location,
+                            "__MSG_@@extension_id__ resolves to Firefox's per-install internal UUID, not a stable extension ID"
+                        )
+                        .with_suggestion("Use relative URLs in CSS instead of building moz-extension:// URLs from the extension ID")
+                    );
+                } else if key.starts_with("@@") {
+                    if !PREDEFINED_MESSAGES.contains(&key) {
+                        issues.push(
+                            Incompatibility::new(
+                                Severity::Minor,
+                                IncompatibilityCategory::BehaviorDifference,
+                                location,
+                                format!("Unknown predefined message __MSG_{}__", key)
+                            )
+                            .with_suggestion(format!("Supported predefined messages are {}", PREDEFINED_MESSAGES.join(", ")))
+                        );
+                    }
+                } else if !known.contains(&key.to_ascii_lowercase()) {
+                    issues.push(
+                        Incompatibility::new(
+                            Severity::Minor,
+                            IncompatibilityCategory::BehaviorDifference,
+                            location,
+                            format!("__MSG_{}__ is not defined in the default locale's messages.json", key)
+                        )
+                        .with_suggestion(format!("Add \"{}\" to _locales/{}/messages.json", key, extension.default_locale().unwrap_or("<default_locale>")))
+                    );
+                }
+            }
+        }
+    }
+    
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_placeholder_resolution() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "default_locale": "en"
+        }"#).unwrap();
+        let files = [
+            (
+                PathBuf::from("_locales/en/messages.json"),
+                br#"{"popupTitle": {"message": "Hello"}}"#.to_vec(),
+            ),
+            (
+                PathBuf::from("popup.css"),
+                b"h1::before { content: '__MSG_popuptitle__'; }\nh2::before { content: '__MSG_missing_key__'; }\n".to_vec(),
+            ),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest, files);
+        
+        let issues = analyze_message_placeholders(&extension);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].description.contains("__MSG_missing_key__"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 2)));
+    }
+}
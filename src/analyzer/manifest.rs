@@ -2,9 +2,12 @@
 
 use crate::models::{
     Manifest, Incompatibility, Severity, IncompatibilityCategory, Location,
-    WebAccessibleResources, ContentSecurityPolicy,
+    WebAccessibleResources, ContentSecurityPolicy, Extension,
 };
 
+/// Maximum length AMO accepts for `name` and `short_name`
+pub const MAX_NAME_LENGTH: usize = 45;
+
 pub fn analyze_manifest(manifest: &Manifest) -> Vec<Incompatibility> {
     let mut issues = Vec::new();
     
@@ -21,6 +24,20 @@ pub fn analyze_manifest(manifest: &Manifest) -> Vec<Incompatibility> {
         return issues;
     }
     
+    // Check version format
+    if let Some(normalized) = crate::utils::helpers::normalize_firefox_version(&manifest.version) {
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::VersionFormat,
+                Location::ManifestField("version".to_string()),
+                format!("Version '{}' is not a valid Firefox version (1-4 integers without leading zeros)", manifest.version)
+            )
+            .with_suggestion(format!("Will use version '{}' and keep the original as version_name", normalized))
+            .auto_fixable()
+        );
+    }
+    
     // Check for browser_specific_settings
     if manifest.browser_specific_settings.is_none() {
         issues.push(
@@ -86,6 +103,39 @@ pub fn analyze_manifest(manifest: &Manifest) -> Vec<Incompatibility> {
         }
     }
     
+    // Check content script match patterns
+    let has_active_tab = manifest.permissions.iter().any(|p| p == "activeTab");
+    for (index, content_script) in manifest.content_scripts.iter().enumerate() {
+        let patterns = content_script.matches.iter().map(|p| ("matches", p))
+            .chain(content_script.exclude_matches.iter().map(|p| ("exclude_matches", p)));
+        
+        for (field, pattern) in patterns {
+            if !crate::utils::helpers::is_valid_match_pattern(pattern) {
+                issues.push(
+                    Incompatibility::new(
+                        Severity::Major,
+                        IncompatibilityCategory::ManifestStructure,
+                        Location::ManifestField(format!("content_scripts[{}].{}", index, field)),
+                        format!("Invalid match pattern '{}': Firefox will refuse to load the content script", pattern)
+                    )
+                    .with_suggestion("Use <all_urls> or scheme://host/path, e.g. *://*.example.com/* (wildcards only at the start of the host)")
+                );
+            }
+        }
+        
+        if has_active_tab && content_script.matches.iter().any(|p| is_all_urls_pattern(p)) {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::HostPermissions,
+                    Location::ManifestField(format!("content_scripts[{}].matches", index)),
+                    "Content script matches all URLs although the extension declares activeTab; Firefox asks users to grant access to every site"
+                )
+                .with_suggestion("If injection is meant to follow activeTab, inject with scripting.executeScript on user action instead; all_frames is left as declared")
+            );
+        }
+    }
+    
     // Check CSP format
     if let Some(ContentSecurityPolicy::V2(_)) = &manifest.content_security_policy {
         issues.push(
@@ -100,6 +150,21 @@ pub fn analyze_manifest(manifest: &Manifest) -> Vec<Incompatibility> {
         );
     }
     
+    // Check omnibox keyword
+    if let Some(omnibox) = &manifest.omnibox {
+        if let Some(problem) = omnibox_keyword_problem(&omnibox.keyword) {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Minor,
+                    IncompatibilityCategory::BehaviorDifference,
+                    Location::ManifestField("omnibox.keyword".to_string()),
+                    format!("Omnibox keyword '{}' {}", omnibox.keyword, problem)
+                )
+                .with_suggestion("Use a short word of two or more characters without spaces or a leading '@'")
+            );
+        }
+    }
+    
     // Check for browser_style
     if let Some(action) = &manifest.action {
         if action.browser_style == Some(true) {
@@ -129,14 +194,84 @@ pub fn analyze_manifest(manifest: &Manifest) -> Vec<Incompatibility> {
             .auto_fixable()
         );
     }
+    if manifest.extra.contains_key("page_action") {
+        issues.push(
+            Incompatibility::new(
+                Severity::Minor,
+                IncompatibilityCategory::ManifestStructure,
+                Location::ManifestField("page_action".to_string()),
+                "page_action (MV2 API, replaced by action in MV3)"
+            )
+            .with_suggestion("Will rename to 'action' (or drop it when an action exists), matching the rewritten pageAction calls")
+            .auto_fixable()
+        );
+    }
     
     issues
 }
 
+/// Check `name` and `short_name` (resolved against the default locale) against AMO limits
+pub fn analyze_name_lengths(extension: &Extension) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    let manifest = &extension.manifest;
+    
+    let short_name = manifest.extra.get("short_name").and_then(|v| v.as_str());
+    let fields = std::iter::once(("name", manifest.name.as_str()))
+        .chain(short_name.map(|value| ("short_name", value)));
+    
+    for (field, raw) in fields {
+        let resolved = extension.resolve_localized(raw);
+        let length = resolved.chars().count();
+        
+        if length > MAX_NAME_LENGTH {
+            let shown = if resolved != raw {
+                format!("'{}' (resolved from {})", resolved, raw)
+            } else {
+                format!("'{}'", resolved)
+            };
+            
+            issues.push(
+                Incompatibility::new(
+                    Severity::Major,
+                    IncompatibilityCategory::ManifestStructure,
+                    Location::ManifestField(field.to_string()),
+                    format!(
+                        "{} {} is {} characters long; AMO accepts at most {}",
+                        field, shown, length, MAX_NAME_LENGTH
+                    )
+                )
+                .with_suggestion(format!("Shorten {} to {} characters or fewer before submitting to AMO", field, MAX_NAME_LENGTH))
+            );
+        }
+    }
+    
+    issues
+}
+
+/// Why Firefox can't reliably trigger the extension with `keyword`
+fn omnibox_keyword_problem(keyword: &str) -> Option<&'static str> {
+    if keyword.trim().is_empty() {
+        Some("is empty, so Firefox never activates the extension in the address bar")
+    } else if keyword.chars().any(char::is_whitespace) {
+        Some("contains whitespace; Firefox activates on the keyword followed by a space, so it can never match")
+    } else if keyword.starts_with('@') {
+        Some("starts with '@', which Firefox reserves for search shortcuts like @bookmarks and @tabs")
+    } else if keyword.chars().count() == 1 {
+        Some("is a single character and easily conflicts with search engine keywords in Firefox")
+    } else {
+        None
+    }
+}
+
 fn is_match_pattern(s: &str) -> bool {
     s.contains("://") || s.starts_with('<') || s.starts_with('*')
 }
 
+/// `<all_urls>` or a pattern equivalent to it for web pages
+pub fn is_all_urls_pattern(pattern: &str) -> bool {
+    matches!(pattern, "<all_urls>" | "*://*/*")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -165,10 +300,92 @@ mod tests {
             browser_specific_settings: None,
             icons: None,
             commands: None,
+            omnibox: None,
             extra: Default::default(),
         };
         
         let issues = analyze_manifest(&manifest);
         assert!(issues.iter().any(|i| matches!(i.category, IncompatibilityCategory::BackgroundWorker)));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_detect_overlong_localized_name() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "__MSG_extName__",
+            "short_name": "Short",
+            "version": "1.0",
+            "default_locale": "en"
+        }"#).unwrap();
+        let mut files = std::collections::HashMap::new();
+        files.insert(
+            std::path::PathBuf::from("_locales/en/messages.json"),
+            br#"{"extName": {"message": "An Extremely Descriptive Extension Name For Testing"}}"#.to_vec(),
+        );
+        let extension = Extension::new(manifest, files);
+        
+        let issues = analyze_name_lengths(&extension);
+        assert_eq!(issues.len(), 1);
+        assert!(matches!(issues[0].severity, Severity::Major));
+        assert!(matches!(&issues[0].location, Location::ManifestField(f) if f == "name"));
+        assert!(issues[0].description.contains("51 characters"));
+    }
+    
+    #[test]
+    fn test_invalid_content_script_match_pattern() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Test", "version": "1.0",
+            "content_scripts": [{
+                "matches": ["https://*.example.com/*", "https://www.*.com/*"],
+                "exclude_matches": ["example.org"],
+                "js": ["content.js"]
+            }]
+        }"#).unwrap();
+        
+        let issues: Vec<_> = analyze_manifest(&manifest).into_iter()
+            .filter(|i| i.description.starts_with("Invalid match pattern"))
+            .collect();
+        assert_eq!(issues.len(), 2);
+        assert!(matches!(&issues[0].location, Location::ManifestField(f) if f == "content_scripts[0].matches"));
+        assert!(issues[1].description.contains("example.org"));
+    }
+    
+    #[test]
+    fn test_all_urls_with_active_tab_advisory() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Test", "version": "1.0",
+            "permissions": ["activeTab"],
+            "content_scripts": [{ "matches": ["<all_urls>"], "js": ["content.js"] }]
+        }"#).unwrap();
+        
+        let issues: Vec<_> = analyze_manifest(&manifest).into_iter()
+            .filter(|i| i.category == IncompatibilityCategory::HostPermissions)
+            .collect();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Minor);
+        assert!(issues[0].description.contains("activeTab"));
+        
+        let transformed = crate::transformer::ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        assert!(!transformed.content_scripts[0].all_frames);
+    }
+    
+    #[test]
+    fn test_omnibox_keyword_validation() {
+        let parse = |keyword: &str| crate::parser::manifest::parse_manifest(
+            serde_json::json!({
+                "manifest_version": 3, "name": "Test", "version": "1.0",
+                "omnibox": { "keyword": keyword }
+            }).to_string().as_bytes()
+        ).unwrap();
+        let keyword_issues = |keyword: &str| analyze_manifest(&parse(keyword)).into_iter()
+            .filter(|i| matches!(&i.location, Location::ManifestField(field) if field == "omnibox.keyword"))
+            .collect::<Vec<_>>();
+        
+        assert!(keyword_issues("go").is_empty());
+        for keyword in ["g", "go to", "@tabs", ""] {
+            let issues = keyword_issues(keyword);
+            assert_eq!(issues.len(), 1, "keyword {:?}", keyword);
+            assert_eq!(issues[0].severity, Severity::Minor);
+        }
+    }
+}
@@ -5,12 +5,23 @@ pub mod api;
 pub mod offscreen;
 pub mod declarative_content;
 pub mod keyboard_shortcuts;
+pub mod dnr;
+pub mod csp;
+pub mod i18n;
+pub mod web_accessible;
+pub mod unsafe_eval;
+pub mod permissions;
+pub mod html;
 
 pub use offscreen::OffscreenAnalyzer;
 pub use declarative_content::DeclarativeContentAnalyzer;
 pub use keyboard_shortcuts::{analyze_shortcuts, ShortcutAnalysis, ShortcutConflict};
 
-use crate::models::{Extension, ConversionContext};
+use crate::models::{Extension, ConversionContext, Location};
+use crate::utils::helpers::glob_match;
+use crate::ConversionOptions;
+use std::path::Path;
+use crate::progress::{NoProgress, ProgressSink};
 use anyhow::Result;
 
 /// Analyze an extension for Chrome-to-Firefox incompatibilities
@@ -23,24 +34,113 @@ use anyhow::Result;
 /// Note: JavaScript code passes through unchanged!
 /// Runtime shims provide compatibility at execution time.
 pub fn analyze_extension(extension: Extension) -> Result<ConversionContext> {
+    analyze_extension_with_progress(extension, &mut NoProgress)
+}
+
+/// [`analyze_extension`] reporting each analyzed JavaScript file to `progress`
+pub fn analyze_extension_with_progress(extension: Extension, progress: &mut dyn ProgressSink) -> Result<ConversionContext> {
+    analyze_extension_with_options(extension, ConversionOptions::default(), progress)
+}
+
+/// Analyze with `options` stored on the returned context
+///
+/// Files matching `options.analyze_ignore` are left out of API scanning and
+/// get no issues; manifest analysis always runs.
+pub fn analyze_extension_with_options(
+    extension: Extension,
+    options: ConversionOptions,
+    progress: &mut dyn ProgressSink,
+) -> Result<ConversionContext> {
     let mut context = ConversionContext::new(extension);
+    context.options = options;
     
     // 1. Analyze manifest for structural differences
     let manifest_issues = manifest::analyze_manifest(&context.source.manifest);
     for issue in manifest_issues {
         context.add_incompatibility(issue);
     }
+    for issue in manifest::analyze_name_lengths(&context.source) {
+        context.add_incompatibility(issue);
+    }
+    for issue in dnr::analyze_dnr_rule_limits(&context.source) {
+        context.add_incompatibility(issue);
+    }
+    for issue in csp::analyze_csp(&context.source.manifest) {
+        context.add_incompatibility(issue);
+    }
+    for issue in i18n::analyze_message_placeholders(&context.source) {
+        context.add_incompatibility(issue);
+    }
+    for issue in web_accessible::analyze_web_accessible_resources(&context.source) {
+        context.add_incompatibility(issue);
+    }
+    for issue in html::analyze_inline_handlers(&context.source) {
+        context.add_incompatibility(issue);
+    }
     
     // 2. Analyze JavaScript files for Chrome-only API usage
     // (Detection only - code passes through, shims handle compatibility)
-    for js_path in context.source.get_javascript_files() {
+    let mut js_files: Vec<_> = context.source.get_javascript_files()
+        .into_iter()
+        .filter(|path| !is_ignored(&context.options, path))
+        .collect();
+    js_files.sort();
+    let total = js_files.len();
+    let gecko_id = crate::transformer::manifest::ManifestTransformer::firefox_extension_id(&context.source.manifest);
+    let angularjs_csp_mode = unsafe_eval::uses_angularjs_csp_mode(&context.source);
+    for (index, js_path) in js_files.into_iter().enumerate() {
+        progress.on_file(&js_path, index + 1, total);
         if let Some(content) = context.source.get_file_content(&js_path) {
             let api_issues = api::analyze_javascript_apis(&content, &js_path);
             for issue in api_issues {
                 context.add_incompatibility(issue);
             }
+            for issue in api::analyze_api_permissions(&content, &js_path, &context.source.manifest) {
+                context.add_incompatibility(issue);
+            }
+            for issue in api::analyze_behavior_differences(&content, &js_path) {
+                context.add_incompatibility(issue);
+            }
+            for issue in api::analyze_hardcoded_extension_ids(&content, &js_path) {
+                context.add_incompatibility(issue);
+            }
+            for issue in api::analyze_native_messaging(&content, &js_path, &context.source.manifest, &gecko_id) {
+                context.add_incompatibility(issue);
+            }
+            for issue in api::analyze_external_messaging(&content, &js_path, &context.source.manifest) {
+                context.add_incompatibility(issue);
+            }
+            for issue in unsafe_eval::analyze_unsafe_eval(&content, &js_path, angularjs_csp_mode) {
+                context.add_incompatibility(issue);
+            }
         }
     }
+    for issue in api::analyze_message_receivers(&context.source) {
+        context.add_incompatibility(issue);
+    }
+    
+    // Service-worker lifecycle events only matter in the background
+    for script in context.source.get_background_scripts() {
+        if is_ignored(&context.options, &script) {
+            continue;
+        }
+        if let Some(content) = context.source.get_file_content(&script) {
+            for issue in api::analyze_service_worker_events(&content, &script) {
+                context.add_incompatibility(issue);
+            }
+            for issue in api::analyze_background_set_icon(&content, &script) {
+                context.add_incompatibility(issue);
+            }
+        }
+    }
+    
+    // Extension-wide passes scan every file (an ignored script can still hold
+    // a message receiver), so drop what they found in ignored files
+    let options = &context.options;
+    context.incompatibilities.retain(|issue| match &issue.location {
+        Location::File(path) | Location::FileLocation(path, _) => !is_ignored(options, path),
+        Location::Manifest | Location::ManifestField(_) => true,
+    });
     
     // 3. Generate user decisions for non-auto-fixable issues
     generate_decisions(&mut context);
@@ -48,6 +148,12 @@ pub fn analyze_extension(extension: Extension) -> Result<ConversionContext> {
     Ok(context)
 }
 
+/// Whether `path` matches one of the `analyze_ignore` globs
+fn is_ignored(options: &ConversionOptions, path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    options.analyze_ignore.iter().any(|pattern| glob_match(pattern, &path))
+}
+
 fn generate_decisions(context: &mut ConversionContext) {
     use crate::models::{UserDecision, DecisionCategory, DecisionOption};
     
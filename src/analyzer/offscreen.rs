@@ -6,6 +6,35 @@ use anyhow::{Result, anyhow};
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 
+// Complexity weights; the factors sum to at most 100. `determine_strategy`
+// keeps single-purpose documents below 70 as workers, so a document only
+// crosses that line when it combines several heavy factors.
+
+/// Points per external dependency (detected library or `<script src>`)
+const DEPENDENCY_WEIGHT: usize = 8;
+const DEPENDENCY_CAP: usize = 24;
+/// One point per this many non-blank script lines
+const LINES_PER_POINT: usize = 20;
+const LINES_CAP: usize = 25;
+/// Points per purpose beyond the first when the document is `Mixed`
+const EXTRA_PURPOSE_WEIGHT: usize = 15;
+const EXTRA_PURPOSE_CAP: usize = 30;
+/// Points per distinct API from [`WORKER_UNAVAILABLE_APIS`]
+const WORKER_UNAVAILABLE_WEIGHT: usize = 7;
+const WORKER_UNAVAILABLE_CAP: usize = 21;
+
+/// APIs that exist in documents but not in (Offscreen)Canvas or audio workers
+const WORKER_UNAVAILABLE_APIS: &[&str] = &[
+    "document.",
+    "window.",
+    "DOMParser",
+    "XMLSerializer",
+    "localStorage",
+    "sessionStorage",
+    "new Audio(",
+    "alert(",
+];
+
 pub struct OffscreenAnalyzer {
     source_dir: PathBuf,
 }
@@ -51,18 +80,28 @@ impl OffscreenAnalyzer {
         let content = std::fs::read_to_string(&full_path)
             .map_err(|e| anyhow!("Failed to read {}: {}", html_path, e))?;
         
-        let scripts = self.extract_all_scripts(&content)?;
+        self.analyze_document_content(&content)
+    }
+
+    /// Analyze offscreen document HTML that is already in memory
+    pub fn analyze_document_content(&self, html_content: &str) -> Result<DocumentAnalysis> {
+        let scripts = self.extract_all_scripts(html_content)?;
         
         let mut analysis = DocumentAnalysis::default();
+        for src in self.extract_script_sources(html_content) {
+            if !analysis.dependencies.contains(&src) {
+                analysis.dependencies.push(src);
+            }
+        }
         
         // Analyze each script block
-        for script in scripts {
-            self.analyze_script_content(&script, &mut analysis)?;
+        for script in &scripts {
+            self.analyze_script_content(script, &mut analysis)?;
         }
         
         // Determine primary purpose based on weighted scoring
         analysis.primary_purpose = self.determine_primary_purpose(&analysis);
-        analysis.complexity_score = self.calculate_complexity(&analysis);
+        analysis.complexity_score = self.calculate_complexity(&analysis, &scripts);
         
         Ok(analysis)
     }
@@ -96,6 +135,14 @@ impl OffscreenAnalyzer {
         Ok(scripts)
     }
 
+    /// `src` attributes of `<script>` tags
+    fn extract_script_sources(&self, html_content: &str) -> Vec<String> {
+        let src_re = regex::Regex::new(r#"<script[^>]*\ssrc\s*=\s*["']([^"']+)["']"#).unwrap();
+        src_re.captures_iter(html_content)
+            .map(|caps| caps[1].to_string())
+            .collect()
+    }
+
     fn analyze_script_content(&self, script: &str, analysis: &mut DocumentAnalysis) -> Result<()> {
         // Regex-based detection (simple pattern matching)
         
@@ -201,16 +248,26 @@ impl OffscreenAnalyzer {
             .unwrap_or(OffscreenPurpose::Unknown)
     }
 
-    fn calculate_complexity(&self, analysis: &DocumentAnalysis) -> u8 {
-        let mut score = 0u8;
+    /// Weighted 0-100 score; see the weight constants at the top of this file
+    fn calculate_complexity(&self, analysis: &DocumentAnalysis, scripts: &[String]) -> u8 {
+        let lines = scripts.iter()
+            .flat_map(|script| script.lines())
+            .filter(|line| !line.trim().is_empty())
+            .count();
+        let extra_purposes = match &analysis.primary_purpose {
+            OffscreenPurpose::Mixed(purposes) => purposes.len().saturating_sub(1),
+            _ => 0,
+        };
+        let unavailable_apis = WORKER_UNAVAILABLE_APIS.iter()
+            .filter(|api| scripts.iter().any(|script| script.contains(*api)))
+            .count();
         
-        // More operations = higher complexity
-        score += (analysis.canvas_operations.len() as u8).min(30);
-        score += (analysis.dom_operations.len() as u8).min(30);
-        score += (analysis.audio_operations.len() as u8).min(20);
-        score += (analysis.dependencies.len() as u8 * 5).min(20);
+        let score = (analysis.dependencies.len() * DEPENDENCY_WEIGHT).min(DEPENDENCY_CAP)
+            + (lines / LINES_PER_POINT).min(LINES_CAP)
+            + (extra_purposes * EXTRA_PURPOSE_WEIGHT).min(EXTRA_PURPOSE_CAP)
+            + (unavailable_apis * WORKER_UNAVAILABLE_WEIGHT).min(WORKER_UNAVAILABLE_CAP);
         
-        score.min(100)
+        score.min(100) as u8
     }
 
     // Helper methods
@@ -291,4 +348,55 @@ impl OffscreenAnalyzer {
         
         deps
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::offscreen_converter::OffscreenConverter;
+
+    fn strategy_for(html: &str) -> (u8, ConversionStrategy) {
+        let analyzer = OffscreenAnalyzer::new(std::env::temp_dir());
+        let analysis = analyzer.analyze_document_content(html).unwrap();
+        let usage = OffscreenUsage {
+            call_location: FileLocation::new(PathBuf::from("background.js"), 1, 0),
+            document_url: "offscreen.html".to_string(),
+            reasons: vec![],
+            justification: None,
+        };
+        let converter = OffscreenConverter::new(std::env::temp_dir(), ConversionPreferences::default());
+        (analysis.complexity_score, converter.determine_strategy(&analysis, &usage))
+    }
+
+    #[test]
+    fn test_trivial_canvas_document_scores_low() {
+        let html = r#"<html><body>
+<script>
+const canvas = new OffscreenCanvas(16, 16);
+const ctx = canvas.getContext('2d');
+ctx.fillRect(0, 0, 16, 16);
+</script>
+</body></html>"#;
+        let (score, strategy) = strategy_for(html);
+        
+        assert!(score < 10, "score was {}", score);
+        assert!(matches!(strategy, ConversionStrategy::CanvasWorker { .. }));
+    }
+
+    #[test]
+    fn test_heavy_mixed_document_scores_high() {
+        let mut body = String::from("const ctx = new AudioContext();\nconst canvas = document.querySelector('canvas');\n");
+        body.push_str("const doc = new DOMParser().parseFromString(html, 'text/html');\nlocalStorage.setItem('k', 'v');\n");
+        for i in 0..500 {
+            body.push_str(&format!("ctx.createGain().gain.value = {};\n", i));
+        }
+        let html = format!(
+            "<script src=\"lib/three.js\"></script>\n<script src=\"lib/tone.js\"></script>\n<script src=\"lib/cheerio.js\"></script>\n<script>\n{}</script>\n",
+            body
+        );
+        let (score, strategy) = strategy_for(&html);
+        
+        assert!(score >= 80, "score was {}", score);
+        assert!(matches!(strategy, ConversionStrategy::ManualGuidance { .. }));
+    }
+}
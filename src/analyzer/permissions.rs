@@ -0,0 +1,143 @@
+//! Suggestions for narrowing broad host permissions
+//!
+//! AMO reviewers push back on `<all_urls>` when the extension only ever talks
+//! to a handful of hosts. The targets are taken from literal `fetch()` and
+//! `XMLHttpRequest.open()` URLs and content-script `matches`; any dynamic
+//! request URL or API that needs every host leaves the permission alone.
+
+use crate::models::Extension;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Host permissions that grant access to every site
+const BROAD_HOST_PATTERNS: &[&str] = &["<all_urls>", "*://*/*", "http://*/*", "https://*/*"];
+
+/// A broad host permission and the narrower set that covers actual usage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionSuggestion {
+    /// The broad permission as declared
+    pub permission: String,
+    /// Permissions to request instead
+    pub replacement: Vec<String>,
+    pub reason: String,
+}
+
+impl PermissionSuggestion {
+    /// One-line description for reports
+    pub fn describe(&self) -> String {
+        let replacement: Vec<String> = self.replacement.iter().map(|p| format!("`{}`", p)).collect();
+        format!("Replace `{}` with {}: {}", self.permission, replacement.join(", "), self.reason)
+    }
+}
+
+/// Suggest narrower replacements for `<all_urls>`-style host permissions
+pub fn suggest_permission_narrowing(extension: &Extension) -> Vec<PermissionSuggestion> {
+    let manifest = &extension.manifest;
+    let broad: Vec<&String> = manifest.host_permissions.iter()
+        .chain(manifest.permissions.iter())
+        .filter(|p| BROAD_HOST_PATTERNS.contains(&p.as_str()))
+        .collect();
+    if broad.is_empty() {
+        return Vec::new();
+    }
+
+    // APIs that observe or act on arbitrary sites keep the broad grant
+    let host_wide_api_re = Regex::new(r"\b(?:chrome|browser)\.(?:webRequest|cookies|proxy)\b").unwrap();
+    if extension.javascript_matches(&host_wide_api_re) {
+        return Vec::new();
+    }
+
+    let mut targets = BTreeSet::new();
+    for content_script in &manifest.content_scripts {
+        for pattern in &content_script.matches {
+            if BROAD_HOST_PATTERNS.contains(&pattern.as_str()) {
+                return Vec::new();
+            }
+            targets.insert(pattern.clone());
+        }
+    }
+
+    let request_re = Regex::new(r#"\bfetch\s*\(|\.open\s*\(\s*['"`][A-Za-z]+['"`]\s*,"#).unwrap();
+    let literal_re = Regex::new(
+        r#"(?:\bfetch\s*\(|\.open\s*\(\s*['"`][A-Za-z]+['"`]\s*,)\s*['"`](https?)://([A-Za-z0-9.-]+)(?::\d+)?[/'"`?#]"#
+    ).unwrap();
+    for path in extension.get_javascript_files() {
+        let Some(content) = extension.get_file_content(&path) else { continue };
+        let literals: Vec<_> = literal_re.captures_iter(&content).collect();
+        if request_re.find_iter(&content).count() > literals.len() {
+            // A request URL built at runtime could go anywhere
+            return Vec::new();
+        }
+        for caps in literals {
+            targets.insert(format!("{}://{}/*", &caps[1], caps[2].to_ascii_lowercase()));
+        }
+    }
+
+    let active_tab_re = Regex::new(
+        r"\b(?:chrome|browser)\.(?:scripting\.(?:executeScript|insertCSS)|tabs\.(?:executeScript|insertCSS|captureVisibleTab))\b"
+    ).unwrap();
+    let uses_active_tab = extension.javascript_matches(&active_tab_re);
+
+    let mut replacement: Vec<String> = targets.into_iter().collect();
+    let reason = match (replacement.is_empty(), uses_active_tab) {
+        (true, false) => return Vec::new(),
+        (true, true) => "scripts are only injected into tabs, which activeTab allows after a user action".to_string(),
+        (false, _) => "these are the only hosts fetched or matched by content scripts".to_string(),
+    };
+    if uses_active_tab && !manifest.permissions.iter().any(|p| p == "activeTab") {
+        replacement.push("activeTab".to_string());
+    }
+
+    broad.into_iter()
+        .map(|permission| PermissionSuggestion {
+            permission: permission.clone(),
+            replacement: replacement.clone(),
+            reason: reason.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn extension(manifest: serde_json::Value, background: &str) -> Extension {
+        let manifest = crate::parser::manifest::parse_manifest(manifest.to_string().as_bytes()).unwrap();
+        let files = [(PathBuf::from("background.js"), background.as_bytes().to_vec())].into_iter().collect();
+        Extension::new(manifest, files)
+    }
+
+    fn manifest() -> serde_json::Value {
+        serde_json::json!({
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "background": { "service_worker": "background.js" },
+            "host_permissions": ["<all_urls>"]
+        })
+    }
+
+    #[test]
+    fn test_all_urls_narrowed_to_fetched_host() {
+        let ext = extension(manifest(), "fetch('https://api.example.com/data').then(r => r.json());\n");
+        let suggestions = suggest_permission_narrowing(&ext);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].permission, "<all_urls>");
+        assert_eq!(suggestions[0].replacement, vec!["https://api.example.com/*"]);
+
+        let ext = extension(manifest(), "fetch(`https://${host}/data`);\n");
+        assert!(suggest_permission_narrowing(&ext).is_empty());
+    }
+
+    #[test]
+    fn test_active_tab_suggested_for_tab_injection() {
+        let ext = extension(
+            manifest(),
+            "chrome.action.onClicked.addListener((tab) => {\n  chrome.scripting.executeScript({ target: { tabId: tab.id }, files: ['inject.js'] });\n});\n",
+        );
+        let suggestions = suggest_permission_narrowing(&ext);
+        assert_eq!(suggestions[0].replacement, vec!["activeTab"]);
+    }
+}
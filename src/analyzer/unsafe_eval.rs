@@ -0,0 +1,182 @@
+//! Bundled code that needs `'unsafe-eval'`, which Firefox MV3 extension pages forbid
+
+use crate::models::{Extension, Incompatibility, IncompatibilityCategory, Location, Severity};
+use regex::Regex;
+use std::path::Path;
+
+/// Library signatures that compile code at runtime: (pattern, culprit, suggestion)
+const LIBRARY_SIGNATURES: &[(&str, &str, &str)] = &[
+    (
+        r"\bHandlebars\.compile\s*\(",
+        "Handlebars runtime template compilation",
+        "Precompile templates with the handlebars CLI and bundle handlebars.runtime instead of the full build",
+    ),
+    (
+        r"\b(?:Vue\.compile\s*\(|compileToFunctions\b)",
+        "the Vue template compiler (full build)",
+        "Use the runtime-only Vue build and precompile templates (single-file components or render functions)",
+    ),
+    (
+        r"\b_\.template\s*\(",
+        "lodash/underscore _.template",
+        "Precompile templates at build time (e.g. lodash-cli or a bundler loader) and ship the generated functions",
+    ),
+    (
+        r"\bdoT\.template\s*\(",
+        "doT template compilation",
+        "Precompile doT templates at build time",
+    ),
+    (
+        r"\bejs\.compile\s*\(",
+        "EJS template compilation",
+        "Precompile EJS templates at build time (ejs --client) and ship the generated functions",
+    ),
+    (
+        r"\bpug\.compile\s*\(",
+        "Pug template compilation",
+        "Precompile Pug templates with pug.compileClient at build time",
+    ),
+];
+
+/// AngularJS only tries `new Function` and falls back when CSP blocks it, so
+/// it's reported as Minor, and not at all once CSP mode is on
+const ANGULARJS_SIGNATURE: (&str, &str, &str) = (
+    r"\bangular\.module\s*\(",
+    "AngularJS expression parsing",
+    "Add the ng-csp directive (or angular.csp) so AngularJS avoids new Function",
+);
+
+/// Whether an extension page turns on AngularJS CSP mode with an `ng-csp` attribute
+///
+/// Only HTML is searched: AngularJS itself mentions `ng-csp` while detecting it.
+pub fn uses_angularjs_csp_mode(extension: &Extension) -> bool {
+    let csp_re = Regex::new(r"(?i)<[a-z][^>]*\s(?:data-)?ng-csp\b").unwrap();
+    extension.files.keys()
+        .filter(|path| {
+            let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+            matches!(ext.as_deref(), Some("html" | "htm"))
+        })
+        .filter_map(|path| extension.get_file_content(path))
+        .any(|content| csp_re.is_match(&content))
+}
+
+/// Generic runtime code generation, reported when no library signature explains it
+const GENERIC_SIGNATURES: &[(&str, &str)] = &[
+    (r"\bnew\s+Function\s*\(", "new Function()"),
+    (r"(?:^|[^.\w$])eval\s*\(", "eval()"),
+];
+
+/// Flag runtime code compilation in `content`, once per culprit per file
+///
+/// Heuristic: known template engines are named when their entry points
+/// appear; otherwise the first `new Function()` / `eval()` is reported.
+/// `angularjs_csp_mode` is [`uses_angularjs_csp_mode`] for the extension.
+pub fn analyze_unsafe_eval(content: &str, path: &Path, angularjs_csp_mode: bool) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    
+    let mut explained = false;
+    let signatures = LIBRARY_SIGNATURES.iter()
+        .map(|signature| (*signature, Severity::Major))
+        .chain([(ANGULARJS_SIGNATURE, Severity::Minor)]);
+    for ((pattern, culprit, suggestion), severity) in signatures {
+        let re = Regex::new(pattern).unwrap();
+        let Some(line) = first_match_line(&re, content) else { continue };
+        // The library explains any `new Function` in the file either way
+        explained = true;
+        if angularjs_csp_mode && pattern == ANGULARJS_SIGNATURE.0 {
+            continue;
+        }
+        issues.push(
+            Incompatibility::new(
+                severity,
+                IncompatibilityCategory::ContentSecurityPolicy,
+                Location::FileLocation(path.to_path_buf(), line),
+                format!("Code appears to use {}, which compiles code at runtime and needs 'unsafe-eval' (forbidden in Firefox MV3 extension pages)", culprit)
+            )
+            .with_suggestion(suggestion)
+        );
+    }
+    if explained {
+        return issues;
+    }
+    
+    for (pattern, construct) in GENERIC_SIGNATURES {
+        let re = Regex::new(pattern).unwrap();
+        if let Some(line) = first_match_line(&re, content) {
+            issues.push(
+                Incompatibility::new(
+                    Severity::Major,
+                    IncompatibilityCategory::ContentSecurityPolicy,
+                    Location::FileLocation(path.to_path_buf(), line),
+                    format!("{} compiles code at runtime and needs 'unsafe-eval', which Firefox MV3 extension pages forbid (likely a template or expression compiler)", construct)
+                )
+                .with_suggestion("Precompile templates at build time or switch to a CSP-safe library build")
+            );
+        }
+    }
+    
+    issues
+}
+
+fn first_match_line(re: &Regex, content: &str) -> Option<usize> {
+    content.lines().position(|line| re.is_match(line)).map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_new_function_template_compiler() {
+        let code = r#"
+function compile(template) {
+  const body = template.replace(/\{\{(\w+)\}\}/g, "' + data.$1 + '");
+  return new Function('data', "return '" + body + "';");
+}
+const retrieval = "document.evaluate('//a', document)";
+"#;
+        let issues = analyze_unsafe_eval(code, Path::new("popup.js"), false);
+        
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        assert!(issues[0].description.contains("new Function()"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 4)));
+    }
+    
+    #[test]
+    fn test_library_signature_named() {
+        let code = "var tpl = Handlebars.compile(source);\nvar f = new Function('a', 'return a');\n";
+        let issues = analyze_unsafe_eval(code, Path::new("lib/app.js"), false);
+        
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].description.contains("Handlebars"));
+    }
+    
+    #[test]
+    fn test_angularjs_minor_unless_csp_mode() {
+        let code = "angular.module('app', []);\n";
+        let issues = analyze_unsafe_eval(code, Path::new("popup.js"), false);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Minor);
+        assert!(issues[0].description.contains("AngularJS"));
+        
+        // In CSP mode AngularJS never reaches its bundled `new Function`
+        let bundled = "var parse = new Function('s', 'l', fnString);\nangular.module('app', []);\n";
+        assert!(analyze_unsafe_eval(bundled, Path::new("popup.js"), true).is_empty());
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        let files = [
+            (std::path::PathBuf::from("popup.html"), b"<html ng-app=\"app\" ng-csp>".to_vec()),
+            (std::path::PathBuf::from("popup.js"), code.as_bytes().to_vec()),
+        ].into_iter().collect();
+        assert!(uses_angularjs_csp_mode(&Extension::new(manifest.clone(), files)));
+        
+        let files = [(
+            std::path::PathBuf::from("angular.js"),
+            b"document.querySelector('[ng-csp]')".to_vec(),
+        )].into_iter().collect();
+        assert!(!uses_angularjs_csp_mode(&Extension::new(manifest, files)));
+    }
+}
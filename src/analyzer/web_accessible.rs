@@ -0,0 +1,157 @@
+//! Resources content scripts load through `runtime.getURL()` without exposing them
+
+use crate::models::{
+    Extension, Incompatibility, IncompatibilityCategory, Location, Manifest, Severity,
+    WebAccessibleResources,
+};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A `getURL()` resource that web pages can't load in Firefox
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingResource {
+    /// Extension-relative path passed to `getURL()`
+    pub resource: String,
+    pub file: PathBuf,
+    pub line: usize,
+    /// Match patterns of the content scripts that load it
+    pub matches: Vec<String>,
+}
+
+/// Find `getURL('literal')` resources in content scripts that no
+/// `web_accessible_resources` entry exposes
+///
+/// Extension pages may load any packaged file, so only content scripts are
+/// scanned. Resources that aren't in the package are skipped.
+pub fn missing_web_accessible_resources(extension: &Extension) -> Vec<MissingResource> {
+    let get_url_re = Regex::new(r#"\b(?:chrome|browser)\.(?:runtime|extension)\.getURL\(\s*(['"`])([^'"`$]+)['"`]\s*\)"#).unwrap();
+    let manifest = &extension.manifest;
+    
+    // Content script file -> match patterns of every entry that injects it
+    let mut scripts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for content_script in &manifest.content_scripts {
+        for js in &content_script.js {
+            scripts.entry(normalize(js).to_string()).or_default()
+                .extend(content_script.matches.iter().cloned());
+        }
+    }
+    
+    let mut missing: BTreeMap<String, MissingResource> = BTreeMap::new();
+    for (script, matches) in &scripts {
+        let path = PathBuf::from(script);
+        let Some(content) = extension.get_file_content(&path) else { continue };
+        
+        for (index, line) in content.lines().enumerate() {
+            for caps in get_url_re.captures_iter(line) {
+                let resource = normalize(caps[2].split(['?', '#']).next().unwrap_or_default());
+                if resource.is_empty()
+                    || !extension.has_file(Path::new(resource))
+                    || is_web_accessible(manifest, resource)
+                {
+                    continue;
+                }
+                
+                let entry = missing.entry(resource.to_string()).or_insert_with(|| MissingResource {
+                    resource: resource.to_string(),
+                    file: path.clone(),
+                    line: index + 1,
+                    matches: Vec::new(),
+                });
+                for pattern in matches {
+                    if !entry.matches.contains(pattern) {
+                        entry.matches.push(pattern.clone());
+                    }
+                }
+            }
+        }
+    }
+    
+    missing.into_values().collect()
+}
+
+/// Flag content-script `getURL()` resources missing from `web_accessible_resources`
+pub fn analyze_web_accessible_resources(extension: &Extension) -> Vec<Incompatibility> {
+    missing_web_accessible_resources(extension)
+        .into_iter()
+        .map(|missing| {
+            Incompatibility::new(
+                Severity::Major,
+                IncompatibilityCategory::WebAccessibleResources,
+                Location::FileLocation(missing.file, missing.line),
+                format!(
+                    "'{}' is loaded via runtime.getURL() in a content script but isn't listed in web_accessible_resources, so Firefox blocks it",
+                    missing.resource
+                )
+            )
+            .with_suggestion(format!(
+                "Add \"{}\" to web_accessible_resources, or convert with --add-web-accessible to add it automatically",
+                missing.resource
+            ))
+        })
+        .collect()
+}
+
+/// Whether any `web_accessible_resources` pattern exposes `resource`
+pub fn is_web_accessible(manifest: &Manifest, resource: &str) -> bool {
+    let patterns: Vec<&String> = match &manifest.web_accessible_resources {
+        Some(WebAccessibleResources::V2(patterns)) => patterns.iter().collect(),
+        Some(WebAccessibleResources::V3(entries)) => entries.iter().flat_map(|e| e.resources.iter()).collect(),
+        None => return false,
+    };
+    patterns.iter().any(|pattern| wildcard_match(normalize(pattern).as_bytes(), resource.as_bytes()))
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim().trim_start_matches("./").trim_start_matches('/')
+}
+
+/// Chrome's resource patterns, where `*` also matches `/`
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn extension_with_war(war: serde_json::Value) -> Extension {
+        let manifest = serde_json::json!({
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [{ "matches": ["https://*.example.com/*"], "js": ["content.js"] }],
+            "web_accessible_resources": war
+        });
+        let manifest = crate::parser::manifest::parse_manifest(manifest.to_string().as_bytes()).unwrap();
+        let files = [
+            (
+                PathBuf::from("content.js"),
+                b"const img = document.createElement('img');\nimg.src = chrome.runtime.getURL('inject.png');\nimg.title = chrome.runtime.getURL('icons/logo.svg');\n".to_vec(),
+            ),
+            (PathBuf::from("inject.png"), Vec::new()),
+            (PathBuf::from("icons/logo.svg"), Vec::new()),
+        ].into_iter().collect();
+        Extension::new(manifest, files)
+    }
+    
+    #[test]
+    fn test_get_url_without_war_entry() {
+        let extension = extension_with_war(serde_json::json!([
+            { "resources": ["icons/*"], "matches": ["<all_urls>"] }
+        ]));
+        
+        let issues = analyze_web_accessible_resources(&extension);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Major);
+        assert!(issues[0].description.contains("'inject.png'"));
+        assert!(matches!(issues[0].location, Location::FileLocation(_, 2)));
+        
+        let missing = missing_web_accessible_resources(&extension);
+        assert_eq!(missing[0].matches, vec!["https://*.example.com/*".to_string()]);
+    }
+}
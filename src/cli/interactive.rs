@@ -5,7 +5,7 @@ use colored::*;
 use dialoguer::{theme::ColorfulTheme, Input, Select, Confirm};
 use std::path::PathBuf;
 use std::fs;
-use crate::{convert_extension, ConversionOptions, CalculatorType};
+use crate::{convert_extension, ConversionOptions};
 
 /// Scan for extensions in the current directory and subdirectories
 fn find_nearby_extensions() -> Vec<PathBuf> {
@@ -74,6 +74,25 @@ fn prompt_for_extension_path(prompt_text: &str) -> Result<PathBuf> {
     }
 }
 
+/// Ask for content script match patterns, pre-filled with `suggested`
+///
+/// Usable as a [`crate::models::chrome_only::UrlPrompt`]; an empty answer or
+/// a failed prompt (e.g. no terminal) returns `None`.
+pub fn prompt_for_match_patterns(suggested: &[String]) -> Option<Vec<String>> {
+    let answer: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("🌐 Which pages should the converted DOM parsing content script match? (comma-separated)")
+        .with_initial_text(suggested.join(", "))
+        .allow_empty(true)
+        .interact_text()
+        .ok()?;
+    
+    let patterns: Vec<String> = answer.split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+    (!patterns.is_empty()).then_some(patterns)
+}
+
 /// Run the interactive CLI mode
 pub fn run_interactive_mode() -> Result<()> {
     print_banner();
@@ -145,7 +164,7 @@ fn handle_convert() -> Result<()> {
     // Ask about options
     let preserve_chrome = Confirm::with_theme(&ColorfulTheme::default())
         .with_prompt("🔧 Preserve Chrome compatibility? (keep both chrome and browser namespaces)")
-        .default(true)
+        .default(false)
         .interact()?;
     
     let generate_report = Confirm::with_theme(&ColorfulTheme::default())
@@ -159,9 +178,11 @@ fn handle_convert() -> Result<()> {
     
     let options = ConversionOptions {
         interactive: false, // We're already in interactive mode
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: preserve_chrome,
         generate_report,
+        only_manifest: false,
+        offscreen_url_prompt: Some(prompt_for_match_patterns),
+        ..Default::default()
     };
     
     match convert_extension(&input, &output, options) {
@@ -183,6 +204,9 @@ fn handle_convert() -> Result<()> {
             println!("  Files Added: {} (compatibility shims)", result.new_files.len());
             println!("  Total Changes: {}", result.report.summary.total_changes);
             println!("  Chrome API Calls Converted: {}", result.report.summary.chrome_api_calls_converted);
+            for (kind, count) in &result.report.summary.changes_by_kind {
+                println!("  {}: {}", kind.label(), count);
+            }
             println!();
             
             // Detailed file changes with line-by-line breakdown
@@ -202,7 +226,7 @@ fn handle_convert() -> Result<()> {
                             desc);
                         
                         // Add clarification for callback warnings
-                        if desc.contains("consider converting to promise") {
+                        if change.kind == crate::models::ChangeKind::CallbackToPromise {
                             println!("       {} This callback was converted from chrome.* to browser.*", "ℹ️".dimmed());
                             println!("       {} Optional: You can manually convert to promise style for better Firefox compatibility", "💡".dimmed());
                         }
@@ -228,7 +252,7 @@ fn handle_convert() -> Result<()> {
                     println!("  • {}", warning);
                     
                     // Provide context for specific warnings
-                    if warning.contains("service worker") {
+                    if warning.message.contains("service worker") {
                         println!("    {}", "ℹ️  What this means - Service Worker → Event Page:".dimmed());
                         println!();
                         println!("    {}", "   CHROME (Service Worker):".dimmed());
@@ -262,7 +286,7 @@ fn handle_convert() -> Result<()> {
                         println!("    {}", "   • Generates alarm listeners automatically".dimmed());
                         println!();
                         println!("    {}", "   ✓ ACTION: Verify data persists and timers work after restarts".cyan());
-                    } else if warning.contains("extension ID") || warning.contains("default extension ID") {
+                    } else if warning.message.contains("extension ID") || warning.message.contains("default extension ID") {
                         println!("    {}", "ℹ️  What this means:".dimmed());
                         println!("    {}", "   Firefox requires a unique extension ID for AMO submission.".dimmed());
                         println!("    {}", "   The generated ID uses email format: name@domain".dimmed());
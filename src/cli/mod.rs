@@ -1,5 +1,6 @@
 //! Interactive CLI for Chrome to Firefox Extension Converter
 
 pub mod interactive;
+pub mod watch;
 
-pub use interactive::run_interactive_mode;
\ No newline at end of file
+pub use interactive::{prompt_for_match_patterns, run_interactive_mode};
\ No newline at end of file
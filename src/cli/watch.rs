@@ -0,0 +1,116 @@
+//! Watch mode: re-convert the extension whenever its source changes
+
+use crate::models::ConversionResult;
+use crate::{convert_extension, ConversionOptions};
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Default quiet period before a burst of edits triggers a conversion
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Convert once, then again after every debounced change under `input`
+///
+/// `on_conversion` receives each result and returns `false` to stop watching.
+/// Changes to the output directory and XPI are ignored, so the output may
+/// live inside the input tree.
+pub fn watch_and_convert<F>(
+    input: &Path,
+    output: &Path,
+    options: ConversionOptions,
+    debounce: Duration,
+    mut on_conversion: F,
+) -> Result<()>
+where
+    F: FnMut(Result<ConversionResult>) -> bool,
+{
+    let input = input.canonicalize()
+        .with_context(|| format!("Failed to resolve {}", input.display()))?;
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&input, RecursiveMode::Recursive)?;
+    
+    if !on_conversion(convert_extension(&input, output, options.clone())) {
+        return Ok(());
+    }
+    let ignored = ignored_paths(output);
+    
+    loop {
+        // Block until a relevant change, then wait for the edits to settle
+        let Ok(event) = rx.recv() else { return Ok(()) };
+        if !is_relevant(&event, &ignored) {
+            continue;
+        }
+        while rx.recv_timeout(debounce).is_ok() {}
+        
+        if !on_conversion(convert_extension(&input, output, options.clone())) {
+            return Ok(());
+        }
+    }
+}
+
+/// Output locations written by a conversion, which must not retrigger one
+fn ignored_paths(output: &Path) -> Vec<PathBuf> {
+    let output = output.canonicalize()
+        .or_else(|_| std::env::current_dir().map(|dir| dir.join(output)))
+        .unwrap_or_else(|_| output.to_path_buf());
+    vec![
+        output.with_extension("xpi"),
+        output.with_extension("md"),
+        output.with_extension("html"),
+        output.with_extension("sources.zip"),
+        output,
+    ]
+}
+
+fn is_relevant(event: &notify::Result<Event>, ignored: &[PathBuf]) -> bool {
+    let Ok(event) = event else { return false };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+        return false;
+    }
+    event.paths.iter().any(|path| !ignored.iter().any(|ignored| path.starts_with(ignored)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_change_triggers_reconversion() {
+        let input = TempDir::new().unwrap();
+        let output = TempDir::new().unwrap();
+        fs::write(
+            input.path().join("manifest.json"),
+            r#"{"manifest_version": 3, "name": "Watch Test", "version": "1.0"}"#,
+        ).unwrap();
+        
+        let input_path = input.path().to_path_buf();
+        let output_path = output.path().join("out");
+        let (done_tx, done_rx) = mpsc::channel();
+        
+        std::thread::spawn(move || {
+            let mut conversions = 0;
+            let options = ConversionOptions { interactive: false, ..Default::default() };
+            watch_and_convert(&input_path, &output_path, options, Duration::from_millis(50), |result| {
+                conversions += 1;
+                let version = result.unwrap().manifest.version;
+                if conversions == 1 {
+                    fs::write(
+                        input_path.join("manifest.json"),
+                        r#"{"manifest_version": 3, "name": "Watch Test", "version": "2.0"}"#,
+                    ).unwrap();
+                    return true;
+                }
+                done_tx.send(version).unwrap();
+                false
+            }).unwrap();
+        });
+        
+        let version = done_rx.recv_timeout(Duration::from_secs(10)).expect("change should trigger a second conversion");
+        assert_eq!(version, "2.0");
+    }
+}
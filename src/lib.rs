@@ -11,6 +11,7 @@ pub mod packager;
 pub mod validator;
 pub mod report;
 pub mod utils;
+pub mod progress;
 
 // CLI-only modules
 #[cfg(feature = "cli")]
@@ -25,6 +26,7 @@ pub mod wasm;
 pub use models::{Extension, Manifest, ConversionContext, ConversionResult};
 pub use analyzer::analyze_extension;
 pub use transformer::transform_extension;
+pub use progress::{ConversionStage, NoProgress, ProgressSink};
 
 use anyhow::Result;
 use std::path::Path;
@@ -34,12 +36,25 @@ pub fn convert_extension(
     input_path: &Path,
     output_path: &Path,
     options: ConversionOptions,
+) -> Result<ConversionResult> {
+    convert_extension_with_progress(input_path, output_path, options, &mut NoProgress)
+}
+
+/// [`convert_extension`] with stage, file and warning callbacks
+pub fn convert_extension_with_progress(
+    input_path: &Path,
+    output_path: &Path,
+    options: ConversionOptions,
+    progress: &mut dyn ProgressSink,
 ) -> Result<ConversionResult> {
     // 1. Extract/load extension
+    progress.on_stage(ConversionStage::Loading);
     let extension = packager::load_extension(input_path)?;
     
     // 2. Analyze for incompatibilities
-    let context = analyze_extension(extension)?;
+    progress.on_stage(ConversionStage::Analyzing);
+    let context = analyzer::analyze_extension_with_options(extension, options.clone(), progress)?;
+    let failing = issues_at_threshold(&context, &options);
     
     // 3. Get user decisions if needed
     let context = if options.interactive {
@@ -49,39 +64,229 @@ pub fn convert_extension(
     };
     
     // 4. Transform extension (AST-based)
-    let result = transformer::transform_extension(context)?;
+    progress.on_stage(ConversionStage::Transforming);
+    let mut result = transformer::transform_extension(context)?;
+    result.fail_on_error = fail_on_error(failing, &options);
+    for warning in &result.report.warnings {
+        progress.on_warning(&warning.to_string());
+    }
     
     // 5. Validate result
+    progress.on_stage(ConversionStage::Validating);
     validator::validate_extension(&result)?;
     
     // 6. Package output (extension is now in result.source)
-    packager::build_complete_extension(&result.source, &result, output_path)?;
+    progress.on_stage(ConversionStage::Packaging);
+    packager::build_complete_extension(&result.source, &result, output_path, options.package_format)?;
+    if options.package_format == PackageFormat::Xpi {
+        validator::validate_xpi_file(&output_path.with_extension("xpi"))?;
+    }
+    if options.generate_source_archive {
+        packager::build_source_archive(&result.source, &options, &packager::source_archive_path(output_path))?;
+    }
     
     // 7. Generate report
+    progress.on_stage(ConversionStage::Reporting);
     let _report = report::generate_report(&result)?;
     
     Ok(result)
 }
 
+/// Convert an extension held in memory as ZIP bytes
+///
+/// Runs the same pipeline as [`convert_extension`] without touching the
+/// filesystem and returns the result together with the packaged XPI bytes.
+pub fn convert_bytes(zip_data: &[u8], options: ConversionOptions) -> Result<(ConversionResult, Vec<u8>)> {
+    let extension = packager::extractor::load_from_bytes(zip_data)?;
+    
+    let context = analyzer::analyze_extension_with_options(extension, options.clone(), &mut NoProgress)?;
+    let failing = issues_at_threshold(&context, &options);
+    
+    let context = if options.interactive {
+        get_user_decisions(context)?
+    } else {
+        apply_default_decisions(context)
+    };
+    
+    let mut result = transformer::transform_extension(context)?;
+    result.fail_on_error = fail_on_error(failing, &options);
+    validator::validate_extension(&result)?;
+    
+    let xpi = packager::builder::build_xpi_bytes(&result)?;
+    validator::validate_xpi(&xpi)?;
+    Ok((result, xpi))
+}
+
 #[derive(Debug, Clone)]
 pub struct ConversionOptions {
     pub interactive: bool,
-    pub target_calculator: CalculatorType,
     pub preserve_chrome_compatibility: bool,
+    /// Keep the manifest `key` so a shared Chrome build keeps its extension ID
+    /// (AMO rejects manifests that still carry `key`)
+    pub preserve_chrome_key: bool,
     pub generate_report: bool,
+    /// Only transform the manifest; JavaScript is copied verbatim and no shims are generated
+    pub only_manifest: bool,
+    /// How `chrome.*` / `browser.*` namespaces are made available in Firefox
+    pub namespace_strategy: NamespaceStrategy,
+    /// Also write `<output>.sources.zip` with the original source tree for AMO review
+    pub generate_source_archive: bool,
+    /// Split background scripts larger than this many bytes into several files
+    pub split_background_threshold: Option<usize>,
+    /// Declare `gecko.data_collection_permissions` for AMO (e.g. `DataCollectionPermissions::none()`)
+    pub data_collection_permissions: Option<models::DataCollectionPermissions>,
+    /// Globs for output files left out of the package (e.g. `.chrome2moz-provenance.json`)
+    pub exclude_patterns: Vec<String>,
+    /// Also pack the provenance file into the `.xpi` (it is always written to the output directory)
+    pub package_provenance: bool,
+    /// Expose resources content scripts load via `runtime.getURL()` in `web_accessible_resources`
+    pub add_web_accessible_resources: bool,
+    /// Keep content scripts' declared `all_frames` instead of enabling it for iframes
+    pub preserve_all_frames: bool,
+    /// Threads for per-file JavaScript transforms (default: available parallelism)
+    pub max_threads: Option<usize>,
+    /// Globs for source files skipped by JavaScript API analysis (e.g. `vendor/**`, `*.min.js`)
+    pub analyze_ignore: Vec<String>,
+    /// Fail the conversion when unresolved issues at or above this severity remain
+    ///
+    /// The output is still written; see [`ConversionResult::fail_on_error`].
+    pub fail_on: Option<models::Severity>,
+    /// Output directory for generated shims, referenced from the manifest (default `shims`)
+    pub shims_dir: String,
+    /// Concatenate the generated shims into a single `compat-bundle.js`
+    pub bundle_shims: bool,
+    /// Whether to zip the output directory into an `.xpi` next to it
+    pub package_format: PackageFormat,
+    /// Oldest Firefox major version to support; features it has skip their shims
+    pub target_firefox_version: Option<u32>,
+    /// Convert `chrome.offscreen` documents during the transform, asking this for
+    /// DOM parsing content script match patterns (set by the interactive CLI)
+    pub offscreen_url_prompt: Option<models::chrome_only::UrlPrompt>,
+}
+
+impl ConversionOptions {
+    /// Namespace strategy actually applied during transformation
+    ///
+    /// `preserve_chrome_compatibility` implies `PolyfillOnly`, so the same
+    /// code can ship to Chrome and Firefox unchanged.
+    pub fn effective_namespace_strategy(&self) -> NamespaceStrategy {
+        if self.preserve_chrome_compatibility {
+            NamespaceStrategy::PolyfillOnly
+        } else {
+            self.namespace_strategy
+        }
+    }
 }
 
 impl Default for ConversionOptions {
     fn default() -> Self {
         Self {
             interactive: true,
-            target_calculator: CalculatorType::Both,
-            preserve_chrome_compatibility: true,
+            preserve_chrome_compatibility: false,
+            preserve_chrome_key: false,
             generate_report: true,
+            only_manifest: false,
+            namespace_strategy: NamespaceStrategy::default(),
+            generate_source_archive: false,
+            split_background_threshold: None,
+            data_collection_permissions: None,
+            exclude_patterns: Vec::new(),
+            package_provenance: false,
+            add_web_accessible_resources: false,
+            preserve_all_frames: false,
+            max_threads: None,
+            analyze_ignore: Vec::new(),
+            fail_on: None,
+            shims_dir: transformer::shims::DEFAULT_SHIMS_DIR.to_string(),
+            bundle_shims: false,
+            package_format: PackageFormat::default(),
+            target_firefox_version: None,
+            offscreen_url_prompt: None,
         }
     }
 }
 
+/// What the conversion writes to the output path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PackageFormat {
+    /// The extension directory plus `<output>.xpi`
+    #[default]
+    Xpi,
+    /// Only the extension directory, for loading as a temporary add-on
+    Directory,
+}
+
+impl std::str::FromStr for PackageFormat {
+    type Err = ParseOptionError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "xpi" => Ok(Self::Xpi),
+            "dir" | "directory" => Ok(Self::Directory),
+            _ => Err(ParseOptionError::new("output format", s, "xpi or dir")),
+        }
+    }
+}
+
+impl std::fmt::Display for PackageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Xpi => write!(f, "xpi"),
+            Self::Directory => write!(f, "dir"),
+        }
+    }
+}
+
+/// Namespace handling for converted scripts
+///
+/// JavaScript is never rewritten from `chrome.*` to `browser.*`; the strategies
+/// only differ in what is loaded ahead of the extension's own scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NamespaceStrategy {
+    /// Rely on Firefox's native `chrome.*` support (no polyfill)
+    #[default]
+    Native,
+    /// Leave `chrome.*` intact and inject `browser-polyfill.js` first in the
+    /// background and every content script, so both namespaces exist everywhere
+    PolyfillOnly,
+}
+
+impl std::str::FromStr for NamespaceStrategy {
+    type Err = ParseOptionError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "native" => Ok(Self::Native),
+            "polyfill-only" | "polyfill" => Ok(Self::PolyfillOnly),
+            _ => Err(ParseOptionError::new("namespace strategy", s, "native or polyfill-only")),
+        }
+    }
+}
+
+impl std::fmt::Display for NamespaceStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Native => write!(f, "native"),
+            Self::PolyfillOnly => write!(f, "polyfill-only"),
+        }
+    }
+}
+
+/// An option value that doesn't name any variant
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown {kind} '{value}' (expected {expected})")]
+pub struct ParseOptionError {
+    kind: &'static str,
+    value: String,
+    expected: &'static str,
+}
+
+impl ParseOptionError {
+    pub fn new(kind: &'static str, value: &str, expected: &'static str) -> Self {
+        Self { kind, value: value.to_string(), expected }
+    }
+}
+
 /// JavaScript/TypeScript transformer backend selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransformerBackend {
@@ -93,11 +298,26 @@ pub enum TransformerBackend {
     Auto,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum CalculatorType {
-    TiNspire,
-    Speedcrunch,
-    Both,
+/// Number of issues `options.fail_on` counts: not auto-fixable and at or above the threshold
+fn issues_at_threshold(context: &ConversionContext, options: &ConversionOptions) -> usize {
+    let Some(threshold) = &options.fail_on else { return 0 };
+    context.incompatibilities.iter()
+        .filter(|issue| !issue.auto_fixable && issue.severity >= *threshold)
+        .count()
+}
+
+/// Why the conversion fails `options.fail_on`, `None` when it passes
+fn fail_on_error(failing: usize, options: &ConversionOptions) -> Option<String> {
+    let threshold = options.fail_on.as_ref()?;
+    if failing == 0 {
+        return None;
+    }
+    Some(format!(
+        "{} unresolved incompatibilit{} at or above {} severity (output was still written)",
+        failing,
+        if failing == 1 { "y" } else { "ies" },
+        threshold.to_string().to_lowercase()
+    ))
 }
 
 fn get_user_decisions(context: ConversionContext) -> Result<ConversionContext> {
@@ -141,4 +361,4 @@ fn apply_default_decisions(mut context: ConversionContext) -> ConversionContext
     }
     
     context
-}
\ No newline at end of file
+}
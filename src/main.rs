@@ -1,17 +1,35 @@
 //! Chrome to Firefox Extension Converter CLI
 
-use chrome2moz::{convert_extension, ConversionOptions, CalculatorType};
+use chrome2moz::{convert_extension, ConversionOptions};
 use chrome2moz::scripts::{fetch_chrome_only_apis, check_keyboard_shortcuts};
 use chrome2moz::cli::run_interactive_mode;
+use chrome2moz::report::ReportFormat;
 use clap::{Parser, Subcommand};
 use colored::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by `--quiet`: only command results and errors are printed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for progress and chatter, silenced in quiet mode
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
 
 #[derive(Parser)]
 #[command(name = "chrome-to-firefox")]
 #[command(about = "Convert Chrome MV3 extensions to Firefox-compatible MV3", long_about = None)]
 #[command(version)]
 struct Cli {
+    /// Suppress progress and informational output (command results and errors are still printed)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -20,7 +38,7 @@ struct Cli {
 enum Commands {
     /// Convert a Chrome extension to Firefox format
     Convert {
-        /// Path to the Chrome extension (ZIP, CRX, or directory)
+        /// Path to the Chrome extension (ZIP, CRX, XPI, or directory)
         #[arg(short, long)]
         input: PathBuf,
         
@@ -36,9 +54,81 @@ enum Commands {
         #[arg(short, long)]
         report: bool,
         
+        /// Report format: "markdown" (<output>.md), "html" (<output>.html) or "summary" (one line on stdout)
+        #[arg(long, value_name = "FORMAT", default_value = "markdown")]
+        report_format: chrome2moz::report::ReportFormat,
+        
         /// Preserve Chrome compatibility (keep both chrome and browser namespaces)
         #[arg(long)]
         preserve_chrome: bool,
+        
+        /// Keep the manifest "key" so a shared Chrome build keeps its ID (AMO rejects it)
+        #[arg(long)]
+        preserve_chrome_key: bool,
+        
+        /// Only transform the manifest (copy JavaScript verbatim, skip shims)
+        #[arg(long)]
+        only_manifest: bool,
+        
+        /// Also write a sources.zip with the original source for AMO review
+        #[arg(long)]
+        sources: bool,
+        
+        /// Split background scripts larger than this many bytes into several files
+        #[arg(long, value_name = "BYTES")]
+        split_background: Option<usize>,
+        
+        /// Declare required AMO data collection permissions (e.g. "none" or "browsingActivity"); raises strict_min_version to 140
+        #[arg(long, value_name = "TYPES", value_delimiter = ',')]
+        data_collection: Option<Vec<String>>,
+        
+        /// Leave matching files out of the package (e.g. ".chrome2moz-provenance.json"); repeatable
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        
+        /// Also pack .chrome2moz-provenance.json into the .xpi (it is always written to the output directory)
+        #[arg(long)]
+        package_provenance: bool,
+        
+        /// Add resources content scripts load via runtime.getURL() to web_accessible_resources
+        #[arg(long)]
+        add_web_accessible: bool,
+        
+        /// Keep content scripts' declared all_frames instead of enabling it
+        #[arg(long)]
+        preserve_all_frames: bool,
+        
+        /// Cap the threads used for per-file transforms (default: available parallelism)
+        #[arg(long, value_name = "N")]
+        threads: Option<usize>,
+        
+        /// Skip matching source files during API analysis (e.g. "vendor/**", "*.min.js"); repeatable
+        #[arg(long, value_name = "GLOB")]
+        ignore: Vec<String>,
+        
+        /// Exit non-zero when unresolved issues at or above this severity remain: blocker, major, minor or info (any issue)
+        #[arg(long, value_name = "SEVERITY")]
+        fail_on: Option<chrome2moz::models::Severity>,
+        
+        /// Directory generated shims are written to and referenced from the manifest
+        #[arg(long, value_name = "DIR", default_value = "shims")]
+        shims_dir: String,
+        
+        /// Concatenate the shims into a single compat-bundle.js
+        #[arg(long)]
+        bundle_shims: bool,
+        
+        /// Oldest Firefox major version to support (e.g. 128); shims for features it has natively are skipped
+        #[arg(long, value_name = "VERSION")]
+        since_version: Option<u32>,
+        
+        /// Output format: "xpi" (directory plus <output>.xpi) or "dir" (directory only)
+        #[arg(long, value_name = "FORMAT", default_value = "xpi")]
+        output_format: chrome2moz::PackageFormat,
+        
+        /// Keep running and re-convert whenever the input directory changes
+        #[arg(long)]
+        watch: bool,
     },
     
     /// Analyze an extension without converting
@@ -46,8 +136,62 @@ enum Commands {
         /// Path to the extension
         #[arg(short, long)]
         input: PathBuf,
+        
+        /// Skip matching source files during API analysis (e.g. "vendor/**", "*.min.js"); repeatable
+        #[arg(long, value_name = "GLOB")]
+        ignore: Vec<String>,
     },
 
+    /// List the compatibility shims a conversion would add, and why
+    ListShims {
+        /// Path to the extension
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Preserve Chrome compatibility (keep both chrome and browser namespaces)
+        #[arg(long)]
+        preserve_chrome: bool,
+        
+        /// Directory generated shims are written to and referenced from the manifest
+        #[arg(long, value_name = "DIR", default_value = "shims")]
+        shims_dir: String,
+        
+        /// Concatenate the shims into a single compat-bundle.js
+        #[arg(long)]
+        bundle_shims: bool,
+        
+        /// Oldest Firefox major version to support (e.g. 128); shims for features it has natively are skipped
+        #[arg(long, value_name = "VERSION")]
+        since_version: Option<u32>,
+    },
+
+    /// Validate converted output with addons-linter (or the built-in checks when it isn't installed)
+    Validate {
+        /// Converted extension directory or XPI
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+
+    /// Compare two converted outputs (directories or XPIs)
+    Diff {
+        /// Previous converted output
+        old: PathBuf,
+        
+        /// New converted output
+        new: PathBuf,
+    },
+
+    /// Run a single JavaScript/TypeScript file through the transformer
+    Transform {
+        /// File to transform
+        #[arg(short, long)]
+        input: PathBuf,
+        
+        /// Where to write the transformed file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    
     /// List WebExtension APIs supported in Chrome but not Firefox
     ChromeOnlyApis,
     
@@ -57,6 +201,7 @@ enum Commands {
 
 fn main() {
     let cli = Cli::parse();
+    QUIET.store(cli.quiet, Ordering::Relaxed);
     
     // If no subcommand is provided, run interactive mode
     let command = match cli.command {
@@ -71,51 +216,114 @@ fn main() {
     };
     
     match command {
-        Commands::Convert { input, output, yes, report, preserve_chrome } => {
-            println!("{}", "Chrome to Firefox Extension Converter".bold().blue());
-            println!("{}", "=".repeat(50).blue());
-            println!();
+        Commands::Convert { input, output, yes, report, report_format, preserve_chrome, preserve_chrome_key, only_manifest, sources, split_background, data_collection, exclude, package_provenance, add_web_accessible, preserve_all_frames, threads, ignore, fail_on, shims_dir, bundle_shims, since_version, output_format, watch } => {
+            let shims_dir = validated_shims_dir(&shims_dir);
+            let data_collection = data_collection.map(validated_data_collection);
+            
+            say!("{}", "Chrome to Firefox Extension Converter".bold().blue());
+            say!("{}", "=".repeat(50).blue());
+            say!();
             
             let options = ConversionOptions {
                 interactive: !yes,
-                target_calculator: CalculatorType::Both,
                 preserve_chrome_compatibility: preserve_chrome,
+                preserve_chrome_key,
                 generate_report: report,
+                only_manifest,
+                generate_source_archive: sources,
+                split_background_threshold: split_background,
+                data_collection_permissions: data_collection,
+                exclude_patterns: exclude,
+                package_provenance,
+                add_web_accessible_resources: add_web_accessible,
+                preserve_all_frames,
+                max_threads: threads,
+                analyze_ignore: ignore,
+                fail_on,
+                shims_dir,
+                bundle_shims,
+                package_format: output_format,
+                target_firefox_version: since_version,
+                ..Default::default()
             };
             
+            if watch {
+                say!("👀 Watching {} for changes (Ctrl+C to stop)", input.display());
+                let watched = chrome2moz::cli::watch::watch_and_convert(
+                    &input,
+                    &output,
+                    options,
+                    chrome2moz::cli::watch::DEFAULT_DEBOUNCE,
+                    |result| {
+                        let time = chrono::Local::now().format("%H:%M:%S");
+                        match result {
+                            Ok(result) => {
+                                say!(
+                                    "[{}] ✅ Converted: {} modified, {} added, {} warnings",
+                                    time,
+                                    result.modified_files.len(),
+                                    result.new_files.len(),
+                                    result.report.warnings.len()
+                                );
+                                if report {
+                                    write_report(&result, &output, report_format);
+                                }
+                                if let Some(error) = &result.fail_on_error {
+                                    eprintln!("{}", format!("[{}] ❌ {}", time, error).red());
+                                }
+                            }
+                            Err(e) => eprintln!("{}", format!("[{}] ❌ Conversion failed: {}", time, e).red()),
+                        }
+                        true
+                    },
+                );
+                if let Err(e) = watched {
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    std::process::exit(1);
+                }
+                return;
+            }
+            
             match convert_extension(&input, &output, options) {
                 Ok(result) => {
-                    println!("{}", "✅ Conversion completed successfully!".green().bold());
-                    println!();
-                    println!("📊 Summary:");
-                    println!("  - Files modified: {}", result.modified_files.len());
-                    println!("  - Files added: {}", result.new_files.len());
-                    println!("  - Output: {}", output.display());
+                    say!("{}", "✅ Conversion completed successfully!".green().bold());
+                    say!();
+                    say!("📊 Summary:");
+                    say!("  - Files modified: {}", result.modified_files.len());
+                    say!("  - Files added: {}", result.new_files.len());
+                    for (kind, count) in &result.report.summary.changes_by_kind {
+                        say!("  - {}: {}", kind.label(), count);
+                    }
+                    say!("  - Output: {}", output.display());
+                    if sources {
+                        say!("  - Sources: {}", chrome2moz::packager::source_archive_path(&output).display());
+                    }
                     
                     if report {
-                        let report_path = output.with_extension("md");
-                        if let Ok(report_content) = chrome2moz::report::generate_report(&result) {
-                            if std::fs::write(&report_path, report_content).is_ok() {
-                                println!("  - Report: {}", report_path.display());
-                            }
-                        }
+                        write_report(&result, &output, report_format);
                     }
                     
                     if !result.report.warnings.is_empty() {
-                        println!();
-                        println!("{}", "⚠️  Warnings:".yellow().bold());
+                        say!();
+                        say!("{}", "⚠️  Warnings:".yellow().bold());
                         for warning in &result.report.warnings {
-                            println!("  - {}", warning);
+                            say!("  - {}", warning);
                         }
                     }
                     
                     if !result.report.manual_actions.is_empty() {
-                        println!();
-                        println!("{}", "📝 Manual actions required:".yellow().bold());
+                        say!();
+                        say!("{}", "📝 Manual actions required:".yellow().bold());
                         for action in &result.report.manual_actions {
-                            println!("  - {}", action);
+                            say!("  - {}", action);
                         }
                     }
+                    
+                    // Only fail once the output and report are written, so CI still gets them
+                    if let Some(error) = &result.fail_on_error {
+                        eprintln!("{}", format!("❌ {}", error).red().bold());
+                        std::process::exit(1);
+                    }
                 }
                 Err(e) => {
                     eprintln!("{}", "❌ Conversion failed!".red().bold());
@@ -125,13 +333,14 @@ fn main() {
             }
         }
         
-        Commands::Analyze { input } => {
-            println!("{}", "Analyzing extension...".bold());
-            println!();
+        Commands::Analyze { input, ignore } => {
+            say!("{}", "Analyzing extension...".bold());
+            say!();
             
             match chrome2moz::packager::load_extension(&input) {
                 Ok(extension) => {
-                    match chrome2moz::analyze_extension(extension) {
+                    let options = chrome2moz::ConversionOptions { analyze_ignore: ignore, ..Default::default() };
+                    match chrome2moz::analyzer::analyze_extension_with_options(extension, options, &mut chrome2moz::progress::NoProgress) {
                         Ok(context) => {
                             println!("{}", "📊 Analysis Results".bold().blue());
                             println!("{}", "=".repeat(50).blue());
@@ -151,23 +360,27 @@ fn main() {
                                     context.incompatibilities.len()).yellow());
                                 println!();
                                 
-                                for issue in &context.incompatibilities {
-                                    let severity_str = match issue.severity {
-                                        chrome2moz::models::Severity::Blocker => "🛑 BLOCKER".red(),
-                                        chrome2moz::models::Severity::Major => "⚠️  MAJOR".yellow(),
-                                        chrome2moz::models::Severity::Minor => "ℹ️  MINOR".blue(),
-                                        chrome2moz::models::Severity::Info => "💡 INFO".white(),
-                                    };
+                                for (path, issues) in context.issues_by_file() {
+                                    println!("{}", format!("📄 {} ({})", path.display(), issues.len()).bold());
                                     
-                                    println!("{} [{}]", severity_str, issue.location);
-                                    println!("  {}", issue.description);
-                                    if let Some(suggestion) = &issue.suggestion {
-                                        println!("  💡 {}", suggestion.dimmed());
+                                    for issue in issues {
+                                        let severity_str = match issue.severity {
+                                            chrome2moz::models::Severity::Blocker => "🛑 BLOCKER".red(),
+                                            chrome2moz::models::Severity::Major => "⚠️  MAJOR".yellow(),
+                                            chrome2moz::models::Severity::Minor => "ℹ️  MINOR".blue(),
+                                            chrome2moz::models::Severity::Info => "💡 INFO".white(),
+                                        };
+                                        
+                                        println!("{} [{}]", severity_str, issue.location);
+                                        println!("  {}", issue.description);
+                                        if let Some(suggestion) = &issue.suggestion {
+                                            println!("  💡 {}", suggestion.dimmed());
+                                        }
+                                        if issue.auto_fixable {
+                                            println!("  {}", "✨ Auto-fixable".green());
+                                        }
+                                        println!();
                                     }
-                                    if issue.auto_fixable {
-                                        println!("  {}", "✨ Auto-fixable".green());
-                                    }
-                                    println!();
                                 }
                             }
                             
@@ -193,12 +406,133 @@ fn main() {
             }
         }
 
+        Commands::Transform { input, output } => {
+            match transform_file(&input, &output) {
+                Ok(result) => {
+                    if result.changes.is_empty() {
+                        say!("{}", "✅ No changes needed".green());
+                    } else {
+                        say!("{}", format!("{} change(s):", result.changes.len()).bold());
+                        for change in &result.changes {
+                            say!("  - Line {}: {}", change.line_number, change.description);
+                        }
+                    }
+                    say!("  - Output: {}", output.display());
+                }
+                Err(e) => {
+                    eprintln!("{}", "❌ Transform failed!".red().bold());
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::ListShims { input, preserve_chrome, shims_dir, bundle_shims, since_version } => {
+            // Same options `convert` builds, so the list matches what it ships
+            let options = ConversionOptions {
+                preserve_chrome_compatibility: preserve_chrome,
+                shims_dir: validated_shims_dir(&shims_dir),
+                bundle_shims,
+                target_firefox_version: since_version,
+                ..Default::default()
+            };
+            match chrome2moz::packager::load_extension(&input) {
+                Ok(extension) if extension.manifest.is_theme() => {
+                    println!("Theme extension: no shims are added");
+                }
+                Ok(extension) => {
+                    let mut context = chrome2moz::models::ConversionContext::new(extension);
+                    context.options = options;
+                    let planned = chrome2moz::transformer::shims::plan_shims(&context);
+                    println!("{}", format!("📦 {} shims for {} v{}", planned.len(), context.source.metadata.name, context.source.metadata.version).bold());
+                    for shim in &planned {
+                        println!("  {} - {}", shim.file.path.display().to_string().bold(), shim.file.purpose);
+                        println!("    {}", shim.reason.dimmed());
+                    }
+                    if context.options.bundle_shims && !planned.is_empty() {
+                        let bundle = chrome2moz::transformer::shims::relocate_shim_path(
+                            Path::new(chrome2moz::transformer::shims::COMPAT_BUNDLE_PATH),
+                            &context.options.shims_dir,
+                        );
+                        println!("  Bundled into {}", bundle.display().to_string().bold());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", "❌ Failed to load extension!".red().bold());
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Validate { input } => {
+            match chrome2moz::validator::validate_output(&input) {
+                Ok(validation) => {
+                    if !validation.linter_ran {
+                        say!("{}", "⚠️  addons-linter not found, ran built-in structure checks only (npm install -g addons-linter)".yellow());
+                    }
+                    for issue in &validation.issues {
+                        let location = match &issue.location {
+                            chrome2moz::models::Location::FileLocation(path, line) => format!("{}:{}", path.display(), line),
+                            chrome2moz::models::Location::File(path) => path.display().to_string(),
+                            _ => "manifest.json".to_string(),
+                        };
+                        println!("  [{}] {}: {}", issue.severity, location, issue.description);
+                    }
+                    let blockers = validation.issues.iter()
+                        .filter(|i| i.severity == chrome2moz::models::Severity::Blocker)
+                        .count();
+                    if blockers > 0 {
+                        eprintln!("{}", format!("❌ {} errors found", blockers).red().bold());
+                        std::process::exit(1);
+                    }
+                    say!("{}", "✅ Validation passed".green());
+                }
+                Err(e) => {
+                    eprintln!("{}", "❌ Validation failed!".red().bold());
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    std::process::exit(1);
+                }
+            }
+        }
+        
+        Commands::Diff { old, new } => {
+            match chrome2moz::report::diff_outputs(&old, &new) {
+                Ok(diff) => {
+                    if diff.is_empty() {
+                        println!("{}", "✅ Outputs are identical".green());
+                        return;
+                    }
+                    
+                    for path in &diff.added {
+                        println!("{} {}", "+ added:".green(), path.display());
+                    }
+                    for path in &diff.removed {
+                        println!("{} {}", "- removed:".red(), path.display());
+                    }
+                    for file in &diff.modified {
+                        println!("{} {}", "~ modified:".yellow(), file.path.display());
+                    }
+                    
+                    for file in diff.modified.iter().filter(|f| f.unified.is_some()) {
+                        println!();
+                        println!("{}", file.unified.as_deref().unwrap_or_default().trim_end());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", "❌ Diff failed!".red().bold());
+                    eprintln!("{}", format!("Error: {}", e).red());
+                    std::process::exit(1);
+                }
+            }
+        }
+
         Commands::ChromeOnlyApis => {
-            println!(
+            say!(
                 "{}",
                 "Fetching Chrome-only WebExtension APIs".bold().blue()
             );
-            println!();
+            say!();
 
             let runtime = tokio::runtime::Runtime::new()
                 .expect("failed to initialize async runtime");
@@ -211,11 +545,11 @@ fn main() {
         }
         
         Commands::CheckShortcuts => {
-            println!(
+            say!(
                 "{}",
                 "Checking Firefox Keyboard Shortcuts".bold().blue()
             );
-            println!();
+            say!();
 
             let runtime = tokio::runtime::Runtime::new()
                 .expect("failed to initialize async runtime");
@@ -231,4 +565,52 @@ fn main() {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Write the `--report` output next to `output`, or print it for the summary format
+fn write_report(result: &chrome2moz::ConversionResult, output: &Path, report_format: ReportFormat) {
+    let (report_path, report_content) = match report_format {
+        ReportFormat::Summary => {
+            // The summary is what was asked for, so `--quiet` keeps it
+            let summary = chrome2moz::report::generate_summary(result);
+            if QUIET.load(Ordering::Relaxed) {
+                println!("{}", summary);
+            } else {
+                println!("  - Report: {}", summary);
+            }
+            return;
+        }
+        ReportFormat::Html => (output.with_extension("html"), chrome2moz::report::generate_html_report(result)),
+        ReportFormat::Markdown => (output.with_extension("md"), chrome2moz::report::generate_report(result)),
+    };
+    match report_content.and_then(|content| Ok(std::fs::write(&report_path, content)?)) {
+        Ok(()) => say!("  - Report: {}", report_path.display()),
+        Err(e) => eprintln!("{}", format!("⚠️  Could not write report {}: {}", report_path.display(), e).yellow()),
+    }
+}
+
+/// Build `--data-collection` permissions, exiting on category names AMO rejects
+fn validated_data_collection(required: Vec<String>) -> chrome2moz::models::DataCollectionPermissions {
+    chrome2moz::models::DataCollectionPermissions::required(required).unwrap_or_else(|e| {
+        eprintln!("{}", format!("❌ Invalid --data-collection: {}", e).red());
+        std::process::exit(2);
+    })
+}
+
+/// Normalize `--shims-dir`, exiting when it points outside the extension
+fn validated_shims_dir(shims_dir: &str) -> String {
+    let shims_dir = shims_dir.trim_matches('/').to_string();
+    if shims_dir.is_empty() || shims_dir.split('/').any(|part| part == "..") {
+        eprintln!("{}", format!("❌ Invalid --shims-dir '{}' (expected a relative directory inside the extension)", shims_dir).red());
+        std::process::exit(2);
+    }
+    shims_dir
+}
+
+/// Run one JavaScript/TypeScript file through the transformer and write the result
+fn transform_file(input: &PathBuf, output: &Path) -> anyhow::Result<chrome2moz::models::ModifiedFile> {
+    let content = std::fs::read_to_string(input)?;
+    let result = chrome2moz::transformer::JavaScriptTransformer::new(&[]).transform(&content, input)?;
+    std::fs::write(output, &result.new_content)?;
+    Ok(result)
+}
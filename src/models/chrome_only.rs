@@ -134,7 +134,7 @@ pub enum PageCondition {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UrlFilter {
     pub host_equals: Option<String>,
     pub host_contains: Option<String>,
@@ -150,25 +150,83 @@ pub struct UrlFilter {
     pub query_suffix: Option<String>,
     pub url_matches: Option<String>,
     pub schemes: Option<Vec<String>>,
+    /// Hosts (or `.domain` suffixes) the converted content script must not run on
+    ///
+    /// Chrome's UrlFilter has no exclusions, so this is only set by callers
+    /// that build rules themselves; it becomes the script's `exclude_matches`.
+    #[serde(default)]
+    pub excluded_hosts: Vec<String>,
 }
 
 impl UrlFilter {
+    /// Closest content script match pattern for this filter
+    ///
+    /// Match patterns can't express every UrlFilter condition: host and query
+    /// substrings widen to `*`, and the page condition checker re-checks the
+    /// full filter in the page. A lone `urlMatches` is passed through unchanged.
     pub fn to_match_pattern(&self) -> String {
-        // Convert UrlFilter to a content script match pattern
-        if let Some(host) = &self.host_equals {
-            format!("*://{host}/*")
-        } else if let Some(pattern) = &self.url_matches {
-            pattern.clone()
+        let has_components = self.host_equals.is_some()
+            || self.host_suffix.is_some()
+            || self.path_equals.is_some()
+            || self.path_prefix.is_some()
+            || self.path_suffix.is_some()
+            || self.path_contains.is_some();
+        if let (Some(pattern), false) = (&self.url_matches, has_components) {
+            return pattern.clone();
+        }
+        
+        let scheme = match self.schemes.as_deref() {
+            Some([scheme]) if matches!(scheme.as_str(), "http" | "https") => scheme.as_str(),
+            _ => "*",
+        };
+        
+        let host = if let Some(host) = &self.host_equals {
+            host.clone()
+        } else if let Some(suffix) = &self.host_suffix {
+            format!("*.{}", suffix.trim_start_matches('.'))
+        } else {
+            "*".to_string()
+        };
+        
+        format!("{}://{}{}", scheme, host, self.path_pattern())
+    }
+    
+    /// Path part of the match pattern (always starts with `/`)
+    fn path_pattern(&self) -> String {
+        let with_slash = |path: &str| {
+            if path.starts_with('/') { path.to_string() } else { format!("/{}", path) }
+        };
+        
+        if let Some(path) = &self.path_equals {
+            with_slash(path)
+        } else if let Some(prefix) = &self.path_prefix {
+            format!("{}*", with_slash(prefix))
+        } else if let Some(suffix) = &self.path_suffix {
+            format!("/*{}", suffix.trim_start_matches('/'))
+        } else if let Some(contains) = &self.path_contains {
+            format!("/*{}*", contains.trim_start_matches('/'))
         } else {
-            "*://*/*".to_string()
+            "/*".to_string()
         }
     }
+    
+    /// `exclude_matches` entries for the excluded hosts
+    pub fn to_exclude_patterns(&self) -> Vec<String> {
+        self.excluded_hosts.iter()
+            .map(|host| match host.strip_prefix('.') {
+                Some(domain) => format!("*://*.{}/*", domain),
+                None => format!("*://{}/*", host),
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PageAction {
     ShowPageAction,
     SetIcon { icon_path: String },
+    /// Inject scripts/stylesheets into pages matching the rule's conditions
+    RequestContentScript { js: Vec<String>, css: Vec<String> },
 }
 
 // ============================================================================
@@ -230,6 +288,8 @@ use super::conversion::{NewFile, ModifiedFile};
 pub enum ManifestChange {
     AddContentScript {
         matches: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclude_matches: Vec<String>,
         js: Vec<String>,
         run_at: String,
     },
@@ -259,6 +319,11 @@ impl Default for ChromeOnlyConversionResult {
     }
 }
 
+/// Asks the user for content script match patterns, given suggested ones
+///
+/// Returning `None` (or no patterns) falls back to `<all_urls>`.
+pub type UrlPrompt = fn(&[String]) -> Option<Vec<String>>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversionPreferences {
     pub prefer_workers: bool,
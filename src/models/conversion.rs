@@ -1,7 +1,10 @@
 //! Conversion context and results
 
 use super::{Extension, Incompatibility, Manifest};
+use anyhow::Result;
+use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
@@ -11,6 +14,7 @@ pub struct ConversionContext {
     pub warnings: Vec<Warning>,
     pub decisions: Vec<UserDecision>,
     pub selected_decisions: Vec<SelectedDecision>,
+    pub options: crate::ConversionOptions,
 }
 
 #[derive(Debug, Clone)]
@@ -60,9 +64,143 @@ pub struct ConversionResult {
     pub modified_files: Vec<ModifiedFile>,
     pub new_files: Vec<NewFile>,
     pub report: ConversionReport,
+    /// Written as [`PROVENANCE_FILE`] unless excluded
+    pub provenance: Option<Provenance>,
+    /// Globs for output files that are not packaged (`ConversionOptions::exclude_patterns`)
+    pub exclude_patterns: Vec<String>,
+    /// Whether the provenance file goes into the `.xpi` (`ConversionOptions::package_provenance`)
+    pub package_provenance: bool,
+    /// Directory the shims were generated in (`ConversionOptions::shims_dir`)
+    pub shims_dir: String,
+    /// Set when unresolved issues reach `ConversionOptions::fail_on`; callers
+    /// finish writing outputs and reports before failing on it
+    pub fail_on_error: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ConversionResult {
+    /// Whether `path` is a generated shim
+    pub fn is_shim(&self, path: &std::path::Path) -> bool {
+        path.starts_with(&self.shims_dir)
+    }
+    
+    /// Whether `path` matches one of the output exclude patterns
+    pub fn is_excluded(&self, path: &std::path::Path) -> bool {
+        let path = path.to_string_lossy();
+        self.exclude_patterns.iter().any(|p| crate::utils::helpers::glob_match(p, &path))
+    }
+    
+    /// Serialized provenance file, `None` when absent or excluded
+    pub fn provenance_json(&self) -> Option<String> {
+        let provenance = self.provenance.as_ref()?;
+        if self.is_excluded(std::path::Path::new(PROVENANCE_FILE)) {
+            return None;
+        }
+        serde_json::to_string_pretty(provenance).ok()
+    }
+    
+    /// Serialize as a versioned JSON document (see [`RESULT_SCHEMA_VERSION`])
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    
+    /// Read a document written by [`ConversionResult::to_json`]
+    ///
+    /// Package contents aren't part of the document, so `source` only carries
+    /// the original manifest and has no files.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let document: ConversionResultDocument = serde_json::from_str(json)?;
+        if document.schema_version > RESULT_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Conversion result uses schema version {}, but this version of chrome2moz only reads up to {}",
+                document.schema_version, RESULT_SCHEMA_VERSION
+            );
+        }
+        
+        Ok(Self {
+            source: Extension::new(document.source_manifest, Default::default()),
+            manifest: document.manifest,
+            modified_files: document.modified_files,
+            new_files: document.new_files,
+            report: document.report,
+            provenance: document.provenance,
+            exclude_patterns: document.exclude_patterns,
+            package_provenance: document.package_provenance,
+            shims_dir: document.shims_dir,
+            fail_on_error: document.fail_on_error,
+        })
+    }
+}
+
+/// Version of the [`ConversionResult::to_json`] document
+///
+/// Compatible changes only add fields, each with `#[serde(default)]` so older
+/// documents still load. Removing, renaming or changing the meaning of a field
+/// bumps this version; documents from newer versions are rejected.
+///
+/// Version 2 turned `report.warnings` from strings into [`ReportWarning`] objects.
+pub const RESULT_SCHEMA_VERSION: u32 = 2;
+
+/// Serialized form: the outputs plus the source manifest, without package contents
+impl Serialize for ConversionResult {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut document = serializer.serialize_struct("ConversionResult", 11)?;
+        document.serialize_field("schema_version", &RESULT_SCHEMA_VERSION)?;
+        document.serialize_field("source_manifest", &self.source.manifest)?;
+        document.serialize_field("manifest", &self.manifest)?;
+        document.serialize_field("modified_files", &self.modified_files)?;
+        document.serialize_field("new_files", &self.new_files)?;
+        document.serialize_field("report", &self.report)?;
+        document.serialize_field("provenance", &self.provenance)?;
+        document.serialize_field("exclude_patterns", &self.exclude_patterns)?;
+        document.serialize_field("package_provenance", &self.package_provenance)?;
+        document.serialize_field("shims_dir", &self.shims_dir)?;
+        document.serialize_field("fail_on_error", &self.fail_on_error)?;
+        document.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct ConversionResultDocument {
+    schema_version: u32,
+    source_manifest: Manifest,
+    manifest: Manifest,
+    #[serde(default)]
+    modified_files: Vec<ModifiedFile>,
+    #[serde(default)]
+    new_files: Vec<NewFile>,
+    report: ConversionReport,
+    #[serde(default)]
+    provenance: Option<Provenance>,
+    #[serde(default)]
+    exclude_patterns: Vec<String>,
+    #[serde(default)]
+    package_provenance: bool,
+    #[serde(default = "default_shims_dir")]
+    shims_dir: String,
+    #[serde(default)]
+    fail_on_error: Option<String>,
+}
+
+fn default_shims_dir() -> String {
+    crate::transformer::shims::DEFAULT_SHIMS_DIR.to_string()
+}
+
+/// Name of the provenance file written into the output
+pub const PROVENANCE_FILE: &str = ".chrome2moz-provenance.json";
+
+/// Which tool version produced an output, from what input and how
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub tool_version: String,
+    pub generated_at: String,
+    /// FNV-1a hash over the sorted source paths and contents
+    pub input_hash: String,
+    pub decisions: Vec<String>,
+    pub transforms: Vec<String>,
+    pub shims: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModifiedFile {
     pub path: PathBuf,
     pub original_content: String,
@@ -70,17 +208,21 @@ pub struct ModifiedFile {
     pub changes: Vec<FileChange>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct NewFile {
     pub path: PathBuf,
     pub content: String,
     pub purpose: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileChange {
     pub line_number: usize,
     pub change_type: ChangeType,
+    /// What kind of rewrite this is, for aggregating counts
+    #[serde(default)]
+    pub kind: ChangeKind,
+    /// Human-readable detail
     pub description: String,
     pub old_code: Option<String>,
     pub new_code: Option<String>,
@@ -93,17 +235,139 @@ pub enum ChangeType {
     Deletion,
 }
 
-#[derive(Debug, Clone)]
+/// Category of a [`FileChange`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+pub enum ChangeKind {
+    /// `chrome.*` rewritten to `browser.*`
+    NamespaceRewrite,
+    /// Callback-style call turned into a promise
+    CallbackToPromise,
+    /// MV2 `tabs.executeScript`/`insertCSS` moved to the scripting API
+    ExecuteScriptMigration,
+    /// Call or option mapped to its Firefox equivalent
+    ApiRemap,
+    /// Compatibility shim or handler code added to the file
+    ShimInjected,
+    #[default]
+    Other,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeKind::NamespaceRewrite => "Namespace rewrites",
+            ChangeKind::CallbackToPromise => "Callback→Promise conversions",
+            ChangeKind::ExecuteScriptMigration => "executeScript migrations",
+            ChangeKind::ApiRemap => "API remaps",
+            ChangeKind::ShimInjected => "Shim injections",
+            ChangeKind::Other => "Other changes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConversionReport {
     pub summary: ReportSummary,
     pub manifest_changes: Vec<String>,
     pub javascript_changes: Vec<String>,
     pub blockers: Vec<String>,
     pub manual_actions: Vec<String>,
-    pub warnings: Vec<String>,
+    pub warnings: Vec<ReportWarning>,
+    /// Analysis issues grouped by file ([`ConversionContext::issues_by_file`])
+    #[serde(default)]
+    pub issues_by_file: BTreeMap<String, Vec<ReportIssue>>,
+    /// Narrower replacements for broad host permissions
+    #[serde(default)]
+    pub permission_suggestions: Vec<crate::analyzer::permissions::PermissionSuggestion>,
 }
 
-#[derive(Debug, Clone)]
+impl ConversionReport {
+    /// Rough 0-100 confidence that the converted extension works unchanged:
+    /// each blocker costs 25 points, each manual action 5 and each warning 1
+    ///
+    /// The weights are a heuristic, not a measurement. A blocker is a feature
+    /// that won't work in Firefox, so four of them bottom out the score; a
+    /// manual action usually needs a small code change; warnings rarely affect
+    /// behaviour and only separate otherwise equal results.
+    pub fn confidence(&self) -> u8 {
+        let penalty = self.blockers.len() * 25 + self.manual_actions.len() * 5 + self.warnings.len();
+        100usize.saturating_sub(penalty) as u8
+    }
+}
+
+/// An analysis issue as listed in the report's per-file section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportIssue {
+    /// [`Severity`](super::Severity) as displayed, e.g. `MAJOR`
+    pub severity: String,
+    /// Line number or manifest field within the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    pub description: String,
+}
+
+impl From<&Incompatibility> for ReportIssue {
+    fn from(issue: &Incompatibility) -> Self {
+        let position = match &issue.location {
+            super::Location::FileLocation(_, line) => Some(format!("Line {}", line)),
+            super::Location::ManifestField(field) => Some(field.clone()),
+            _ => None,
+        };
+        Self { severity: issue.severity.to_string(), position, description: issue.description.clone() }
+    }
+}
+
+/// A report warning and the file it concerns, if known
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReportWarning {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl ReportWarning {
+    pub fn new(message: impl Into<String>, location: Option<String>) -> Self {
+        Self { message: message.into(), location }
+    }
+}
+
+impl From<&Warning> for ReportWarning {
+    fn from(warning: &Warning) -> Self {
+        Self::new(warning.message.clone(), warning.location.clone())
+    }
+}
+
+impl std::fmt::Display for ReportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Also reads the plain strings written by schema version 1
+impl<'de> Deserialize<'de> for ReportWarning {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Message(String),
+            Located {
+                message: String,
+                #[serde(default)]
+                location: Option<String>,
+            },
+        }
+        
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Message(message) => Self::new(message, None),
+            Stored::Located { message, location } => Self::new(message, location),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReportSummary {
     pub extension_name: String,
     pub extension_version: String,
@@ -113,6 +377,19 @@ pub struct ReportSummary {
     pub total_changes: usize,
     pub chrome_api_calls_converted: usize,
     pub callback_to_promise_conversions: usize,
+    /// Number of file changes of each kind
+    pub changes_by_kind: BTreeMap<ChangeKind, usize>,
+}
+
+impl ReportSummary {
+    /// Count the changes of each kind across `files`
+    pub fn count_changes_by_kind(files: &[ModifiedFile]) -> BTreeMap<ChangeKind, usize> {
+        let mut counts = BTreeMap::new();
+        for change in files.iter().flat_map(|f| &f.changes) {
+            *counts.entry(change.kind).or_insert(0) += 1;
+        }
+        counts
+    }
 }
 
 impl ConversionContext {
@@ -123,9 +400,15 @@ impl ConversionContext {
             warnings: Vec::new(),
             decisions: Vec::new(),
             selected_decisions: Vec::new(),
+            options: crate::ConversionOptions::default(),
         }
     }
     
+    /// Whether the targeted Firefox version (`ConversionOptions::target_firefox_version`) has `feature`
+    pub fn supports(&self, feature: crate::transformer::capabilities::Feature) -> bool {
+        feature.supported_in(self.options.target_firefox_version)
+    }
+    
     pub fn add_incompatibility(&mut self, incompatibility: Incompatibility) {
         self.incompatibilities.push(incompatibility);
     }
@@ -142,4 +425,22 @@ impl ConversionContext {
             .iter()
             .any(|i| matches!(i.severity, super::incompatibility::Severity::Blocker))
     }
-}
\ No newline at end of file
+    
+    /// Incompatibilities grouped by the file their location refers to
+    ///
+    /// Manifest-level issues are grouped under [`MANIFEST_ISSUES_KEY`].
+    pub fn issues_by_file(&self) -> BTreeMap<PathBuf, Vec<&Incompatibility>> {
+        let mut grouped: BTreeMap<PathBuf, Vec<&Incompatibility>> = BTreeMap::new();
+        for issue in &self.incompatibilities {
+            let key = match &issue.location {
+                super::Location::File(path) | super::Location::FileLocation(path, _) => path.clone(),
+                super::Location::Manifest | super::Location::ManifestField(_) => PathBuf::from(MANIFEST_ISSUES_KEY),
+            };
+            grouped.entry(key).or_default().push(issue);
+        }
+        grouped
+    }
+}
+
+/// Group key used by [`ConversionContext::issues_by_file`] for manifest-level issues
+pub const MANIFEST_ISSUES_KEY: &str = "manifest.json";
@@ -1,16 +1,73 @@
 //! Extension representation and metadata
 
 use super::manifest::Manifest;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub struct Extension {
     pub manifest: Manifest,
+    /// Files loaded for analysis and transformation
     pub files: HashMap<PathBuf, Vec<u8>>,
+    /// Archive entries copied to the output without being loaded
+    pub deferred: DeferredFiles,
     pub metadata: ExtensionMetadata,
 }
 
+/// Entries of a source archive that are left compressed until the output is built
+#[derive(Debug, Clone, Default)]
+pub struct DeferredFiles {
+    pub archive: PathBuf,
+    pub entries: BTreeMap<PathBuf, DeferredEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeferredEntry {
+    /// Entry name inside the archive
+    pub name: String,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+impl DeferredFiles {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    
+    /// Stream every entry, in path order, through `visit` (the archive is opened once)
+    pub fn for_each(&self, mut visit: impl FnMut(&Path, &mut dyn Read) -> Result<()>) -> Result<()> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let mut archive = self.open()?;
+        for (path, entry) in &self.entries {
+            let mut file = archive.by_name(&entry.name)
+                .with_context(|| format!("Failed to read {} from archive", entry.name))?;
+            visit(path, &mut file)?;
+        }
+        Ok(())
+    }
+    
+    /// Decompress a single entry into memory
+    pub fn read(&self, path: &Path) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.get(path) else { return Ok(None) };
+        let mut archive = self.open()?;
+        let mut file = archive.by_name(&entry.name)
+            .with_context(|| format!("Failed to read {} from archive", entry.name))?;
+        let mut content = Vec::with_capacity(entry.size as usize);
+        file.read_to_end(&mut content)?;
+        Ok(Some(content))
+    }
+    
+    fn open(&self) -> Result<zip::ZipArchive<std::fs::File>> {
+        let file = std::fs::File::open(&self.archive)
+            .with_context(|| format!("Failed to reopen {}", self.archive.display()))?;
+        zip::ZipArchive::new(file).context("Failed to read ZIP archive")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExtensionMetadata {
     pub name: String,
@@ -47,10 +104,32 @@ impl Extension {
         Self {
             manifest,
             files,
+            deferred: DeferredFiles::default(),
             metadata,
         }
     }
     
+    /// Attach archive entries that weren't loaded into `files`
+    pub fn with_deferred_files(mut self, deferred: DeferredFiles) -> Self {
+        self.metadata.size_bytes += deferred.entries.values().map(|e| e.size as usize).sum::<usize>();
+        self.metadata.file_count += deferred.entries.len();
+        self.deferred = deferred;
+        self
+    }
+    
+    /// Whether the package contains `path`, loaded or not
+    pub fn has_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path) || self.deferred.entries.contains_key(path)
+    }
+    
+    /// Content of any packaged file, decompressing deferred entries on demand
+    pub fn read_file(&self, path: &Path) -> Option<Vec<u8>> {
+        match self.files.get(path) {
+            Some(content) => Some(content.clone()),
+            None => self.deferred.read(path).ok().flatten(),
+        }
+    }
+    
     /// Count total lines in all text files
     fn count_lines(files: &HashMap<PathBuf, Vec<u8>>) -> usize {
         files.iter()
@@ -95,6 +174,13 @@ impl Extension {
         })
     }
     
+    /// Whether any JavaScript file contains a match for `re`
+    pub fn javascript_matches(&self, re: &regex::Regex) -> bool {
+        self.get_javascript_files().iter()
+            .filter_map(|path| self.get_file_content(path))
+            .any(|content| re.is_match(&content))
+    }
+    
     /// Get background script paths
     pub fn get_background_scripts(&self) -> Vec<PathBuf> {
         let mut scripts = Vec::new();
@@ -119,6 +205,43 @@ impl Extension {
             .flat_map(|cs| cs.js.iter().map(PathBuf::from))
             .collect()
     }
+    
+    /// Get the manifest's `default_locale`, if any
+    pub fn default_locale(&self) -> Option<&str> {
+        self.manifest.extra.get("default_locale").and_then(|v| v.as_str())
+    }
+    
+    /// Get the parsed `_locales/<default_locale>/messages.json`, if present and valid
+    pub fn default_locale_messages(&self) -> Option<serde_json::Map<String, serde_json::Value>> {
+        let locale = self.default_locale()?;
+        let path = PathBuf::from("_locales").join(locale).join("messages.json");
+        let content = self.get_file_content(&path)?;
+        match serde_json::from_str(content.trim_start_matches('\u{feff}')) {
+            Ok(serde_json::Value::Object(messages)) => Some(messages),
+            _ => None,
+        }
+    }
+    
+    /// Resolve a `__MSG_name__` manifest value against the default locale
+    ///
+    /// Values that aren't message references (or can't be resolved) are returned unchanged.
+    pub fn resolve_localized(&self, value: &str) -> String {
+        let key = match value.strip_prefix("__MSG_").and_then(|v| v.strip_suffix("__")) {
+            Some(key) => key,
+            None => return value.to_string(),
+        };
+        
+        // Message names are case-insensitive
+        self.default_locale_messages()
+            .and_then(|messages| {
+                messages.iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                    .and_then(|(_, entry)| entry.get("message"))
+                    .and_then(|message| message.as_str())
+                    .map(|message| message.to_string())
+            })
+            .unwrap_or_else(|| value.to_string())
+    }
 }
 
 #[derive(Debug, Clone)]
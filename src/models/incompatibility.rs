@@ -28,6 +28,8 @@ pub enum IncompatibilityCategory {
     ApiNamespace,
     CallbackVsPromise,
     HostPermissions,
+    /// API permission the code needs but the manifest doesn't declare
+    Permissions,
     WebRequest,
     WebAccessibleResources,
     ContentSecurityPolicy,
@@ -36,6 +38,12 @@ pub enum IncompatibilityCategory {
     VersionFormat,
     ImportScripts,
     ServiceWorkerLifecycle,
+    /// API exists in Firefox but behaves differently
+    BehaviorDifference,
+    /// Code depends on the Chrome extension ID, which differs from the gecko ID
+    ExtensionId,
+    /// Reported by addons-linter on converted output
+    LinterFinding,
 }
 
 #[derive(Debug, Clone)]
@@ -85,6 +93,20 @@ impl std::fmt::Display for Severity {
     }
 }
 
+impl std::str::FromStr for Severity {
+    type Err = crate::ParseOptionError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "minor" => Ok(Severity::Minor),
+            "major" => Ok(Severity::Major),
+            "blocker" => Ok(Severity::Blocker),
+            _ => Err(crate::ParseOptionError::new("severity", s, "blocker, major, minor or info")),
+        }
+    }
+}
+
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,9 +1,10 @@
 //! Manifest data structures for Chrome and Firefox extensions
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Manifest {
     pub manifest_version: u8,
     pub name: String,
@@ -45,11 +46,24 @@ pub struct Manifest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commands: Option<HashMap<String, Command>>,
     
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omnibox: Option<Omnibox>,
+    
+    /// Fields without a typed counterpart, in source order
     #[serde(flatten)]
-    pub extra: HashMap<String, serde_json::Value>,
+    pub extra: IndexMap<String, serde_json::Value>,
+}
+
+impl Manifest {
+    /// Theme-only extension: a top-level `theme` and no background or content scripts
+    pub fn is_theme(&self) -> bool {
+        self.extra.contains_key("theme")
+            && self.background.is_none()
+            && self.content_scripts.is_empty()
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Background {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_worker: Option<String>,
@@ -64,7 +78,7 @@ pub struct Background {
     pub type_: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Action {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_popup: Option<String>,
@@ -79,14 +93,14 @@ pub struct Action {
     pub browser_style: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum IconSet {
     Single(String),
     Multiple(HashMap<String, String>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContentScript {
     pub matches: Vec<String>,
     
@@ -96,21 +110,33 @@ pub struct ContentScript {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub css: Vec<String>,
     
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_matches: Vec<String>,
+    
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_globs: Vec<String>,
+    
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_globs: Vec<String>,
+    
     #[serde(skip_serializing_if = "Option::is_none")]
     pub run_at: Option<String>,
     
     #[serde(default)]
     pub all_frames: bool,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_about_blank: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum WebAccessibleResources {
     V2(Vec<String>),
     V3(Vec<WebAccessibleResourceV3>),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WebAccessibleResourceV3 {
     pub resources: Vec<String>,
     
@@ -124,14 +150,14 @@ pub struct WebAccessibleResourceV3 {
     pub use_dynamic_url: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ContentSecurityPolicy {
     V2(String),
     V3(ContentSecurityPolicyV3),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ContentSecurityPolicyV3 {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extension_pages: Option<String>,
@@ -140,13 +166,13 @@ pub struct ContentSecurityPolicyV3 {
     pub sandbox: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BrowserSpecificSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub gecko: Option<GeckoSettings>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GeckoSettings {
     pub id: String,
     
@@ -155,9 +181,70 @@ pub struct GeckoSettings {
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub strict_max_version: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection_permissions: Option<DataCollectionPermissions>,
+}
+
+/// AMO data collection declaration (`gecko.data_collection_permissions`, Firefox 140+)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataCollectionPermissions {
+    #[serde(default)]
+    pub required: Vec<String>,
+    
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub optional: Vec<String>,
+}
+
+/// Categories AMO accepts in `data_collection_permissions.required`
+pub const DATA_COLLECTION_CATEGORIES: &[&str] = &[
+    "none",
+    "authenticationInfo",
+    "bookmarksInfo",
+    "browsingActivity",
+    "financialAndPaymentInfo",
+    "healthInfo",
+    "locationInfo",
+    "personalCommunications",
+    "personallyIdentifyingInfo",
+    "searchTerms",
+    "websiteActivity",
+    "websiteContent",
+];
+
+impl DataCollectionPermissions {
+    /// Declares `categories` as required, rejecting names AMO doesn't know
+    pub fn required(categories: Vec<String>) -> Result<Self, crate::ParseOptionError> {
+        if let Some(unknown) = categories.iter().find(|c| !DATA_COLLECTION_CATEGORIES.contains(&c.as_str())) {
+            return Err(crate::ParseOptionError::new(
+                "data collection category",
+                unknown,
+                "none or one of the AMO categories, e.g. browsingActivity, websiteContent",
+            ));
+        }
+        Ok(Self { required: categories, optional: Vec::new() })
+    }
+    
+    /// Declares that the extension collects no data (`{ "required": ["none"] }`)
+    pub fn none() -> Self {
+        Self {
+            required: vec!["none".to_string()],
+            optional: Vec::new(),
+        }
+    }
+}
+
+/// Address bar keyword registration (`omnibox`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Omnibox {
+    #[serde(default)]
+    pub keyword: String,
+    
+    #[serde(flatten)]
+    pub extra: IndexMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Command {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub suggested_key: Option<HashMap<String, String>>,
@@ -1,6 +1,6 @@
 //! Firefox extension package builder
 
-use crate::models::ConversionResult;
+use crate::models::{ConversionResult, PROVENANCE_FILE};
 use crate::utils::replace_chrome_urls;
 use anyhow::{Context, Result};
 use std::fs::{self, File};
@@ -21,25 +21,29 @@ pub fn build_xpi(result: &ConversionResult, output_path: &Path) -> Result<()> {
 }
 
 pub fn create_zip_from_directory(source_dir: &Path, zip_path: &Path) -> Result<()> {
+    create_zip_from_directory_excluding(source_dir, zip_path, &[])
+}
+
+/// Like [`create_zip_from_directory`], leaving out the files at `excluded` (relative paths)
+pub fn create_zip_from_directory_excluding(source_dir: &Path, zip_path: &Path, excluded: &[&Path]) -> Result<()> {
     use walkdir::WalkDir;
     
     let file = File::create(zip_path)
         .context("Failed to create ZIP file")?;
     let mut zip = ZipWriter::new(file);
+    let options = zip_options();
     
-    let options = FileOptions::default()
-        .compression_method(CompressionMethod::Deflated)
-        .unix_permissions(0o755);
-    
+    // Stream each file so large assets are never held in memory
     for entry in WalkDir::new(source_dir).into_iter().filter_map(|e| e.ok()) {
         let path = entry.path();
         if path.is_file() {
             let relative_path = path.strip_prefix(source_dir)
                 .context("Failed to get relative path")?;
-            
-            zip.start_file(relative_path.to_string_lossy().as_ref(), options)?;
-            let content = fs::read(path)?;
-            zip.write_all(&content)?;
+            if excluded.contains(&relative_path) {
+                continue;
+            }
+            zip.start_file(relative_path.to_string_lossy(), options)?;
+            std::io::copy(&mut File::open(path)?, &mut zip)?;
         }
     }
     
@@ -47,6 +51,237 @@ pub fn create_zip_from_directory(source_dir: &Path, zip_path: &Path) -> Result<(
     Ok(())
 }
 
+fn zip_options() -> FileOptions {
+    FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .unix_permissions(0o755)
+}
+
+/// Write `(archive path, content)` entries to a new ZIP file
+pub fn write_zip<I, C>(zip_path: &Path, entries: I) -> Result<()>
+where
+    I: IntoIterator<Item = (String, C)>,
+    C: AsRef<[u8]>,
+{
+    let file = File::create(zip_path)
+        .context("Failed to create ZIP file")?;
+    let mut zip = ZipWriter::new(file);
+    let options = zip_options();
+    
+    for (name, content) in entries {
+        zip.start_file(name, options)?;
+        zip.write_all(content.as_ref())?;
+    }
+    
+    zip.finish()?;
+    Ok(())
+}
+
+/// Build the converted extension as in-memory XPI (ZIP) bytes
+pub fn build_xpi_bytes(result: &ConversionResult) -> Result<Vec<u8>> {
+    use std::collections::HashSet;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+    
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    
+    // FileOptions without time feature (WASM compatible)
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .unix_permissions(0o755);
+    
+    // Track which files have been written
+    let mut written_files = HashSet::new();
+    
+    // 1. Write manifest.json
+    let manifest_json = serde_json::to_string_pretty(&result.manifest)?;
+    zip.start_file("manifest.json", options)?;
+    zip.write_all(manifest_json.as_bytes())?;
+    written_files.insert(PathBuf::from("manifest.json"));
+    
+    // 2. Write modified files
+    for modified in result.modified_files.iter().filter(|f| !result.is_excluded(&f.path)) {
+        zip.start_file(modified.path.to_string_lossy(), options)?;
+        zip.write_all(modified.new_content.as_bytes())?;
+        written_files.insert(modified.path.clone());
+    }
+    
+    // 3. Write new files (shims)
+    for new_file in result.new_files.iter().filter(|f| !result.is_excluded(&f.path)) {
+        zip.start_file(new_file.path.to_string_lossy(), options)?;
+        zip.write_all(new_file.content.as_bytes())?;
+        written_files.insert(new_file.path.clone());
+    }
+    
+    if let Some(provenance) = result.provenance_json().filter(|_| result.package_provenance) {
+        zip.start_file(PROVENANCE_FILE, options)?;
+        zip.write_all(provenance.as_bytes())?;
+        written_files.insert(PathBuf::from(PROVENANCE_FILE));
+    }
+    
+    // 4. Copy all other original files
+    for (path, content) in &result.source.files {
+        if !written_files.contains(path) && !result.is_excluded(path) {
+            zip.start_file(path.to_string_lossy(), options)?;
+            zip.write_all(content)?;
+        }
+    }
+    result.source.deferred.for_each(|path, reader| {
+        if !written_files.contains(path) && !result.is_excluded(path) {
+            zip.start_file(path.to_string_lossy(), options)?;
+            std::io::copy(reader, &mut zip)?;
+        }
+        Ok(())
+    })?;
+    
+    let cursor = zip.finish()?;
+    Ok(cursor.into_inner())
+}
+
+/// Build an AMO source archive: the original (pre-transform) tree plus a README
+/// describing how the submitted package was produced from it with `options`
+pub fn build_source_archive(
+    source_extension: &crate::models::Extension,
+    options: &crate::ConversionOptions,
+    zip_path: &Path,
+) -> Result<()> {
+    let mut paths: Vec<_> = source_extension.files.keys().collect();
+    paths.sort();
+    
+    let file = File::create(zip_path)
+        .context("Failed to create ZIP file")?;
+    let mut zip = ZipWriter::new(file);
+    let zip_options = zip_options();
+    
+    for path in paths {
+        zip.start_file(path.to_string_lossy().replace('\\', "/"), zip_options)?;
+        zip.write_all(&source_extension.files[path])?;
+    }
+    source_extension.deferred.for_each(|path, reader| {
+        zip.start_file(path.to_string_lossy().replace('\\', "/"), zip_options)?;
+        std::io::copy(reader, &mut zip)?;
+        Ok(())
+    })?;
+    zip.start_file("README.md", zip_options)?;
+    zip.write_all(source_readme(source_extension, options).as_bytes())?;
+    
+    zip.finish()?;
+    Ok(())
+}
+
+fn source_readme(source_extension: &crate::models::Extension, options: &crate::ConversionOptions) -> String {
+    let tool_version = env!("CARGO_PKG_VERSION");
+    let output = match options.package_format {
+        crate::PackageFormat::Xpi => "The package is written to `converted.xpi`",
+        crate::PackageFormat::Directory => "The extension is written to the `converted/` directory",
+    };
+    let mut lines = vec![
+        format!("# Source code for {} {}", source_extension.metadata.name, source_extension.metadata.version),
+        String::new(),
+        "This archive contains the original, unmodified Chrome extension source.".to_string(),
+        format!("The Firefox package was generated from it with chrome2moz {}", tool_version),
+        format!("({}); there is no other build step.", env!("CARGO_PKG_REPOSITORY")),
+        String::new(),
+        "## Reproducing the submitted package".to_string(),
+        String::new(),
+        format!("1. Build chrome2moz {} from the repository above (`cargo build --release`)", tool_version),
+        "2. Extract this archive into a directory, e.g. `source/`".to_string(),
+        format!("3. Run `{}`", convert_command(options)),
+        format!("4. {}", output),
+    ];
+    if options.interactive {
+        lines.push(String::new());
+        lines.push(
+            "The conversion was run interactively: answer the prompts as listed under `decisions` in `.chrome2moz-provenance.json`."
+                .to_string(),
+        );
+    }
+    if options.offscreen_url_prompt.is_some() {
+        lines.push(String::new());
+        lines.push(
+            "Offscreen DOM content script matches were entered at a prompt: use the `matches` of `content-scripts/dom-parser.js` in the converted manifest."
+                .to_string(),
+        );
+    }
+    if !options.preserve_chrome_compatibility && options.namespace_strategy != crate::NamespaceStrategy::default() {
+        lines.push(String::new());
+        lines.push(format!(
+            "The library option `namespace_strategy` was set to `{:?}`, which the CLI has no flag for.",
+            options.namespace_strategy
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// The `chrome2moz convert` command line that reproduces a conversion run with `options`
+///
+/// Only options that change the package are rendered.
+fn convert_command(options: &crate::ConversionOptions) -> String {
+    let mut args = vec!["chrome2moz convert --input source --output converted".to_string()];
+    let mut flag = |name: &str, value: Option<String>| {
+        args.push(match value {
+            Some(value) => format!("--{} {}", name, shell_quote(&value)),
+            None => format!("--{}", name),
+        });
+    };
+    
+    if !options.interactive {
+        flag("yes", None);
+    }
+    if options.preserve_chrome_compatibility {
+        flag("preserve-chrome", None);
+    }
+    if options.preserve_chrome_key {
+        flag("preserve-chrome-key", None);
+    }
+    if options.only_manifest {
+        flag("only-manifest", None);
+    }
+    if let Some(threshold) = options.split_background_threshold {
+        flag("split-background", Some(threshold.to_string()));
+    }
+    if let Some(permissions) = &options.data_collection_permissions {
+        flag("data-collection", Some(permissions.required.join(",")));
+    }
+    for pattern in &options.exclude_patterns {
+        flag("exclude", Some(pattern.clone()));
+    }
+    if options.package_provenance {
+        flag("package-provenance", None);
+    }
+    if options.add_web_accessible_resources {
+        flag("add-web-accessible", None);
+    }
+    if options.preserve_all_frames {
+        flag("preserve-all-frames", None);
+    }
+    for pattern in &options.analyze_ignore {
+        flag("ignore", Some(pattern.clone()));
+    }
+    if options.shims_dir != crate::transformer::shims::DEFAULT_SHIMS_DIR {
+        flag("shims-dir", Some(options.shims_dir.clone()));
+    }
+    if options.bundle_shims {
+        flag("bundle-shims", None);
+    }
+    if let Some(version) = options.target_firefox_version {
+        flag("since-version", Some(version.to_string()));
+    }
+    if options.package_format == crate::PackageFormat::Directory {
+        flag("output-format", Some("dir".to_string()));
+    }
+    args.join(" ")
+}
+
+/// Quote `value` for a POSIX shell when it contains anything but plain characters
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./,=:@".contains(c)) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
 /// Check if file is a text file that should have URL replacement
 fn is_text_file(path: &Path) -> bool {
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
@@ -119,7 +354,7 @@ pub fn build_complete_directory(
             continue; // Skip manifest, we'll write the transformed one
         }
         
-        if !modified_paths.contains(path) {
+        if !modified_paths.contains(path) && !result.is_excluded(path) {
             let dest_path = output_path.join(path);
             if let Some(parent) = dest_path.parent() {
                 fs::create_dir_all(parent)?;
@@ -140,12 +375,24 @@ pub fn build_complete_directory(
         }
     }
     
+    // Entries left in the source archive are streamed out unchanged
+    source_extension.deferred.for_each(|path, reader| {
+        if !modified_paths.contains(path) && !result.is_excluded(path) {
+            let dest_path = output_path.join(path);
+            if let Some(parent) = dest_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            std::io::copy(reader, &mut File::create(dest_path)?)?;
+        }
+        Ok(())
+    })?;
+    
     // 2. Write transformed manifest
     let manifest_json = serde_json::to_string_pretty(&result.manifest)?;
     fs::write(output_path.join("manifest.json"), manifest_json)?;
     
     // 3. Write modified files
-    for modified in &result.modified_files {
+    for modified in result.modified_files.iter().filter(|f| !result.is_excluded(&f.path)) {
         let file_path = output_path.join(&modified.path);
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent)?;
@@ -154,7 +401,7 @@ pub fn build_complete_directory(
     }
     
     // 4. Write new files (shims)
-    for new_file in &result.new_files {
+    for new_file in result.new_files.iter().filter(|f| !result.is_excluded(&f.path)) {
         let file_path = output_path.join(&new_file.path);
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent)?;
@@ -162,5 +409,10 @@ pub fn build_complete_directory(
         fs::write(file_path, &new_file.content)?;
     }
     
+    // 5. Record how the output was produced
+    if let Some(provenance) = result.provenance_json() {
+        fs::write(output_path.join(PROVENANCE_FILE), provenance)?;
+    }
+    
     Ok(())
 }
\ No newline at end of file
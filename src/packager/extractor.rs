@@ -1,10 +1,11 @@
 //! Extension extraction from archives and directories
 
-use crate::models::Extension;
+use crate::models::{DeferredEntry, DeferredFiles, Extension};
 use crate::parser::manifest::parse_manifest;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::fs;
+use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 use zip::ZipArchive;
@@ -36,43 +37,107 @@ pub fn load_from_directory(dir: &Path) -> Result<Extension> {
     Ok(Extension::new(manifest, files))
 }
 
+/// Whether an archive entry is read by the analyzers or transformers
+///
+/// Everything else (images, fonts, wasm, ...) is left in the archive and
+/// streamed into the output.
+pub fn is_analyzed_file(path: &Path) -> bool {
+    if path.starts_with("_locales") {
+        return true;
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase());
+    matches!(
+        ext.as_deref(),
+        Some("js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" | "json" | "html" | "htm" | "css" | "xml" | "svg" | "txt" | "md")
+    )
+}
+
 /// Load extension from ZIP or CRX archive
+///
+/// Only [`is_analyzed_file`] entries are decompressed; the rest become
+/// [`DeferredFiles`] that the builder copies straight from the archive.
 pub fn load_from_archive(archive_path: &Path) -> Result<Extension> {
     let file = fs::File::open(archive_path)
         .context("Failed to open archive")?;
     
-    let mut archive = ZipArchive::new(file)
+    let archive = ZipArchive::new(file)
         .context("Failed to read ZIP archive")?;
     
+    load_zip(archive, Some(archive_path))
+}
+
+/// Load extension from ZIP bytes held in memory
+pub fn load_from_bytes(zip_data: &[u8]) -> Result<Extension> {
+    let archive = ZipArchive::new(std::io::Cursor::new(zip_data))
+        .context("Failed to read ZIP archive")?;
+    
+    load_zip(archive, None)
+}
+
+/// Read every file under the extension root, deferring non-analyzed entries
+/// when the archive can be reopened from `deferred_from`
+fn load_zip<R: Read + Seek>(mut archive: ZipArchive<R>, deferred_from: Option<&Path>) -> Result<Extension> {
+    let root = extension_root(archive.file_names())?;
+    
     let mut files = HashMap::new();
-    let mut manifest_content = None;
+    let mut deferred = DeferredFiles {
+        archive: deferred_from.map(Path::to_path_buf).unwrap_or_default(),
+        entries: Default::default(),
+    };
     
-    // Extract all files
     for i in 0..archive.len() {
         let mut file = archive.by_index(i)
             .context("Failed to read file from archive")?;
         
-        if file.is_file() {
-            let path = PathBuf::from(file.name());
-            let mut content = Vec::new();
-            std::io::copy(&mut file, &mut content)
-                .context("Failed to read file content")?;
-            
-            // Save manifest content separately
-            if path.file_name().and_then(|n| n.to_str()) == Some("manifest.json") {
-                manifest_content = Some(content.clone());
-            }
-            
-            files.insert(path, content);
+        if !file.is_file() {
+            continue;
+        }
+        // Files outside the extension root aren't part of the extension
+        let Some(relative) = file.name().strip_prefix(root.as_str()) else { continue };
+        let path = PathBuf::from(relative);
+        
+        if deferred_from.is_some() && !is_analyzed_file(&path) {
+            deferred.entries.insert(path, DeferredEntry {
+                name: file.name().to_string(),
+                size: file.size(),
+                crc32: file.crc32(),
+            });
+            continue;
         }
+        
+        let mut content = Vec::new();
+        std::io::copy(&mut file, &mut content)
+            .context("Failed to read file content")?;
+        files.insert(path, content);
     }
     
-    // Parse manifest
-    let manifest = manifest_content
+    let manifest = files.get(Path::new("manifest.json"))
         .ok_or_else(|| anyhow::anyhow!("manifest.json not found in archive"))
-        .and_then(|content| parse_manifest(&content))?;
+        .and_then(|content| parse_manifest(content))?;
     
-    Ok(Extension::new(manifest, files))
+    Ok(Extension::new(manifest, files).with_deferred_files(deferred))
+}
+
+/// Directory prefix (`""` or `"my-ext/"`) of the archive's `manifest.json`
+///
+/// A root manifest wins; otherwise exactly one nested manifest must exist.
+fn extension_root<'a>(names: impl Iterator<Item = &'a str>) -> Result<String> {
+    let mut candidates: Vec<&str> = names
+        .filter(|name| *name == "manifest.json" || name.ends_with("/manifest.json"))
+        .collect();
+    if candidates.contains(&"manifest.json") {
+        return Ok(String::new());
+    }
+    candidates.sort();
+    
+    match candidates.as_slice() {
+        [] => anyhow::bail!("manifest.json not found in archive"),
+        [manifest] => Ok(manifest.trim_end_matches("manifest.json").to_string()),
+        _ => anyhow::bail!(
+            "Archive contains several nested manifests, so the extension root is ambiguous: {}",
+            candidates.join(", ")
+        ),
+    }
 }
 
 #[cfg(test)]
@@ -96,4 +161,38 @@ mod tests {
         assert_eq!(extension.manifest.name, "Test");
         assert_eq!(extension.files.len(), 2);
     }
-}
\ No newline at end of file
+    
+    fn zip_with(entries: &[(&str, &str)]) -> Vec<u8> {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+    
+    #[test]
+    fn test_nested_manifest_becomes_root() {
+        let data = zip_with(&[
+            ("my-ext/manifest.json", r#"{"manifest_version": 3, "name": "Nested", "version": "1.0"}"#),
+            ("my-ext/js/background.js", "console.log('bg');"),
+            ("README.txt", "outside the extension"),
+        ]);
+        
+        let extension = load_from_bytes(&data).unwrap();
+        assert_eq!(extension.manifest.name, "Nested");
+        let mut paths: Vec<_> = extension.files.keys().cloned().collect();
+        paths.sort();
+        assert_eq!(paths, vec![PathBuf::from("js/background.js"), PathBuf::from("manifest.json")]);
+    }
+    
+    #[test]
+    fn test_ambiguous_nested_manifests() {
+        let manifest = r#"{"manifest_version": 3, "name": "X", "version": "1.0"}"#;
+        let data = zip_with(&[("chrome/manifest.json", manifest), ("edge/manifest.json", manifest)]);
+        
+        let error = load_from_bytes(&data).unwrap_err().to_string();
+        assert!(error.contains("chrome/manifest.json, edge/manifest.json"));
+    }
+}
@@ -4,18 +4,23 @@ pub mod extractor;
 pub mod builder;
 
 use crate::models::{Extension, ConversionResult};
+use crate::PackageFormat;
 use anyhow::Result;
 use std::path::Path;
 
 /// Load extension from file or directory
 pub fn load_extension(path: &Path) -> Result<Extension> {
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    
     if path.is_dir() {
         extractor::load_from_directory(path)
-    } else if path.extension().and_then(|e| e.to_str()) == Some("zip") 
-        || path.extension().and_then(|e| e.to_str()) == Some("crx") {
+    } else if matches!(extension.as_deref(), Some("zip" | "crx" | "xpi")) {
+        // XPIs are plain ZIP archives, so previously converted output can be re-converted
         extractor::load_from_archive(path)
     } else {
-        anyhow::bail!("Unsupported input format. Expected directory, .zip, or .crx file")
+        anyhow::bail!("Unsupported input format. Expected directory, .zip, .crx, or .xpi file")
     }
 }
 
@@ -23,18 +28,59 @@ pub fn load_extension(path: &Path) -> Result<Extension> {
 pub fn build_complete_extension(
     source: &Extension,
     result: &ConversionResult,
-    output_path: &Path
+    output_path: &Path,
+    format: PackageFormat,
 ) -> Result<()> {
     builder::build_complete_directory(source, result, output_path)?;
     
-    // Create XPI from directory
-    let zip_path = output_path.with_extension("xpi");
-    builder::create_zip_from_directory(output_path, &zip_path)?;
+    // Create XPI from directory; the provenance file stays out unless asked for
+    if format == PackageFormat::Xpi {
+        let zip_path = output_path.with_extension("xpi");
+        let provenance = std::path::Path::new(crate::models::PROVENANCE_FILE);
+        let excluded: &[&std::path::Path] = if result.package_provenance { &[] } else { &[provenance] };
+        builder::create_zip_from_directory_excluding(output_path, &zip_path, excluded)?;
+    }
     
     Ok(())
 }
 
+/// Build the AMO source archive (original sources + a README with the options used)
+pub fn build_source_archive(source: &Extension, options: &crate::ConversionOptions, zip_path: &Path) -> Result<()> {
+    builder::build_source_archive(source, options, zip_path)
+}
+
+/// Location of the source archive written next to the XPI for `output_path`
+pub fn source_archive_path(output_path: &Path) -> std::path::PathBuf {
+    output_path.with_extension("sources.zip")
+}
+
 /// Build Firefox extension package (simple version)
 pub fn build_extension(result: &ConversionResult, output_path: &Path) -> Result<()> {
     builder::build_xpi(result, output_path)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+    use zip::write::{FileOptions, ZipWriter};
+    
+    #[test]
+    fn test_load_xpi() {
+        let temp_dir = TempDir::new().unwrap();
+        let xpi_path = temp_dir.path().join("converted.xpi");
+        
+        let mut zip = ZipWriter::new(std::fs::File::create(&xpi_path).unwrap());
+        zip.start_file("manifest.json", FileOptions::default()).unwrap();
+        zip.write_all(br#"{"manifest_version": 3, "name": "Rebuilt", "version": "2.0"}"#).unwrap();
+        zip.start_file("background.js", FileOptions::default()).unwrap();
+        zip.write_all(b"console.log('hi');").unwrap();
+        zip.finish().unwrap();
+        
+        let extension = load_extension(&xpi_path).unwrap();
+        assert_eq!(extension.manifest.name, "Rebuilt");
+        assert_eq!(extension.manifest.version, "2.0");
+        assert_eq!(extension.files.len(), 2);
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::models::extension::ChromeApiCall;
 use crate::models::chrome_api_data::ChromeApiDataset;
+use crate::utils::helpers::line_of;
 use regex::Regex;
 use lazy_static::lazy_static;
 use anyhow::Result;
@@ -109,6 +110,144 @@ pub fn get_chrome_api_info(api_name: &str) -> Option<&'static crate::models::chr
     CHROME_API_DATASET.get_info(api_name)
 }
 
+/// A call expression located by [`find_calls`]
+///
+/// Offsets are byte offsets into the scanned source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallSite {
+    /// Start of the matched callee (e.g. `chrome.tabs.query`)
+    pub start: usize,
+    /// Position just after the opening parenthesis
+    pub args_start: usize,
+    /// Position of the closing parenthesis
+    pub args_end: usize,
+    /// 1-based line of the callee
+    pub line: usize,
+}
+
+impl CallSite {
+    /// Raw argument text between the parentheses
+    pub fn args<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.args_start..self.args_end]
+    }
+    
+    /// Top-level arguments, trimmed
+    pub fn arguments<'a>(&self, source: &'a str) -> Vec<&'a str> {
+        split_top_level(self.args(source), ',')
+    }
+}
+
+/// Find calls whose callee matches `callee_pattern` (a regex without the trailing `(`)
+///
+/// Lightweight alternative to a full parser: delimiters are balanced while
+/// skipping string literals, template literals and comments.
+pub fn find_calls(source: &str, callee_pattern: &str) -> Vec<CallSite> {
+    let re = match Regex::new(&format!(r"(?:{})\s*\(", callee_pattern)) {
+        Ok(re) => re,
+        Err(_) => return Vec::new(),
+    };
+    
+    re.find_iter(source)
+        .filter(|m| !in_comment(source, m.start()))
+        .filter_map(|m| {
+            let open = m.end() - 1;
+            let close = find_matching_delimiter(source, open)?;
+            Some(CallSite {
+                start: m.start(),
+                args_start: open + 1,
+                args_end: close,
+                line: line_of(source, m.start()),
+            })
+        })
+        .collect()
+}
+
+/// Find the delimiter closing the one at `open` (`(`, `[` or `{`)
+pub fn find_matching_delimiter(source: &str, open: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open;
+    
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'\'' | b'"' | b'`' => i = skip_string(bytes, i),
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    
+    None
+}
+
+/// Split `text` on `separator` occurring outside nested delimiters, strings and comments
+pub fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let bytes = text.as_bytes();
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut last = 0;
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        match bytes[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b'\'' | b'"' | b'`' => i = skip_string(bytes, i),
+            c if depth == 0 && c as char == separator => {
+                parts.push(text[last..i].trim());
+                last = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    
+    let tail = text[last..].trim();
+    if !tail.is_empty() || !parts.is_empty() {
+        parts.push(tail);
+    }
+    parts
+}
+
+/// Index of the closing quote of the string literal starting at `start`
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            c if c == quote => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Whether `pos` sits inside a `//` line comment
+fn in_comment(source: &str, pos: usize) -> bool {
+    let line_start = source[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    source[line_start..pos].trim_start().starts_with("//")
+}
+
 pub struct JavaScriptAnalyzer;
 
 impl JavaScriptAnalyzer {
@@ -166,4 +305,18 @@ mod tests {
         assert!(offscreen_call.is_some());
         assert!(offscreen_call.unwrap().is_chrome_only);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_find_calls_balances_arguments() {
+        let code = r#"
+            // chrome.tabs.query({}) in a comment
+            chrome.tabs.query({ url: "https://x/(y" }, (tabs) => { f(tabs); });
+        "#;
+        
+        let calls = find_calls(code, r"chrome\.tabs\.query");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].line, 3);
+        let args = calls[0].arguments(code);
+        assert_eq!(args, vec![r#"{ url: "https://x/(y" }"#, "(tabs) => { f(tabs); }"]);
+    }
+}
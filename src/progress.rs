@@ -0,0 +1,34 @@
+//! Progress reporting for long-running conversions
+
+use std::path::Path;
+
+/// Pipeline stages reported through [`ProgressSink::on_stage`], in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionStage {
+    Loading,
+    Analyzing,
+    Transforming,
+    Validating,
+    Packaging,
+    Reporting,
+}
+
+/// Receives progress updates during a conversion
+///
+/// All methods default to no-ops, so implementors only override what they need.
+pub trait ProgressSink {
+    /// A new pipeline stage started
+    fn on_stage(&mut self, _stage: ConversionStage) {}
+    
+    /// File `index` (1-based) of `total` is being processed in the current stage
+    fn on_file(&mut self, _path: &Path, _index: usize, _total: usize) {}
+    
+    /// A conversion warning was produced
+    fn on_warning(&mut self, _message: &str) {}
+}
+
+/// Sink that ignores all progress updates
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {}
@@ -0,0 +1,119 @@
+//! Diff between two converted outputs
+//!
+//! Used to review what a tool upgrade changes in the generated extension.
+
+use crate::models::Extension;
+use anyhow::{Context, Result};
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// File-by-file comparison of two converted extensions
+#[derive(Debug, Clone, Default)]
+pub struct OutputDiff {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub modified: Vec<FileDiff>,
+}
+
+/// A file present in both outputs with different content
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Unified diff for manifest and JavaScript files, `None` for other files
+    pub unified: Option<String>,
+}
+
+impl OutputDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// Compare two converted outputs (directories or XPIs)
+pub fn diff_outputs(old: &Path, new: &Path) -> Result<OutputDiff> {
+    let old_ext = crate::packager::load_extension(old)
+        .with_context(|| format!("Failed to load {}", old.display()))?;
+    let new_ext = crate::packager::load_extension(new)
+        .with_context(|| format!("Failed to load {}", new.display()))?;
+    
+    Ok(diff_extensions(&old_ext, &new_ext))
+}
+
+/// Compare the files of two loaded extensions
+pub fn diff_extensions(old: &Extension, new: &Extension) -> OutputDiff {
+    let paths: BTreeSet<&PathBuf> = old.files.keys()
+        .chain(old.deferred.entries.keys())
+        .chain(new.files.keys())
+        .chain(new.deferred.entries.keys())
+        .collect();
+    let mut diff = OutputDiff::default();
+    
+    for path in paths {
+        match (old.read_file(path), new.read_file(path)) {
+            (Some(_), None) => diff.removed.push(path.clone()),
+            (None, Some(_)) => diff.added.push(path.clone()),
+            (Some(before), Some(after)) if before != after => {
+                let unified = if is_diffable(path) {
+                    let before = String::from_utf8_lossy(&before);
+                    let after = String::from_utf8_lossy(&after);
+                    let name = path.to_string_lossy();
+                    Some(
+                        TextDiff::from_lines(before.as_ref(), after.as_ref())
+                            .unified_diff()
+                            .header(&format!("old/{}", name), &format!("new/{}", name))
+                            .to_string()
+                    )
+                } else {
+                    None
+                };
+                diff.modified.push(FileDiff { path: path.clone(), unified });
+            }
+            _ => {}
+        }
+    }
+    
+    diff
+}
+
+/// Only the manifest and scripts get textual diffs
+fn is_diffable(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == "manifest.json")
+        || path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e, "js" | "mjs" | "cjs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+    
+    #[test]
+    fn test_diff_outputs() {
+        let old = TempDir::new().unwrap();
+        let new = TempDir::new().unwrap();
+        
+        let manifest = r#"{"manifest_version": 3, "name": "Diff", "version": "1.0"}"#;
+        for dir in [old.path(), new.path()] {
+            fs::write(dir.join("manifest.json"), manifest).unwrap();
+            fs::write(dir.join("popup.html"), "<html></html>").unwrap();
+        }
+        fs::write(old.path().join("background.js"), "const a = 1;\nrun(a);\n").unwrap();
+        fs::write(new.path().join("background.js"), "const a = 2;\nrun(a);\n").unwrap();
+        fs::create_dir(new.path().join("shims")).unwrap();
+        fs::write(new.path().join("shims/polyfill.js"), "// shim\n").unwrap();
+        
+        let diff = diff_outputs(old.path(), new.path()).unwrap();
+        
+        assert_eq!(diff.added, vec![PathBuf::from("shims/polyfill.js")]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].path, PathBuf::from("background.js"));
+        
+        let unified = diff.modified[0].unified.as_ref().unwrap();
+        assert!(unified.contains("-const a = 1;"));
+        assert!(unified.contains("+const a = 2;"));
+    }
+}
@@ -3,6 +3,40 @@
 use crate::models::ConversionResult;
 use anyhow::Result;
 
+/// Markdown section listing analysis issues per source file
+fn generate_issues_by_file(result: &ConversionResult) -> String {
+    let mut report = String::new();
+    report.push_str("## Issues by File\n\n");
+    
+    for (path, issues) in &result.report.issues_by_file {
+        report.push_str(&format!("### {} ({})\n\n", path, issues.len()));
+        for issue in issues {
+            let position = issue.position.as_ref().map(|p| format!("{}: ", p)).unwrap_or_default();
+            report.push_str(&format!("- **{}** {}{}\n", issue.severity, position, issue.description));
+        }
+        report.push('\n');
+    }
+    
+    report
+}
+
+/// One-line summary for CI logs
+pub fn generate_summary(result: &ConversionResult) -> String {
+    let shims = result.new_files.iter().filter(|f| result.is_shim(&f.path)).count();
+    format!(
+        "Modified {}, added {}, {}, {} (confidence {}%)",
+        count(result.modified_files.len(), "file", "files"),
+        count(shims, "shim", "shims"),
+        count(result.report.blockers.len(), "blocker", "blockers"),
+        count(result.report.manual_actions.len(), "manual action", "manual actions"),
+        result.report.confidence()
+    )
+}
+
+fn count(n: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", n, if n == 1 { singular } else { plural })
+}
+
 pub fn generate_markdown_report(result: &ConversionResult) -> Result<String> {
     let mut report = String::new();
     
@@ -20,8 +54,12 @@ pub fn generate_markdown_report(result: &ConversionResult) -> Result<String> {
     report.push_str(&format!("- **Total Changes**: {}\n", result.report.summary.total_changes));
     report.push_str(&format!("- **Chrome API Calls Converted**: {}\n",
         result.report.summary.chrome_api_calls_converted));
-    report.push_str(&format!("- **Callback→Promise Conversions**: {}\n\n",
+    report.push_str(&format!("- **Callback→Promise Conversions**: {}\n",
         result.report.summary.callback_to_promise_conversions));
+    for (kind, count) in &result.report.summary.changes_by_kind {
+        report.push_str(&format!("- **{}**: {}\n", kind.label(), count));
+    }
+    report.push('\n');
     
     // Detailed File Changes
     if !result.modified_files.is_empty() {
@@ -56,6 +94,16 @@ pub fn generate_markdown_report(result: &ConversionResult) -> Result<String> {
         report.push_str("\n");
     }
     
+    // Permission narrowing
+    let suggestions = &result.report.permission_suggestions;
+    if !suggestions.is_empty() {
+        report.push_str("## 🔒 Permission Narrowing Suggestions\n\n");
+        for suggestion in suggestions {
+            report.push_str(&format!("- {}\n", suggestion.describe()));
+        }
+        report.push('\n');
+    }
+    
     // JavaScript Changes Summary
     if !result.report.javascript_changes.is_empty() {
         report.push_str("## JavaScript Transformations Summary\n\n");
@@ -83,13 +131,22 @@ pub fn generate_markdown_report(result: &ConversionResult) -> Result<String> {
         report.push_str("\n");
     }
     
+    // Issues grouped by the file they were found in
+    if !result.report.issues_by_file.is_empty() {
+        report.push_str(&generate_issues_by_file(result));
+    }
+    
     // Warnings with detailed explanations
     if !result.report.warnings.is_empty() {
         report.push_str("## ℹ️ Warnings & What They Mean\n\n");
         for warning in &result.report.warnings {
-            report.push_str(&format!("- {}\n", warning));
+            match &warning.location {
+                Some(location) => report.push_str(&format!("- `{}`: {}\n", location, warning.message)),
+                None => report.push_str(&format!("- {}\n", warning.message)),
+            }
             
             // Add detailed explanations for common warnings
+            let warning = &warning.message;
             if warning.contains("service worker") {
                 report.push_str("\n### Service Worker → Event Page Conversion\n\n");
                 report.push_str("**CHROME (Service Worker):**\n");
@@ -0,0 +1,161 @@
+//! Self-contained HTML conversion report
+
+use crate::models::ConversionResult;
+use anyhow::Result;
+
+const STYLE: &str = "\
+body { font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #1f2328; }
+h1 { font-size: 1.6em; border-bottom: 2px solid #ff7139; padding-bottom: .3em; }
+.status-ok { color: #1a7f37; } .status-failed { color: #cf222e; }
+table.summary td { padding: .2em 1em .2em 0; }
+details { border: 1px solid #d0d7de; border-radius: 6px; margin: .8em 0; padding: .4em .8em; }
+details details { margin-left: 1em; }
+summary { cursor: pointer; font-weight: 600; }
+li { margin: .2em 0; }
+code { background: #f6f8fa; padding: .1em .3em; border-radius: 4px; }
+.line { color: #6e7781; font-variant-numeric: tabular-nums; }
+";
+
+/// Render the conversion report as a single HTML page with inline CSS
+///
+/// Each section is a collapsible `<details>` element; empty sections are omitted.
+pub fn generate_html_report(result: &ConversionResult) -> Result<String> {
+    let summary = &result.report.summary;
+    let title = format!("{} v{}", summary.extension_name, summary.extension_version);
+    let mut html = String::new();
+
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>Conversion report: {}</title>\n", escape(&title)));
+    html.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+    html.push_str(&format!("<h1>Chrome to Firefox conversion: {}</h1>\n", escape(&title)));
+
+    // Summary
+    let (status_class, status) = if summary.conversion_successful {
+        ("status-ok", "Success")
+    } else {
+        ("status-failed", "Failed")
+    };
+    html.push_str("<table class=\"summary\">\n");
+    html.push_str(&format!("<tr><td>Status</td><td class=\"{}\">{}</td></tr>\n", status_class, status));
+    html.push_str(&format!("<tr><td>Files modified</td><td>{}</td></tr>\n", summary.files_modified));
+    html.push_str(&format!("<tr><td>Files added</td><td>{}</td></tr>\n", summary.files_added));
+    html.push_str(&format!("<tr><td>Total changes</td><td>{}</td></tr>\n", summary.total_changes));
+    for (kind, count) in &summary.changes_by_kind {
+        html.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", escape(kind.label()), count));
+    }
+    html.push_str("</table>\n");
+
+    push_list(&mut html, "Blockers", &result.report.blockers, true);
+    push_list(&mut html, "Manual actions required", &result.report.manual_actions, true);
+    push_list(&mut html, "Manifest changes", &result.report.manifest_changes, false);
+    let suggestions: Vec<String> = result.report.permission_suggestions.iter()
+        .map(|suggestion| {
+            let replacement: Vec<String> = suggestion.replacement.iter()
+                .map(|p| format!("<code>{}</code>", escape(p)))
+                .collect();
+            format!(
+                "Replace <code>{}</code> with {}: {}",
+                escape(&suggestion.permission),
+                replacement.join(", "),
+                escape(&suggestion.reason)
+            )
+        })
+        .collect();
+    push_html_list(&mut html, "Permission narrowing suggestions", &suggestions, false);
+
+    // Modified files with per-line changes
+    if !result.modified_files.is_empty() {
+        html.push_str(&format!("<details open>\n<summary>Modified files ({})</summary>\n", result.modified_files.len()));
+        for modified in &result.modified_files {
+            html.push_str(&format!(
+                "<details class=\"file\">\n<summary><code>{}</code> ({} changes)</summary>\n<ul>\n",
+                escape(&modified.path.display().to_string()),
+                modified.changes.len()
+            ));
+            for change in &modified.changes {
+                html.push_str(&format!(
+                    "<li><span class=\"line\">Line {}</span> {}</li>\n",
+                    change.line_number,
+                    escape(&change.description)
+                ));
+            }
+            html.push_str("</ul>\n</details>\n");
+        }
+        html.push_str("</details>\n");
+    }
+
+    // Added shims
+    if !result.new_files.is_empty() {
+        html.push_str(&format!("<details>\n<summary>Added shims ({})</summary>\n<ul>\n", result.new_files.len()));
+        for new_file in &result.new_files {
+            html.push_str(&format!(
+                "<li><code>{}</code> – {}</li>\n",
+                escape(&new_file.path.display().to_string()),
+                escape(&new_file.purpose)
+            ));
+        }
+        html.push_str("</ul>\n</details>\n");
+    }
+
+    // Analysis issues grouped by file
+    let issues_by_file = &result.report.issues_by_file;
+    if !issues_by_file.is_empty() {
+        let total: usize = issues_by_file.values().map(Vec::len).sum();
+        html.push_str(&format!("<details>\n<summary>Issues by file ({})</summary>\n", total));
+        for (path, issues) in issues_by_file {
+            html.push_str(&format!(
+                "<details class=\"file\">\n<summary><code>{}</code> ({})</summary>\n<ul>\n",
+                escape(path),
+                issues.len()
+            ));
+            for issue in issues {
+                let position = issue.position.as_ref()
+                    .map(|p| format!("<span class=\"line\">{}</span> ", escape(p)))
+                    .unwrap_or_default();
+                html.push_str(&format!(
+                    "<li><strong>{}</strong> {}{}</li>\n",
+                    escape(&issue.severity),
+                    position,
+                    escape(&issue.description)
+                ));
+            }
+            html.push_str("</ul>\n</details>\n");
+        }
+        html.push_str("</details>\n");
+    }
+
+    let warnings: Vec<String> = result.report.warnings.iter().map(|w| w.to_string()).collect();
+    push_list(&mut html, "Warnings", &warnings, false);
+
+    html.push_str("</body>\n</html>\n");
+    Ok(html)
+}
+
+fn push_list(html: &mut String, title: &str, items: &[String], open: bool) {
+    let items: Vec<String> = items.iter().map(|item| escape(item)).collect();
+    push_html_list(html, title, &items, open);
+}
+
+/// Like `push_list`, for items that are already HTML
+fn push_html_list(html: &mut String, title: &str, items: &[String], open: bool) {
+    if items.is_empty() {
+        return;
+    }
+    html.push_str(&format!(
+        "<details{}>\n<summary>{} ({})</summary>\n<ul>\n",
+        if open { " open" } else { "" },
+        title,
+        items.len()
+    ));
+    for item in items {
+        html.push_str(&format!("<li>{}</li>\n", item));
+    }
+    html.push_str("</ul>\n</details>\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
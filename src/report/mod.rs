@@ -1,10 +1,59 @@
 //! Report generation
 
 pub mod generator;
+pub mod diff;
+pub mod html;
 
 use crate::models::ConversionResult;
 use anyhow::Result;
 
+pub use diff::{diff_outputs, OutputDiff, FileDiff};
+
 pub fn generate_report(result: &ConversionResult) -> Result<String> {
     generator::generate_markdown_report(result)
-}
\ No newline at end of file
+}
+
+/// One-line summary with change counts and the confidence score
+pub fn generate_summary(result: &ConversionResult) -> String {
+    generator::generate_summary(result)
+}
+
+/// Self-contained HTML page for sharing outside the repository
+pub fn generate_html_report(result: &ConversionResult) -> Result<String> {
+    html::generate_html_report(result)
+}
+
+/// Which report `convert --report` writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    /// Markdown report at `<output>.md`
+    #[default]
+    Markdown,
+    /// Self-contained HTML page at `<output>.html`
+    Html,
+    /// One line on stdout (see [`generate_summary`])
+    Summary,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = crate::ParseOptionError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            "summary" => Ok(Self::Summary),
+            _ => Err(crate::ParseOptionError::new("report format", s, "markdown, html or summary")),
+        }
+    }
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "markdown"),
+            Self::Html => write!(f, "html"),
+            Self::Summary => write!(f, "summary"),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Optional splitting of oversized background scripts
+//!
+//! Firefox loads `background.scripts` as classic scripts sharing one global
+//! scope, so top-level declarations can move into files listed *before* the
+//! main script. Function declarations are hoisted anyway, so loading them
+//! earlier is indistinguishable. Classes are only moved when that is equally
+//! safe (no `extends` clause and no `static` members evaluated at definition).
+
+use crate::models::{ChangeKind, ChangeType, FileChange, NewFile};
+use crate::parser::javascript::find_matching_delimiter;
+use crate::utils::helpers::line_of;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Result of splitting one background script
+#[derive(Debug, Clone)]
+pub struct BackgroundSplit {
+    /// Remaining main script content (declarations removed)
+    pub main_content: String,
+    /// Extracted declaration files, in load order
+    pub parts: Vec<NewFile>,
+    /// Change records for the main script
+    pub changes: Vec<FileChange>,
+}
+
+/// A movable top-level declaration
+struct Declaration {
+    name: String,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+/// Split `content` when it is larger than `threshold` bytes
+///
+/// Returns `None` when the script is small enough, is an ES module, or has
+/// nothing that can be moved safely.
+pub fn split_background_script(path: &Path, content: &str, threshold: usize) -> Option<BackgroundSplit> {
+    if content.len() <= threshold || is_module(content) {
+        return None;
+    }
+
+    let declarations = find_movable_declarations(content);
+    if declarations.is_empty() {
+        return None;
+    }
+
+    // Keep a leading 'use strict' directive in effect for the moved code
+    let prologue = if has_use_strict(content) { "'use strict';\n\n" } else { "" };
+
+    // Group declarations into parts of at most `threshold` bytes each
+    let mut groups: Vec<Vec<&Declaration>> = vec![Vec::new()];
+    let mut group_size = 0;
+    for declaration in &declarations {
+        let size = declaration.end - declaration.start;
+        if group_size > 0 && group_size + size > threshold {
+            groups.push(Vec::new());
+            group_size = 0;
+        }
+        groups.last_mut()?.push(declaration);
+        group_size += size;
+    }
+
+    let mut parts = Vec::new();
+    let mut changes = Vec::new();
+    let mut main_content = content.to_string();
+
+    for (index, group) in groups.iter().enumerate() {
+        let part_path = part_path(path, index + 1);
+        let part_name = part_path.to_string_lossy().replace('\\', "/");
+
+        let mut part_content = format!(
+            "// Extracted from {} by chrome2moz (loaded before it via background.scripts)\n{}",
+            path.display(),
+            prologue
+        );
+        for declaration in group {
+            part_content.push_str(&content[declaration.start..declaration.end]);
+            part_content.push_str("\n\n");
+
+            changes.push(FileChange {
+                line_number: declaration.line,
+                change_type: ChangeType::Deletion,
+                kind: ChangeKind::Other,
+                description: format!("Moved top-level declaration '{}' to {}", declaration.name, part_name),
+                old_code: None,
+                new_code: None,
+            });
+        }
+
+        parts.push(NewFile {
+            path: part_path,
+            content: part_content,
+            purpose: format!("Top-level declarations split out of {}", path.display()),
+        });
+    }
+
+    // Remove moved declarations back to front so offsets stay valid
+    for (index, group) in groups.iter().enumerate().rev() {
+        for declaration in group.iter().rev() {
+            main_content.replace_range(
+                declaration.start..declaration.end,
+                &format!("// '{}' moved to {}", declaration.name, part_path(path, index + 1).display()),
+            );
+        }
+    }
+
+    Some(BackgroundSplit {
+        main_content,
+        parts,
+        changes,
+    })
+}
+
+/// `dir/background.js` -> `dir/background.part1.js`
+fn part_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("background");
+    path.with_file_name(format!("{}.part{}.js", stem, index))
+}
+
+fn is_module(content: &str) -> bool {
+    Regex::new(r"(?m)^\s*(?:import\s+[\w{*'\x22]|export\s+)").unwrap().is_match(content)
+}
+
+fn has_use_strict(content: &str) -> bool {
+    let trimmed = content.trim_start();
+    trimmed.starts_with("'use strict'") || trimmed.starts_with("\"use strict\"")
+}
+
+/// Top-level function declarations and side-effect-free class declarations
+fn find_movable_declarations(content: &str) -> Vec<Declaration> {
+    let declaration_re = Regex::new(
+        r"(?m)^(?:async\s+)?function\s*\*?\s*([A-Za-z_$][\w$]*)\s*\(|^class\s+([A-Za-z_$][\w$]*)\s*(extends\b)?[^{]*\{"
+    ).unwrap();
+    let static_member_re = Regex::new(r"(?m)^\s*static\b").unwrap();
+
+    let top_level = top_level_offsets(content);
+    let mut declarations = Vec::new();
+
+    for caps in declaration_re.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        if !top_level.contains(&whole.start()) {
+            continue;
+        }
+
+        let (name, body_open) = if let Some(name) = caps.get(1) {
+            // Skip the parameter list, then find the body
+            let params_open = whole.end() - 1;
+            let Some(params_close) = find_matching_delimiter(content, params_open) else { continue };
+            let Some(brace) = content[params_close..].find('{') else { continue };
+            (name.as_str(), params_close + brace)
+        } else {
+            if caps.get(3).is_some() {
+                continue; // Superclass must exist before the class is defined
+            }
+            (caps.get(2).unwrap().as_str(), whole.end() - 1)
+        };
+
+        let Some(body_close) = find_matching_delimiter(content, body_open) else { continue };
+        let body = &content[body_open..body_close];
+        if caps.get(2).is_some() && static_member_re.is_match(body) {
+            continue; // Static members run at definition time
+        }
+
+        declarations.push(Declaration {
+            name: name.to_string(),
+            start: whole.start(),
+            end: body_close + 1,
+            line: line_of(content, whole.start()),
+        });
+    }
+
+    declarations
+}
+
+/// Byte offsets of line starts that are at nesting depth 0 (outside strings/comments)
+fn top_level_offsets(content: &str) -> std::collections::HashSet<usize> {
+    let mut offsets = std::collections::HashSet::new();
+    let bytes = content.as_bytes();
+    let mut depth = 0i32;
+    let mut i = 0;
+    let mut at_line_start = true;
+
+    while i < bytes.len() {
+        if at_line_start && depth == 0 {
+            offsets.insert(i);
+        }
+        at_line_start = false;
+
+        match bytes[i] {
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => depth -= 1,
+            b'\n' => at_line_start = true,
+            b'\'' | b'"' | b'`' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i + 1 < bytes.len() && bytes[i + 1] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_large_background() {
+        let mut code = String::from("'use strict';\nconst state = { count: 0 };\n\n");
+        for i in 0..20 {
+            code.push_str(&format!(
+                "function handler{i}(msg) {{\n  if (msg.type === '{{') {{ state.count += {i}; }}\n  return state.count;\n}}\n\n"
+            ));
+        }
+        code.push_str("class Store {\n  get() { return state; }\n}\n\n");
+        code.push_str("class Derived extends Store {}\n\n");
+        code.push_str("chrome.runtime.onMessage.addListener((msg) => handler0(msg));\n");
+
+        let split = split_background_script(Path::new("background.js"), &code, 400).unwrap();
+
+        assert!(split.parts.len() > 1);
+        assert_eq!(split.parts[0].path, PathBuf::from("background.part1.js"));
+        assert!(split.parts.iter().all(|p| p.content.contains("'use strict'")));
+        assert!(split.parts.iter().any(|p| p.content.contains("class Store")));
+
+        // Listeners, state and classes with a superclass stay in the main script
+        assert!(split.main_content.contains("const state"));
+        assert!(split.main_content.contains("class Derived extends Store"));
+        assert!(split.main_content.contains("onMessage.addListener"));
+        assert!(!split.main_content.contains("function handler0"));
+    }
+
+    #[test]
+    fn test_small_or_module_background_not_split() {
+        let code = "function a() {}\nfunction b() {}\n";
+        assert!(split_background_script(Path::new("bg.js"), code, 1024).is_none());
+
+        let module = format!("import {{ x }} from './x.js';\n{}", "function f() {}\n".repeat(100));
+        assert!(split_background_script(Path::new("bg.js"), &module, 10).is_none());
+    }
+}
@@ -0,0 +1,64 @@
+//! Firefox releases that introduced features transforms depend on
+//!
+//! Transforms ask [`ConversionContext::supports`](crate::models::ConversionContext::supports)
+//! instead of hardcoding versions. Without a target version nothing is
+//! assumed, so every shim and fallback is kept.
+
+/// A Firefox capability that changes how something is converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// Manifest V3 extensions
+    ManifestV3,
+    /// `declarativeNetRequest` for MV3 extensions
+    NativeDnr,
+    /// `storage.session`
+    StorageSession,
+    /// `world: "MAIN"` for scripting and content scripts
+    MainWorldScripts,
+    /// `action.openPopup()` without a user gesture
+    ActionOpenPopup,
+    /// `gecko.data_collection_permissions` in the manifest
+    DataCollectionPermissions,
+}
+
+/// First Firefox major version shipping each feature
+const FEATURE_VERSIONS: &[(Feature, u32)] = &[
+    (Feature::ManifestV3, 109),
+    (Feature::NativeDnr, 113),
+    (Feature::StorageSession, 115),
+    (Feature::MainWorldScripts, 128),
+    (Feature::ActionOpenPopup, 127),
+    (Feature::DataCollectionPermissions, 140),
+];
+
+impl Feature {
+    /// First Firefox major version supporting the feature
+    pub fn min_version(self) -> u32 {
+        FEATURE_VERSIONS.iter()
+            .find(|(feature, _)| *feature == self)
+            .map(|(_, version)| *version)
+            .expect("every feature has a minimum version")
+    }
+
+    /// Whether Firefox `target_version` (a major version) has the feature
+    ///
+    /// An unknown target supports nothing.
+    pub fn supported_in(self, target_version: Option<u32>) -> bool {
+        target_version.is_some_and(|target| target >= self.min_version())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_versions() {
+        assert_eq!(Feature::NativeDnr.min_version(), 113);
+        assert!(Feature::NativeDnr.supported_in(Some(113)));
+        assert!(!Feature::NativeDnr.supported_in(Some(112)));
+        assert!(!Feature::MainWorldScripts.supported_in(Some(121)));
+        assert!(Feature::MainWorldScripts.supported_in(Some(128)));
+        assert!(!Feature::ManifestV3.supported_in(None));
+    }
+}
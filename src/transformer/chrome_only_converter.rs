@@ -30,6 +30,13 @@ impl ChromeOnlyApiConverter {
         }
     }
 
+    /// Ask for match patterns when an offscreen DOM document's target pages can't be
+    /// inferred (otherwise `<all_urls>` is used)
+    pub fn with_url_prompt(mut self, prompt: UrlPrompt) -> Self {
+        self.offscreen_converter = self.offscreen_converter.with_url_prompt(prompt);
+        self
+    }
+
     /// Convert all Chrome-only APIs found in the extension
     pub fn convert_all(&self, context: &ConversionContext) -> Result<Vec<ChromeOnlyConversionResult>> {
         let mut all_results = Vec::new();
@@ -51,7 +58,8 @@ impl ChromeOnlyApiConverter {
         Ok(all_results)
     }
 
-    fn convert_offscreen_apis(
+    /// Convert `chrome.offscreen` documents only
+    pub fn convert_offscreen_apis(
         &self,
         context: &ConversionContext,
     ) -> Result<Vec<ChromeOnlyConversionResult>> {
@@ -63,10 +71,15 @@ impl ChromeOnlyApiConverter {
                 let usages = self.offscreen_analyzer.detect_usage(&content, &js_path)?;
 
                 for usage in usages {
-                    // Analyze the offscreen document
-                    let analysis = self
-                        .offscreen_analyzer
-                        .analyze_offscreen_document(&usage.document_url)?;
+                    // Analyze the offscreen document, preferring the loaded copy
+                    // (archive inputs have no source directory to read from)
+                    let document_path = PathBuf::from(usage.document_url.trim_start_matches('/'));
+                    let analysis = match context.source.get_file_content(&document_path) {
+                        Some(html) => self.offscreen_analyzer.analyze_document_content(&html)?,
+                        None => self
+                            .offscreen_analyzer
+                            .analyze_offscreen_document(&usage.document_url)?,
+                    };
 
                     // Determine conversion strategy
                     let strategy = self.offscreen_converter.determine_strategy(&analysis, &usage);
@@ -85,7 +98,8 @@ impl ChromeOnlyApiConverter {
                             self.offscreen_converter
                                 .convert_network_to_background(&analysis, &usage)?
                         }
-                        ConversionStrategy::ContentScript { .. } => {
+                        ConversionStrategy::ContentScript { .. }
+                        | ConversionStrategy::InteractiveContentScript { .. } => {
                             self.offscreen_converter
                                 .convert_dom_to_content_script(&analysis, &usage)?
                         }
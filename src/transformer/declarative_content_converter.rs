@@ -16,38 +16,51 @@ impl DeclarativeContentConverter {
     /// Convert declarativeContent rules to content scripts + messaging
     pub fn convert(&self, rules: &[DeclarativeContentRule]) -> Result<ChromeOnlyConversionResult> {
         let mut content_script_matches = HashSet::new();
+        let mut exclude_matches = HashSet::new();
         let mut conditions_code = Vec::new();
 
-        for rule in rules {
+        for (rule_id, rule) in rules.iter().enumerate() {
             for condition in &rule.conditions {
                 let PageCondition::PageStateMatcher { page_url, css, .. } = condition;
+                let url_check = url_filter_check(page_url);
                 content_script_matches.insert(page_url.to_match_pattern());
+                exclude_matches.extend(page_url.to_exclude_patterns());
 
+                // The script runs on every rule's pages (and on pages the match
+                // pattern widens to), so each rule re-checks its full URL filter
                 let check_code = if let Some(selectors) = css {
                     format!(
                         r#"
 // Check page conditions
-const elements = document.querySelectorAll('{}');
-if (elements.length > 0) {{
+if ({} && document.querySelectorAll('{}').length > 0) {{
   // Condition met - notify background
   browser.runtime.sendMessage({{
     type: 'page_condition_met',
-    action: 'show_page_action'
+    action: 'show_page_action',
+    ruleId: {}
   }});
 }}
 "#,
-                        selectors.join(", ")
+                        url_check,
+                        selectors.join(", "),
+                        rule_id
                     )
                 } else {
                     // Just URL matching - simpler case
-                    r#"
+                    format!(
+                        r#"
 // URL matched - notify background
-browser.runtime.sendMessage({
-  type: 'page_condition_met',
-  action: 'show_page_action'
-});
-"#
-                    .to_string()
+if ({}) {{
+  browser.runtime.sendMessage({{
+    type: 'page_condition_met',
+    action: 'show_page_action',
+    ruleId: {}
+  }});
+}}
+"#,
+                        url_check,
+                        rule_id
+                    )
                 };
 
                 conditions_code.push(check_code);
@@ -63,6 +76,7 @@ browser.runtime.sendMessage({
 (function() {{
   // Check conditions on page load
   function checkConditions() {{
+    const url = new URL(location.href);
     {}
   }}
   
@@ -84,25 +98,87 @@ browser.runtime.sendMessage({
             conditions_code.join("\n\n")
         );
 
-        let background_handler = r#"// Auto-generated handler for declarativeContent conversion
-browser.runtime.onMessage.addListener((message, sender) => {
-  if (message.type === 'page_condition_met' && sender.tab?.id) {
+        let requested_scripts = Self::requested_content_scripts(rules);
+        let has_injection = !requested_scripts.is_empty();
+        let injection_code = if has_injection {
+            r#"
+    // Inject the matched rule's RequestContentScript files once per document
+    const files = ruleContentScripts[message.ruleId];
+    const target = { tabId: sender.tab.id, frameIds: [sender.frameId ?? 0] };
+    const documentKey = `${message.ruleId}:${sender.tab.id}:${sender.frameId ?? 0}:${sender.url}`;
+    if (files && !injectedDocuments.has(documentKey)) {
+      injectedDocuments.add(documentKey);
+      if (files.css.length > 0) {
+        browser.scripting.insertCSS({ target, files: files.css });
+      }
+      if (files.js.length > 0) {
+        browser.scripting.executeScript({ target, files: files.js });
+      }
+    }
+"#
+            .to_string()
+        } else {
+            String::new()
+        };
+        let injection_state = if has_injection {
+            format!(
+                "const ruleContentScripts = {};\nconst injectedDocuments = new Set();\n",
+                serde_json::Value::Object(requested_scripts)
+            )
+        } else {
+            String::new()
+        };
+        
+        let background_handler = format!(
+            r#"// Auto-generated handler for declarativeContent conversion
+{}browser.runtime.onMessage.addListener((message, sender) => {{
+  if (message.type === 'page_condition_met' && sender.tab?.id) {{
     // Show page action for this tab
     browser.pageAction.show(sender.tab.id);
     
     // Set icon if specified
-    if (message.iconPath) {
-      browser.pageAction.setIcon({
+    if (message.iconPath) {{
+      browser.pageAction.setIcon({{
         tabId: sender.tab.id,
         path: message.iconPath
-      });
-    }
-  }
-});
-"#;
+      }});
+    }}
+{}  }}
+}});
+"#,
+            injection_state,
+            injection_code
+        );
 
-        let matches: Vec<String> = content_script_matches.into_iter().collect();
+        let mut matches: Vec<String> = content_script_matches.into_iter().collect();
+        let mut exclude_matches: Vec<String> = exclude_matches.into_iter().collect();
+        matches.sort();
+        exclude_matches.sort();
 
+        let mut manifest_changes = vec![
+            ManifestChange::AddContentScript {
+                matches,
+                exclude_matches,
+                js: vec!["content-scripts/page-condition-checker.js".to_string()],
+                run_at: "document_idle".to_string(),
+            },
+            ManifestChange::AddPermission("pageAction".to_string()),
+        ];
+        let mut instructions = vec![
+            "declarativeContent rules converted to content script + messaging".to_string(),
+            "Page action will be shown when conditions are met".to_string(),
+            "Firefox requires explicit pageAction permission".to_string(),
+            "Add background_declarative_content_handler.js content to your background script"
+                .to_string(),
+        ];
+        if has_injection {
+            manifest_changes.push(ManifestChange::AddPermission("scripting".to_string()));
+            instructions.push(
+                "RequestContentScript actions are injected with scripting.executeScript/insertCSS when conditions are met; the host permissions must cover the matched pages"
+                    .to_string(),
+            );
+        }
+        
         Ok(ChromeOnlyConversionResult {
             new_files: vec![
                 NewFile {
@@ -113,29 +189,43 @@ browser.runtime.onMessage.addListener((message, sender) => {
                 },
                 NewFile {
                     path: PathBuf::from("background_declarative_content_handler.js"),
-                    content: background_handler.to_string(),
+                    content: background_handler,
                     purpose: "Background handler for page condition messages".to_string(),
                 },
             ],
             modified_files: Vec::new(),
-            manifest_changes: vec![
-                ManifestChange::AddContentScript {
-                    matches,
-                    js: vec!["content-scripts/page-condition-checker.js".to_string()],
-                    run_at: "document_idle".to_string(),
-                },
-                ManifestChange::AddPermission("pageAction".to_string()),
-            ],
+            manifest_changes,
             removed_files: Vec::new(),
-            instructions: vec![
-                "declarativeContent rules converted to content script + messaging".to_string(),
-                "Page action will be shown when conditions are met".to_string(),
-                "Firefox requires explicit pageAction permission".to_string(),
-                "Add background_declarative_content_handler.js content to your background script"
-                    .to_string(),
-            ],
+            instructions,
         })
     }
+    
+    /// `RequestContentScript` files keyed by rule index, for rules that request any
+    fn requested_content_scripts(rules: &[DeclarativeContentRule]) -> serde_json::Map<String, serde_json::Value> {
+        let mut requested = serde_json::Map::new();
+        for (rule_id, rule) in rules.iter().enumerate() {
+            let mut rule_js: Vec<&String> = Vec::new();
+            let mut rule_css: Vec<&String> = Vec::new();
+            for action in &rule.actions {
+                if let PageAction::RequestContentScript { js, css } = action {
+                    for file in js {
+                        if !rule_js.contains(&file) {
+                            rule_js.push(file);
+                        }
+                    }
+                    for file in css {
+                        if !rule_css.contains(&file) {
+                            rule_css.push(file);
+                        }
+                    }
+                }
+            }
+            if !rule_js.is_empty() || !rule_css.is_empty() {
+                requested.insert(rule_id.to_string(), serde_json::json!({ "js": rule_js, "css": rule_css }));
+            }
+        }
+        requested
+    }
 
     /// Convert complex conditions with advanced monitoring
     pub fn convert_complex_conditions(
@@ -218,6 +308,15 @@ if (document.readyState === 'loading') {
             .collect::<HashSet<_>>()
             .into_iter()
             .collect();
+        let exclude_matches: Vec<String> = rules
+            .iter()
+            .flat_map(|r| &r.conditions)
+            .flat_map(|c| match c {
+                PageCondition::PageStateMatcher { page_url, .. } => page_url.to_exclude_patterns(),
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
 
         Ok(ChromeOnlyConversionResult {
             new_files: vec![NewFile {
@@ -229,6 +328,7 @@ if (document.readyState === 'loading') {
             modified_files: Vec::new(),
             manifest_changes: vec![ManifestChange::AddContentScript {
                 matches,
+                exclude_matches,
                 js: vec!["content-scripts/advanced-condition-checker.js".to_string()],
                 run_at: "document_idle".to_string(),
             }],
@@ -241,6 +341,58 @@ if (document.readyState === 'loading') {
     }
 }
 
+/// Render a value as a JavaScript string literal
+fn js_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "''".to_string())
+}
+
+/// JavaScript condition on `url` (the page's `URL`) that holds when every part of `filter` matches
+///
+/// Unlike the content script's match pattern, this keeps the host and query
+/// conditions match patterns can't express.
+fn url_filter_check(filter: &UrlFilter) -> String {
+    let host = "url.hostname";
+    let path = "url.pathname";
+    let query = "url.search.slice(1)";
+    let comparisons = [
+        (host, "===", &filter.host_equals),
+        (host, "includes", &filter.host_contains),
+        (host, "startsWith", &filter.host_prefix),
+        (host, "endsWith", &filter.host_suffix),
+        (path, "===", &filter.path_equals),
+        (path, "includes", &filter.path_contains),
+        (path, "startsWith", &filter.path_prefix),
+        (path, "endsWith", &filter.path_suffix),
+        (query, "===", &filter.query_equals),
+        (query, "includes", &filter.query_contains),
+        (query, "startsWith", &filter.query_prefix),
+        (query, "endsWith", &filter.query_suffix),
+    ];
+    
+    let mut checks: Vec<String> = comparisons.iter()
+        .filter_map(|(part, op, value)| {
+            let value = js_string(value.as_deref()?);
+            Some(match *op {
+                "===" => format!("{} === {}", part, value),
+                method => format!("{}.{}({})", part, method, value),
+            })
+        })
+        .collect();
+    if let Some(schemes) = &filter.schemes {
+        let schemes: Vec<String> = schemes.iter().map(|scheme| js_string(scheme)).collect();
+        checks.push(format!("[{}].includes(url.protocol.slice(0, -1))", schemes.join(", ")));
+    }
+    if let Some(pattern) = &filter.url_matches {
+        checks.push(format!("new RegExp({}).test(url.origin + url.pathname + url.search)", js_string(pattern)));
+    }
+    
+    if checks.is_empty() {
+        "true".to_string()
+    } else {
+        checks.join(" && ")
+    }
+}
+
 impl Default for DeclarativeContentConverter {
     fn default() -> Self {
         Self::new()
@@ -8,12 +8,14 @@
 //! - No code transformation needed - just pass through
 
 use crate::models::{ModifiedFile, FileChange, SelectedDecision};
+use crate::parser::javascript::{find_calls, split_top_level};
 use anyhow::Result;
 use std::path::PathBuf;
 
 /// Simple pass-through transformer (no AST parsing needed!)
 pub struct JavaScriptTransformer {
     _decisions: Vec<SelectedDecision>,
+    background_scripts: Vec<PathBuf>,
 }
 
 impl JavaScriptTransformer {
@@ -21,9 +23,17 @@ impl JavaScriptTransformer {
     pub fn new(decisions: &[SelectedDecision]) -> Self {
         Self {
             _decisions: decisions.to_vec(),
+            background_scripts: Vec::new(),
         }
     }
     
+    /// Scripts loaded via `background.scripts` (including imported libraries),
+    /// treated as background code regardless of their file name
+    pub fn with_background_scripts(mut self, scripts: Vec<PathBuf>) -> Self {
+        self.background_scripts = scripts;
+        self
+    }
+    
     /// Get handlers generated during the last transform (always empty now)
     pub fn get_generated_handlers(&self) -> Option<Vec<String>> {
         None
@@ -45,6 +55,7 @@ impl JavaScriptTransformer {
             FileChange {
                 line_number: 1,
                 change_type: crate::models::ChangeType::Addition,
+                kind: crate::models::ChangeKind::ShimInjected,
                 description: format!("Injected {} handler(s) at top of file", handlers.len()),
                 old_code: None,
                 new_code: None,
@@ -60,13 +71,14 @@ impl JavaScriptTransformer {
     }
     
     /// Simple pass-through with importScripts() removal and Firefox self-uninstall fix
-    pub fn transform(&mut self, content: &str, path: &PathBuf) -> Result<ModifiedFile> {
+    pub fn transform(&self, content: &str, path: &PathBuf) -> Result<ModifiedFile> {
         let original_content = content.to_string();
         let mut new_content = content.to_string();
         let mut changes = Vec::new();
         
         // Check if this is a background script that might have importScripts()
-        let is_background = path.to_string_lossy().contains("background");
+        let is_background = path.to_string_lossy().contains("background")
+            || self.background_scripts.contains(path);
         
         if is_background {
             // Remove or comment out importScripts() calls
@@ -82,6 +94,7 @@ impl JavaScriptTransformer {
                 changes.push(FileChange {
                     line_number: 0,
                     change_type: crate::models::ChangeType::Modification,
+                    kind: crate::models::ChangeKind::Other,
                     description: "Commented out importScripts() calls (scripts now loaded via manifest)".to_string(),
                     old_code: None,
                     new_code: None,
@@ -107,6 +120,7 @@ impl JavaScriptTransformer {
             changes.push(FileChange {
                 line_number: 0,
                 change_type: crate::models::ChangeType::Modification,
+                kind: crate::models::ChangeKind::Other,
                 description: "Disabled browser.management.uninstallSelf() calls for Firefox compatibility".to_string(),
                 old_code: None,
                 new_code: None,
@@ -125,12 +139,33 @@ impl JavaScriptTransformer {
             changes.push(FileChange {
                 line_number: 0,
                 change_type: crate::models::ChangeType::Modification,
+                kind: crate::models::ChangeKind::Other,
                 description: "INFO: Found Firefox-specific conditional check (clipperType !== 3) - manual review may be needed".to_string(),
                 old_code: None,
                 new_code: None,
             });
         }
         
+        // Normalize scripting.registerContentScripts()/updateContentScripts() options
+        let (normalized, registration_changes) = normalize_content_script_registrations(&new_content);
+        new_content = normalized;
+        changes.extend(registration_changes);
+        
+        // MV3 manifests have no page_action/browser_action, so those calls must target action
+        let (rewritten, page_action_changes) = rewrite_legacy_action_calls(&new_content);
+        new_content = rewritten;
+        changes.extend(page_action_changes);
+        
+        // Firefox rejects the whole addListener() call on an unknown 'extraHeaders' flag
+        let (stripped, extra_headers_changes) = strip_extra_headers_option(&new_content);
+        new_content = stripped;
+        changes.extend(extra_headers_changes);
+        
+        // Firefox runs alarms at most once a minute
+        let (annotated, alarm_changes) = annotate_short_alarms(&new_content);
+        new_content = annotated;
+        changes.extend(alarm_changes);
+        
         Ok(ModifiedFile {
             path: path.clone(),
             original_content,
@@ -140,13 +175,216 @@ impl JavaScriptTransformer {
     }
 }
 
+/// Manifest-style option names that the scripting API only accepts in camelCase
+const REGISTERED_SCRIPT_KEYS: &[(&str, &str)] = &[
+    ("run_at", "runAt"),
+    ("all_frames", "allFrames"),
+    ("exclude_matches", "excludeMatches"),
+    ("match_about_blank", "matchAboutBlank"),
+    ("match_origin_as_fallback", "matchOriginAsFallback"),
+];
+
+/// Rewrite snake_case keys in registerContentScripts()/updateContentScripts() options
+///
+/// Firefox validates the RegisteredContentScript objects strictly and throws on
+/// unexpected properties such as `run_at` copied over from manifest entries.
+fn normalize_content_script_registrations(content: &str) -> (String, Vec<FileChange>) {
+    let calls = find_calls(content, r"\b(?:chrome|browser)\.scripting\.(?:register|update)ContentScripts");
+    if calls.is_empty() {
+        return (content.to_string(), Vec::new());
+    }
+    
+    let key_re = regex::Regex::new(
+        r#"(["']?)\b(run_at|all_frames|exclude_matches|match_about_blank|match_origin_as_fallback)\b["']?(\s*:)"#
+    ).unwrap();
+    
+    let mut result = content.to_string();
+    let mut changes = Vec::new();
+    
+    // Rewrite back to front so earlier offsets stay valid
+    for call in calls.iter().rev() {
+        let args = call.args(content);
+        
+        if key_re.is_match(args) {
+            let rewritten = key_re.replace_all(args, |caps: &regex::Captures| {
+                let camel = REGISTERED_SCRIPT_KEYS.iter()
+                    .find(|(snake, _)| *snake == &caps[2])
+                    .map(|(_, camel)| *camel)
+                    .unwrap_or(&caps[2]);
+                format!("{}{}{}{}", &caps[1], camel, &caps[1], &caps[3])
+            });
+            result.replace_range(call.args_start..call.args_end, &rewritten);
+            
+            changes.push(FileChange {
+                line_number: call.line,
+                change_type: crate::models::ChangeType::Modification,
+                kind: crate::models::ChangeKind::ApiRemap,
+                description: "Normalized registered content script options to camelCase for Firefox".to_string(),
+                old_code: Some(args.to_string()),
+                new_code: Some(rewritten.into_owned()),
+            });
+        }
+        
+        if args.contains("persistAcrossSessions") {
+            changes.push(FileChange {
+                line_number: call.line,
+                change_type: crate::models::ChangeType::Modification,
+                kind: crate::models::ChangeKind::Other,
+                description: "INFO: persistAcrossSessions behaves differently in Firefox - re-register scripts in runtime.onStartup if they must survive restarts".to_string(),
+                old_code: None,
+                new_code: None,
+            });
+        }
+    }
+    
+    changes.reverse();
+    (result, changes)
+}
+
+/// pageAction members whose action equivalent has a different name
+const PAGE_ACTION_RENAMES: &[(&str, &str)] = &[
+    ("show", "enable"),
+    ("hide", "disable"),
+    ("isShown", "isEnabled"),
+];
+
+/// Rewrite `pageAction.*` / `browserAction.*` member access to `action`
+///
+/// The converted manifest is always Manifest V3, where Firefox only exposes
+/// `action`; `pageAction.show(tabId)`/`hide(tabId)` become `enable(tabId)`/
+/// `disable(tabId)`, and `browserAction` members (e.g. `setPopup`/`getPopup`)
+/// keep their names.
+fn rewrite_legacy_action_calls(content: &str) -> (String, Vec<FileChange>) {
+    let page_action_re = regex::Regex::new(r"\b(chrome|browser)\.(pageAction|browserAction)\.([A-Za-z_$][\w$]*)").unwrap();
+    let mut changes = Vec::new();
+    let mut lines = Vec::new();
+    
+    for (index, line) in content.split('\n').enumerate() {
+        if line.trim_start().starts_with("//") || !page_action_re.is_match(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+        
+        let rewritten = page_action_re.replace_all(line, |caps: &regex::Captures| {
+            let member = PAGE_ACTION_RENAMES.iter()
+                .filter(|_| &caps[2] == "pageAction")
+                .find(|(old, _)| *old == &caps[3])
+                .map(|(_, new)| *new)
+                .unwrap_or(&caps[3]);
+            format!("{}.action.{}", &caps[1], member)
+        }).into_owned();
+        
+        let mut descriptions: Vec<&str> = page_action_re.captures_iter(line)
+            .map(|caps| match caps.get(2).map(|m| m.as_str()) {
+                Some("pageAction") => "Rewrote pageAction to action (Manifest V3 has no page_action)",
+                _ => "Rewrote browserAction to action (Manifest V3 has no browser_action)",
+            })
+            .collect();
+        descriptions.dedup();
+        let description = descriptions.join("; ");
+        changes.push(FileChange {
+            line_number: index + 1,
+            change_type: crate::models::ChangeType::Modification,
+            kind: crate::models::ChangeKind::ApiRemap,
+            description,
+            old_code: Some(line.trim().to_string()),
+            new_code: Some(rewritten.trim().to_string()),
+        });
+        lines.push(rewritten);
+    }
+    
+    (lines.join("\n"), changes)
+}
+
+/// Comment prepended to `alarms.create` calls with sub-minute literal timings
+const ALARM_CLAMP_NOTE: &str = "/* Firefox clamps alarm periods and delays below 1 minute to 1 minute */ ";
+
+/// Note the clamp in front of `alarms.create` calls with literal sub-minute timings
+fn annotate_short_alarms(content: &str) -> (String, Vec<FileChange>) {
+    let mut timings: Vec<_> = crate::analyzer::api::short_alarm_timings(content)
+        .into_iter()
+        .filter(|timing| timing.minutes.is_some())
+        .collect();
+    timings.dedup_by_key(|timing| timing.call_start);
+    
+    let mut result = content.to_string();
+    let mut changes = Vec::new();
+    
+    // Insert back to front so earlier offsets stay valid
+    for timing in timings.iter().rev() {
+        if content[..timing.call_start].ends_with(ALARM_CLAMP_NOTE) {
+            continue;
+        }
+        result.insert_str(timing.call_start, ALARM_CLAMP_NOTE);
+        changes.push(FileChange {
+            line_number: timing.line,
+            change_type: crate::models::ChangeType::Addition,
+            kind: crate::models::ChangeKind::Other,
+            description: format!("Noted that Firefox clamps alarm {} {} to 1 minute", timing.field, timing.value),
+            old_code: None,
+            new_code: Some(ALARM_CLAMP_NOTE.trim().to_string()),
+        });
+    }
+    
+    changes.reverse();
+    (result, changes)
+}
+
+/// Remove `'extraHeaders'` from `webRequest.on*.addListener(listener, filter, [...])`
+///
+/// Chrome needs the flag to expose headers such as `Cookie` or `Referer`;
+/// Firefox always exposes them and throws on the unknown option.
+fn strip_extra_headers_option(content: &str) -> (String, Vec<FileChange>) {
+    let calls = find_calls(content, r"\b(?:chrome|browser)\.webRequest\.on[A-Za-z]+\.addListener");
+    let mut result = content.to_string();
+    let mut changes = Vec::new();
+    
+    // Rewrite back to front so earlier offsets stay valid
+    for call in calls.iter().rev() {
+        let args = call.args(content);
+        let arguments: Vec<&str> = call.arguments(content).into_iter().filter(|a| !a.is_empty()).collect();
+        let Some(options) = arguments.get(2).filter(|o| o.starts_with('[') && o.ends_with(']')) else { continue };
+        
+        let flags = split_top_level(&options[1..options.len() - 1], ',');
+        let kept: Vec<&str> = flags.iter()
+            .copied()
+            .filter(|flag| !flag.is_empty() && !is_extra_headers_flag(flag))
+            .collect();
+        if kept.len() == flags.iter().filter(|flag| !flag.is_empty()).count() {
+            continue;
+        }
+        
+        let Some(offset) = args.rfind(options) else { continue };
+        let rewritten = format!("[{}]", kept.join(", "));
+        let start = call.args_start + offset;
+        result.replace_range(start..start + options.len(), &rewritten);
+        
+        changes.push(FileChange {
+            line_number: call.line,
+            change_type: crate::models::ChangeType::Modification,
+            kind: crate::models::ChangeKind::ApiRemap,
+            description: "Removed 'extraHeaders' from webRequest listener options (not supported in Firefox, which exposes those headers by default)".to_string(),
+            old_code: Some(options.to_string()),
+            new_code: Some(rewritten),
+        });
+    }
+    
+    changes.reverse();
+    (result, changes)
+}
+
+fn is_extra_headers_flag(flag: &str) -> bool {
+    matches!(flag, "'extraHeaders'" | "\"extraHeaders\"" | "`extraHeaders`")
+        || flag.ends_with(".EXTRA_HEADERS")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
     #[test]
     fn test_transform_simple_code() {
-        let mut transformer = JavaScriptTransformer::new(&[]);
+        let transformer = JavaScriptTransformer::new(&[]);
         let code = "chrome.storage.local.get('key');";
         let path = PathBuf::from("test.js");
         
@@ -158,7 +396,7 @@ mod tests {
     
     #[test]
     fn test_transform_typescript() {
-        let mut transformer = JavaScriptTransformer::new(&[]);
+        let transformer = JavaScriptTransformer::new(&[]);
         let code = "const x: string = 'test'; chrome.runtime.id;";
         let path = PathBuf::from("test.ts");
         
@@ -172,7 +410,7 @@ mod tests {
     
     #[test]
     fn test_no_changes_needed() {
-        let mut transformer = JavaScriptTransformer::new(&[]);
+        let transformer = JavaScriptTransformer::new(&[]);
         let code = "const x = 1; console.log(x);";
         let path = PathBuf::from("test.js");
         
@@ -184,7 +422,7 @@ mod tests {
     
     #[test]
     fn test_remove_firefox_uninstall_self() {
-        let mut transformer = JavaScriptTransformer::new(&[]);
+        let transformer = JavaScriptTransformer::new(&[]);
         // Simulating the pattern from the source code
         let code = r#"
             if (this.clientInfo.get().clipperType === 3) {
@@ -203,7 +441,7 @@ mod tests {
     
     #[test]
     fn test_remove_standalone_uninstall_self() {
-        let mut transformer = JavaScriptTransformer::new(&[]);
+        let transformer = JavaScriptTransformer::new(&[]);
         let code = "browser.management.uninstallSelf();";
         let path = PathBuf::from("test.js");
         
@@ -214,4 +452,104 @@ mod tests {
         assert!(result.new_content.contains("DISABLED"));
         assert!(!result.changes.is_empty());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_normalize_register_content_scripts_options() {
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = r#"chrome.scripting.registerContentScripts([{
+    id: 'main',
+    matches: ['https://example.com/*'],
+    js: ['content.js'],
+    run_at: 'document_idle',
+    "all_frames": true,
+    persistAcrossSessions: false
+}]);
+const manifestEntry = { run_at: 'document_end' };
+"#;
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        
+        assert!(result.new_content.contains("runAt: 'document_idle'"));
+        assert!(result.new_content.contains(r#""allFrames": true"#));
+        // Objects outside the call are left alone
+        assert!(result.new_content.contains("{ run_at: 'document_end' }"));
+        assert!(result.changes.iter().any(|c| c.description.contains("persistAcrossSessions")));
+    }
+    
+    #[test]
+    fn test_page_action_rewritten_to_action() {
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = "chrome.pageAction.show(tabId);\nbrowser.pageAction.setIcon({ tabId, path: 'on.png' });\nchrome.pageAction.hide(tabId);\n// chrome.pageAction.show(old);\n";
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        
+        assert!(result.new_content.contains("chrome.action.enable(tabId);"));
+        assert!(result.new_content.contains("browser.action.setIcon({ tabId, path: 'on.png' });"));
+        assert!(result.new_content.contains("chrome.action.disable(tabId);"));
+        assert!(result.new_content.contains("// chrome.pageAction.show(old);"));
+        assert_eq!(result.changes.iter().filter(|c| c.description.contains("pageAction")).count(), 3);
+    }
+    
+    #[test]
+    fn test_browser_action_set_popup_rewritten_to_action() {
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = "chrome.browserAction.setPopup({ popup: loggedIn ? 'menu.html' : 'login.html' });\nconst popup = await browser.browserAction.getPopup({});\n";
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        
+        assert!(result.new_content.contains("chrome.action.setPopup({ popup: loggedIn ? 'menu.html' : 'login.html' });"));
+        assert!(result.new_content.contains("await browser.action.getPopup({});"));
+        assert!(!result.new_content.contains("browserAction"));
+        assert_eq!(result.changes.iter().filter(|c| c.description.contains("browserAction")).count(), 2);
+    }
+    
+    #[test]
+    fn test_storage_get_default_values_preserved() {
+        // Chrome and Firefox both fill missing keys from an object argument;
+        // the defaults must reach storage.get untouched in either call style
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = "chrome.storage.local.get({ enabled: true, count: 0 }, (items) => render(items));\nbrowser.storage.local.get({ enabled: true, count: 0 }).then((items) => render(items));\n";
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        
+        assert_eq!(result.new_content, code);
+        assert!(result.changes.is_empty());
+        let calls = find_calls(&result.new_content, r"\b(?:chrome|browser)\.storage\.local\.get");
+        assert_eq!(calls.len(), 2);
+        assert!(calls.iter().all(|call| call.arguments(&result.new_content)[0] == "{ enabled: true, count: 0 }"));
+    }
+    
+    #[test]
+    fn test_strip_extra_headers_option() {
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = r#"chrome.webRequest.onBeforeSendHeaders.addListener(
+    (details) => ({ requestHeaders: details.requestHeaders }),
+    { urls: ['<all_urls>'] },
+    ['blocking', 'requestHeaders', 'extraHeaders']
+);
+chrome.webRequest.onCompleted.addListener(log, { urls: ['<all_urls>'] }, ['responseHeaders']);"#;
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        assert!(result.new_content.contains("['blocking', 'requestHeaders']"));
+        assert!(!result.new_content.contains("extraHeaders"));
+        assert!(result.new_content.contains("['responseHeaders']"));
+        assert_eq!(result.changes.iter().filter(|c| c.description.contains("extraHeaders")).count(), 1);
+    }
+    
+    #[test]
+    fn test_short_alarm_annotated() {
+        let transformer = JavaScriptTransformer::new(&[]);
+        let code = "chrome.alarms.create('poll', { delayInMinutes: 0.5, periodInMinutes: 0.5 });\nchrome.alarms.create('sync', { periodInMinutes: interval });";
+        let path = PathBuf::from("background.js");
+        
+        let result = transformer.transform(code, &path).unwrap();
+        assert!(result.new_content.starts_with(&format!("{}chrome.alarms.create('poll'", ALARM_CLAMP_NOTE)));
+        assert!(result.new_content.contains("\nchrome.alarms.create('sync'"));
+        assert_eq!(result.changes.iter().filter(|c| c.description.contains("clamps alarm")).count(), 1);
+    }
+}
@@ -1,29 +1,221 @@
 //! Manifest transformation for Firefox compatibility
 
 use crate::models::{
-    Manifest, BrowserSpecificSettings, GeckoSettings,
-    ContentSecurityPolicy, ContentSecurityPolicyV3, WebAccessibleResources,
+    Manifest, BrowserSpecificSettings, GeckoSettings, DataCollectionPermissions,
+    ContentSecurityPolicy, ContentSecurityPolicyV3, WebAccessibleResources, WebAccessibleResourceV3,
     SelectedDecision, Extension,
 };
+use crate::transformer::capabilities::Feature;
+use crate::utils::helpers::normalize_firefox_version;
 use anyhow::Result;
 use regex::Regex;
 
+/// Shim scripts loaded ahead of the background scripts by default
+/// (mirrors the always-included files produced by `generate_shims`, in `shim_load_order`)
+const DEFAULT_SHIM_SCRIPTS: &[&str] = &[
+    "shims/declarative-net-request-stub.js",
+    "shims/downloads-compat.js",
+    "shims/execute-script-compat.js",
+    "shims/notifications-compat.js",
+    "shims/privacy-stub.js",
+    "shims/runtime-compat.js",
+    "shims/sidepanel-compat.js",
+    "shims/storage-session-compat.js",
+    "shims/tabs-windows-compat.js",
+    "shims/user-scripts-compat.js",
+];
+
+/// strict_min_version used when the source gives no better hint
+pub const DEFAULT_STRICT_MIN_VERSION: &str = "121.0";
+
+/// Heuristic Chrome → Firefox version floors: (Chrome version, feature it brought, description)
+/// The Firefox floor is the first release with the same feature.
+const CHROME_VERSION_FLOORS: &[(u32, Feature, &str)] = &[
+    (88, Feature::ManifestV3, "Manifest V3"),
+    (102, Feature::StorageSession, "storage.session"),
+    (111, Feature::MainWorldScripts, "scripting MAIN world execution"),
+];
+
+/// Firefox floors for APIs found in the code: (call pattern, feature needing the floor, API name)
+const API_VERSION_FLOORS: &[(&str, Feature, &str)] = &[
+    // action.openPopup without a user gesture
+    (r"\b(?:chrome|browser)\.action\.openPopup\s*\(", Feature::ActionOpenPopup, "action.openPopup"),
+];
+
+/// Manifest keys of other Chromium-based browsers that Firefox doesn't support
+const NON_FIREFOX_VENDOR_FIELDS: &[(&str, &str)] = &[
+    ("key", "Chrome/Edge public key used to pin the extension ID"),
+    ("update_url", "Chrome Web Store / Edge Add-ons auto-update URL"),
+    ("minimum_chrome_version", "Chrome version requirement"),
+    ("minimum_opera_version", "Opera version requirement"),
+    ("oauth2", "Chrome identity OAuth2 configuration"),
+    ("export", "Chrome shared module exports"),
+    ("import", "Chrome shared module imports"),
+    ("differential_fingerprint", "Chrome Web Store packaging metadata"),
+    ("storage", "Chrome/Edge managed storage schema (managed_schema)"),
+    ("-ms-preload", "legacy Edge preload configuration"),
+];
+
+/// Standard manifest keys Firefox doesn't implement
+const FIREFOX_UNSUPPORTED_FIELDS: &[(&str, &str)] = &[
+    ("externally_connectable", "web pages can't message the extension"),
+];
+
+/// Chrome theme color keys Firefox names differently, and their Firefox `theme.colors` names
+const THEME_COLOR_RENAMES: &[(&str, &str)] = &[
+    ("omnibox_background", "toolbar_field"),
+    ("omnibox_text", "toolbar_field_text"),
+];
+
+/// Chrome theme color keys with no Firefox equivalent
+const CHROME_ONLY_THEME_COLORS: &[&str] = &[
+    "frame_incognito",
+    "frame_incognito_inactive",
+    "background_tab",
+    "background_tab_inactive",
+    "background_tab_incognito",
+    "background_tab_incognito_inactive",
+    "button_background",
+    "control_button_background",
+    "toolbar_button_icon",
+    "ntp_header",
+    "ntp_link",
+    "ntp_section",
+    "ntp_section_text",
+    "ntp_section_link",
+];
+
+/// Firefox floor derived from `minimum_chrome_version`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChromeVersionFloor {
+    pub chrome_version: String,
+    pub strict_min_version: String,
+    pub reason: String,
+}
+
+/// What a manifest transform changed, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestTransformReport {
+    pub changes: Vec<ManifestTransformChange>,
+}
+
+impl ManifestTransformReport {
+    /// Whether a change of `kind` was recorded
+    pub fn contains(&self, kind: ManifestTransformKind) -> bool {
+        self.changes.iter().any(|change| change.kind == kind)
+    }
+}
+
+/// One change made by [`ManifestTransformer::transform_with_report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestTransformChange {
+    pub kind: ManifestTransformKind,
+    /// Manifest key the change applies to
+    pub field: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestTransformKind {
+    GeckoSettingsAdded,
+    BackgroundConverted,
+    FieldRemoved,
+    UnsupportedFieldRemoved,
+    ActionRenamed,
+    CspUpgraded,
+    PermissionsMoved,
+    VersionFloorRaised,
+    KeyPreserved,
+    WebAccessibleResourcesAdded,
+    ScriptsInjected,
+}
+
 pub struct ManifestTransformer {
     _decisions: Vec<SelectedDecision>,
+    shim_scripts: Vec<String>,
+    content_script_prelude: Vec<String>,
+    data_collection_permissions: Option<DataCollectionPermissions>,
+    add_web_accessible_resources: bool,
+    preserve_all_frames: bool,
+    preserve_chrome_key: bool,
+    target_version: Option<u32>,
 }
 
 impl ManifestTransformer {
     pub fn new(decisions: &[SelectedDecision]) -> Self {
         Self {
             _decisions: decisions.to_vec(),
+            shim_scripts: DEFAULT_SHIM_SCRIPTS.iter().map(|s| s.to_string()).collect(),
+            content_script_prelude: Vec::new(),
+            data_collection_permissions: None,
+            add_web_accessible_resources: false,
+            preserve_all_frames: false,
+            preserve_chrome_key: false,
+            target_version: None,
         }
     }
     
+    /// Declare `gecko.data_collection_permissions` unless the source already does
+    pub fn with_data_collection_permissions(mut self, permissions: Option<DataCollectionPermissions>) -> Self {
+        self.data_collection_permissions = permissions;
+        self
+    }
+    
+    /// Expose resources content scripts load via `runtime.getURL()` (requires a source extension)
+    pub fn with_web_accessible_additions(mut self, enabled: bool) -> Self {
+        self.add_web_accessible_resources = enabled;
+        self
+    }
+    
+    /// Keep each content script's declared `all_frames` instead of enabling it
+    pub fn with_preserved_all_frames(mut self, preserve: bool) -> Self {
+        self.preserve_all_frames = preserve;
+        self
+    }
+    
+    /// Keep the manifest `key` so a shared Chrome build keeps its extension ID
+    /// (Firefox ignores `key`, Chrome ignores `browser_specific_settings`)
+    pub fn with_preserved_chrome_key(mut self, preserve: bool) -> Self {
+        self.preserve_chrome_key = preserve;
+        self
+    }
+    
+    /// Require at least Firefox `version` (a major version) in strict_min_version
+    pub fn with_target_version(mut self, version: Option<u32>) -> Self {
+        self.target_version = version;
+        self
+    }
+    
+    /// Scripts prepended to every content script's `js` list (e.g. the namespace polyfill)
+    pub fn with_content_script_prelude(mut self, scripts: Vec<String>) -> Self {
+        self.content_script_prelude = scripts;
+        self
+    }
+    
+    /// Override the shim scripts injected ahead of the background scripts
+    /// (an empty list injects none, e.g. when no shims are generated)
+    pub fn with_shim_scripts(mut self, shim_scripts: Vec<String>) -> Self {
+        self.shim_scripts = shim_scripts;
+        self
+    }
+    
     pub fn transform(&self, manifest: &Manifest, source: Option<&Extension>) -> Result<Manifest> {
+        self.transform_with_report(manifest, source).map(|(result, _)| result)
+    }
+    
+    /// Transform `manifest` and list what changed
+    pub fn transform_with_report(&self, manifest: &Manifest, source: Option<&Extension>) -> Result<(Manifest, ManifestTransformReport)> {
+        let result = self.apply_transforms(manifest, source);
+        let report = self.report_changes(manifest, &result, source);
+        Ok((result, report))
+    }
+    
+    fn apply_transforms(&self, manifest: &Manifest, source: Option<&Extension>) -> Manifest {
         let mut result = manifest.clone();
         
         // 1. Add Firefox-specific settings
         self.add_firefox_settings(&mut result);
+        self.apply_version_floors(&mut result, source);
         
         // 2. Transform background configuration
         self.transform_background(&mut result, source);
@@ -33,46 +225,307 @@ impl ManifestTransformer {
         
         // 4. Fix web_accessible_resources
         self.transform_web_accessible_resources(&mut result);
+        if let Some(source) = source {
+            Self::expose_redirect_targets(&mut result, source);
+            if self.add_web_accessible_resources {
+                Self::add_missing_web_accessible_resources(&mut result, source);
+            }
+        }
         
         // 5. Fix CSP format
         self.transform_csp(&mut result);
         
-        // 6. Fix action/browser_action
+        // 6. Fix action/browser_action/page_action
         self.transform_action(&mut result);
         
         // 7. Fix content scripts for iframe support
-        self.fix_content_scripts(&mut result);
+        self.fix_content_scripts(&mut result, source);
+        
+        // 8. Remove Chrome/Edge/Opera-specific fields and ones Firefox lacks
+        self.remove_vendor_specific_fields(&mut result);
         
-        // 8. Remove Chrome-specific fields
-        self.remove_chrome_specific_fields(&mut result);
+        // 9. Normalize version format
+        self.normalize_version(&mut result);
+        
+        // 10. Ensure permissions required by APIs used in the code
+        self.ensure_scripting_permission(&mut result, source);
+        
+        // 11. Map Chrome theme keys to Firefox's theme schema
+        self.transform_theme(&mut result);
+        
+        result
+    }
+    
+    /// Compare the source manifest with its transformed version
+    fn report_changes(&self, original: &Manifest, result: &Manifest, source: Option<&Extension>) -> ManifestTransformReport {
+        let mut changes = Vec::new();
+        let mut record = |kind, field: &str, description: String| {
+            changes.push(ManifestTransformChange { kind, field: field.to_string(), description });
+        };
+        
+        if original.browser_specific_settings.is_none() && result.browser_specific_settings.is_some() {
+            record(
+                ManifestTransformKind::GeckoSettingsAdded,
+                "browser_specific_settings",
+                "Added browser_specific_settings.gecko.id for Firefox".to_string(),
+            );
+        }
+        if original.background.as_ref().and_then(|b| b.service_worker.as_ref()).is_some() {
+            record(
+                ManifestTransformKind::BackgroundConverted,
+                "background",
+                "Added background.scripts for Firefox event page compatibility".to_string(),
+            );
+        }
+        
+        let moved: Vec<&str> = original.permissions.iter()
+            .filter(|p| is_match_pattern(p) && result.host_permissions.contains(p))
+            .map(String::as_str)
+            .collect();
+        if !moved.is_empty() {
+            record(
+                ManifestTransformKind::PermissionsMoved,
+                "permissions",
+                format!("Moved {} from permissions to host_permissions", moved.join(", ")),
+            );
+        }
+        
+        if matches!(original.content_security_policy, Some(ContentSecurityPolicy::V2(_))) {
+            record(
+                ManifestTransformKind::CspUpgraded,
+                "content_security_policy",
+                "Converted content_security_policy to the Manifest V3 extension_pages form".to_string(),
+            );
+        }
+        
+        if original.browser_action.is_some() && result.browser_action.is_none() {
+            record(
+                ManifestTransformKind::ActionRenamed,
+                "browser_action",
+                "Renamed browser_action to action".to_string(),
+            );
+        }
+        if original.extra.contains_key("page_action") && !result.extra.contains_key("page_action") {
+            let description = if original.action.is_none() && original.browser_action.is_none() {
+                "Renamed page_action to action"
+            } else {
+                "Removed page_action (the extension already has an action)"
+            };
+            record(ManifestTransformKind::ActionRenamed, "page_action", description.to_string());
+        }
+        
+        for (field, description) in self.vendor_specific_fields(original) {
+            record(
+                ManifestTransformKind::FieldRemoved,
+                field,
+                format!("Removed non-Firefox manifest field '{}' ({})", field, description),
+            );
+        }
+        for (field, consequence) in Self::unsupported_fields(original) {
+            record(
+                ManifestTransformKind::UnsupportedFieldRemoved,
+                field,
+                format!("Removed '{}', which Firefox doesn't support ({})", field, consequence),
+            );
+        }
+        if self.preserve_chrome_key && original.extra.contains_key("key") {
+            record(
+                ManifestTransformKind::KeyPreserved,
+                "key",
+                "Kept 'key' so the Chrome build keeps its extension ID (Firefox ignores it)".to_string(),
+            );
+        }
+        
+        let original_gecko = original.browser_specific_settings.as_ref().and_then(|b| b.gecko.as_ref());
+        let chrome_floor = Self::chrome_version_floor(original);
+        let mut current = match (original_gecko.and_then(|g| g.strict_min_version.as_deref()), &chrome_floor) {
+            (Some(version), _) => version_major(version),
+            (None, Some(floor)) => version_major(&floor.strict_min_version),
+            (None, None) => version_major(DEFAULT_STRICT_MIN_VERSION),
+        };
+        if let Some(floor) = chrome_floor.filter(|_| original_gecko.is_none_or(|g| g.strict_min_version.is_none())) {
+            if version_major(&floor.strict_min_version) > version_major(DEFAULT_STRICT_MIN_VERSION) {
+                record(
+                    ManifestTransformKind::VersionFloorRaised,
+                    "minimum_chrome_version",
+                    format!(
+                        "Replaced minimum_chrome_version {} with Firefox strict_min_version floor {} ({})",
+                        floor.chrome_version, floor.strict_min_version, floor.reason
+                    ),
+                );
+            }
+        }
+        
+        // Floors already met by the version above don't change anything
+        for (floor, description) in self.version_floors(result, source) {
+            if floor > current {
+                current = floor;
+                record(ManifestTransformKind::VersionFloorRaised, "browser_specific_settings", description);
+            }
+        }
+        
+        if let Some(source) = source {
+            let redirect_targets = Self::unexposed_redirect_targets(source);
+            if !redirect_targets.is_empty() {
+                record(
+                    ManifestTransformKind::WebAccessibleResourcesAdded,
+                    "web_accessible_resources",
+                    format!("Added DNR redirect target(s) {} to web_accessible_resources", redirect_targets.join(", ")),
+                );
+            }
+            if self.add_web_accessible_resources {
+                let missing = crate::analyzer::web_accessible::missing_web_accessible_resources(source);
+                if !missing.is_empty() {
+                    let resources: Vec<&str> = missing.iter().map(|m| m.resource.as_str()).collect();
+                    record(
+                        ManifestTransformKind::WebAccessibleResourcesAdded,
+                        "web_accessible_resources",
+                        format!("Added {} to web_accessible_resources for content script getURL() loads", resources.join(", ")),
+                    );
+                }
+            }
+        }
+        
+        for script in &self.content_script_prelude {
+            let targets = match (self.shim_scripts.contains(script), !result.content_scripts.is_empty()) {
+                (true, true) => "background and content scripts",
+                (true, false) => "background scripts",
+                (false, true) => "content scripts",
+                (false, false) => continue,
+            };
+            record(
+                ManifestTransformKind::ScriptsInjected,
+                "content_scripts",
+                format!("Injected {} ahead of {}", script, targets),
+            );
+        }
+        
+        ManifestTransformReport { changes }
+    }
+    
+    /// Map `minimum_chrome_version` to a Firefox `strict_min_version` floor
+    ///
+    /// The result never goes below `DEFAULT_STRICT_MIN_VERSION`.
+    pub fn chrome_version_floor(manifest: &Manifest) -> Option<ChromeVersionFloor> {
+        let chrome_version = manifest.extra.get("minimum_chrome_version")?.as_str()?;
+        let major: u32 = chrome_version.split('.').next()?.trim().parse().ok()?;
+        
+        let (strict_min_version, reason) = match CHROME_VERSION_FLOORS.iter().rev().find(|(chrome, _, _)| major >= *chrome) {
+            Some((chrome, feature, description)) if feature.min_version() > version_major(DEFAULT_STRICT_MIN_VERSION) => (
+                format!("{}.0", feature.min_version()),
+                format!("Chrome {}+ provides {}, first available in Firefox {}.0", chrome, description, feature.min_version()),
+            ),
+            _ => (
+                DEFAULT_STRICT_MIN_VERSION.to_string(),
+                format!("features up to Chrome {} are covered by the default Firefox {} floor", major, DEFAULT_STRICT_MIN_VERSION),
+            ),
+        };
         
-        Ok(result)
+        Some(ChromeVersionFloor {
+            chrome_version: chrome_version.to_string(),
+            strict_min_version,
+            reason,
+        })
     }
     
     fn add_firefox_settings(&self, manifest: &mut Manifest) {
+        let min_version = Self::chrome_version_floor(manifest)
+            .map(|floor| floor.strict_min_version)
+            .unwrap_or_else(|| DEFAULT_STRICT_MIN_VERSION.to_string());
+        
+        // Fill in a missing floor on existing gecko settings
+        if let Some(gecko) = manifest.browser_specific_settings.as_mut().and_then(|b| b.gecko.as_mut()) {
+            if gecko.strict_min_version.is_none() {
+                gecko.strict_min_version = Some(min_version.clone());
+            }
+            if gecko.data_collection_permissions.is_none() {
+                gecko.data_collection_permissions = self.data_collection_permissions.clone();
+            }
+        }
+        
         if manifest.browser_specific_settings.is_none() {
-            // Generate Firefox-compliant email-style ID
-            // Pattern: [a-zA-Z0-9-._]*@[a-zA-Z0-9-._]+
-            let sanitized_name = Self::sanitize_extension_name(&manifest.name);
-            let extension_id = format!("{}@converted-extension.org", sanitized_name);
+            let extension_id = Self::firefox_extension_id(manifest);
             
             manifest.browser_specific_settings = Some(BrowserSpecificSettings {
                 gecko: Some(GeckoSettings {
                     id: extension_id,
-                    strict_min_version: Some("121.0".to_string()),
+                    strict_min_version: Some(min_version),
                     strict_max_version: None,
+                    data_collection_permissions: self.data_collection_permissions.clone(),
                 }),
             });
         }
     }
     
+    /// Raise strict_min_version to the target version and to the first Firefox
+    /// release supporting APIs the code calls and declared manifest keys
+    fn apply_version_floors(&self, manifest: &mut Manifest, source: Option<&Extension>) {
+        let floors = self.version_floors(manifest, source);
+        let Some(gecko) = manifest.browser_specific_settings.as_mut().and_then(|b| b.gecko.as_mut()) else { return };
+        
+        for (floor, _) in floors {
+            let current = gecko.strict_min_version.as_deref().map(version_major).unwrap_or(0);
+            if current < floor {
+                gecko.strict_min_version = Some(format!("{}.0", floor));
+            }
+        }
+    }
+    
+    /// Firefox versions `manifest` and the code need, each with a report description
+    fn version_floors(&self, manifest: &Manifest, source: Option<&Extension>) -> Vec<(u32, String)> {
+        let mut floors: Vec<(u32, String)> = self.target_version
+            .map(|version| (version, format!("Raised strict_min_version to the targeted Firefox {}", version)))
+            .into_iter()
+            .collect();
+        if let Some(source) = source {
+            floors.extend(API_VERSION_FLOORS.iter()
+                .filter(|(pattern, _, _)| source.javascript_matches(&Regex::new(pattern).unwrap()))
+                .map(|(_, feature, api)| {
+                    (feature.min_version(), format!("Required Firefox {}+ (strict_min_version) for {}", feature.min_version(), api))
+                }));
+        }
+        let declares_data_collection = manifest.browser_specific_settings.as_ref()
+            .and_then(|b| b.gecko.as_ref())
+            .is_some_and(|g| g.data_collection_permissions.is_some());
+        if declares_data_collection {
+            let version = Feature::DataCollectionPermissions.min_version();
+            floors.push((version, format!("Required Firefox {}+ (strict_min_version) for data_collection_permissions", version)));
+        }
+        floors
+    }
+    
+    /// Gecko ID of the converted extension: the declared one, or the one generated from the name
+    pub fn firefox_extension_id(manifest: &Manifest) -> String {
+        if let Some(gecko) = manifest.browser_specific_settings.as_ref().and_then(|b| b.gecko.as_ref()) {
+            return gecko.id.clone();
+        }
+        
+        // Generate Firefox-compliant email-style ID
+        // Pattern: [a-zA-Z0-9-._]*@[a-zA-Z0-9-._]+
+        let sanitized = Self::sanitize_extension_name(&manifest.name);
+        let id = format!("{}@converted-extension.org", sanitized);
+        if !sanitized.is_empty() && crate::utils::helpers::is_valid_gecko_id(&id) {
+            return id;
+        }
+        
+        // Names without usable characters get a braced UUID, derived from the
+        // name so repeated conversions keep the same ID
+        let name = manifest.name.as_bytes();
+        let hex = format!(
+            "{}{}",
+            crate::utils::helpers::fnv1a_hex([name]),
+            crate::utils::helpers::fnv1a_hex([b"chrome2moz-gecko-id:".as_slice(), name])
+        );
+        format!("{{{}-{}-4{}-8{}-{}}}", &hex[0..8], &hex[8..12], &hex[13..16], &hex[17..20], &hex[20..32])
+    }
+    
     /// Sanitize extension name to be valid in Firefox email-style IDs
     /// Only allows: a-z, A-Z, 0-9, hyphen, dot, underscore
     fn sanitize_extension_name(name: &str) -> String {
         name.chars()
             .map(|c| {
-                if c.is_alphanumeric() || c == '-' || c == '.' || c == '_' {
-                    c.to_lowercase().to_string()
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_' {
+                    c.to_ascii_lowercase().to_string()
                 } else if c.is_whitespace() {
                     "-".to_string()
                 } else {
@@ -90,36 +543,27 @@ impl ManifestTransformer {
     fn transform_background(&self, manifest: &mut Manifest, source: Option<&Extension>) {
         if let Some(background) = &mut manifest.background {
             // Build the scripts array with shims FIRST, then original scripts
-            let mut scripts = vec![];
-            
             // CRITICAL: Add all shims BEFORE the background scripts (no importScripts polyfill needed!)
-            scripts.push("shims/storage-session-compat.js".to_string());
-            scripts.push("shims/execute-script-compat.js".to_string());
-            scripts.push("shims/sidepanel-compat.js".to_string());
-            scripts.push("shims/declarative-net-request-stub.js".to_string());
-            scripts.push("shims/user-scripts-compat.js".to_string());
-            scripts.push("shims/tabs-windows-compat.js".to_string());
-            scripts.push("shims/runtime-compat.js".to_string());
-            scripts.push("shims/downloads-compat.js".to_string());
-            scripts.push("shims/privacy-stub.js".to_string());
-            scripts.push("shims/notifications-compat.js".to_string());
+            let mut scripts = self.shim_scripts.clone();
+            
+            // Add original background scripts (and extract importScripts). A hybrid
+            // background declares both `scripts` and `service_worker`; keep the union.
+            let entries: Vec<String> = background.scripts.iter()
+                .flatten()
+                .chain(background.service_worker.iter())
+                .cloned()
+                .collect();
             
-            // Add original background scripts (and extract importScripts)
-            if let Some(existing_scripts) = &background.scripts {
-                for script in existing_scripts {
-                    // Try to extract importScripts() calls from this script
-                    if let Some(imported) = Self::extract_imported_scripts(script, source) {
-                        // Add imported scripts before the main script
-                        scripts.extend(imported);
+            for script in entries {
+                // Add imported scripts before the script that imports them
+                for imported in Self::extract_imported_scripts(&script, source).unwrap_or_default() {
+                    if !scripts.contains(&imported) {
+                        scripts.push(imported);
                     }
-                    scripts.push(script.clone());
                 }
-            } else if let Some(sw) = &background.service_worker {
-                // Try to extract importScripts() calls from service worker
-                if let Some(imported) = Self::extract_imported_scripts(sw, source) {
-                    scripts.extend(imported);
+                if !scripts.contains(&script) {
+                    scripts.push(script);
                 }
-                scripts.push(sw.clone());
             }
             
             background.scripts = Some(scripts);
@@ -138,37 +582,48 @@ impl ManifestTransformer {
     /// Extract script names from importScripts() calls using regex
     /// This is SAFE - no eval() needed! We parse the calls and add scripts to manifest.
     /// Handles both commented and uncommented importScripts() calls.
+    ///
+    /// Imports are followed transitively and returned in load order (a file's
+    /// own imports come before it); cycles are ignored. As in a worker, every
+    /// relative path resolves against the background script's directory, also
+    /// inside imported scripts.
     fn extract_imported_scripts(script_path: &str, source: Option<&Extension>) -> Option<Vec<String>> {
+        let source = source?;
+        let mut visited = vec![script_path.to_string()];
+        let mut imported = Vec::new();
+        
+        Self::collect_imported_scripts(script_path, script_path, source, &mut visited, &mut imported);
+        
+        if !imported.is_empty() {
+            Some(imported)
+        } else {
+            None
+        }
+    }
+    
+    fn collect_imported_scripts(script_path: &str, entry: &str, source: &Extension, visited: &mut Vec<String>, imported: &mut Vec<String>) {
         // Read the script file content
-        let content = source?.get_file_content(&std::path::PathBuf::from(script_path))?;
+        let Some(content) = source.get_file_content(&std::path::PathBuf::from(script_path)) else { return };
         
         // Match importScripts() calls, including commented out ones
         // Pattern: optional // comment, then importScripts(...)
-        let re = Regex::new(r#"(?://\s*)?importScripts\s*\([^)]*\)"#).ok()?;
-        
-        let mut imported = Vec::new();
+        let re = Regex::new(r#"(?://\s*)?importScripts\s*\([^)]*\)"#).unwrap();
+        let file_re = Regex::new(r#"['"]([^'"]+)['"]"#).unwrap();
         
         // Find all importScripts() calls (commented or not)
         for call_match in re.find_iter(&content) {
-            let call = call_match.as_str();
-            
             // Extract each quoted string (file name) from the call
-            let file_re = Regex::new(r#"['"]([^'"]+)['"]"#).ok()?;
-            for file_cap in file_re.captures_iter(call) {
-                if let Some(file) = file_cap.get(1) {
-                    let filename = file.as_str().to_string();
-                    if !imported.contains(&filename) {
-                        imported.push(filename);
-                    }
+            for file_cap in file_re.captures_iter(call_match.as_str()) {
+                let resolved = resolve_import_path(entry, &file_cap[1], source);
+                if visited.contains(&resolved) {
+                    continue;
                 }
+                visited.push(resolved.clone());
+                
+                Self::collect_imported_scripts(&resolved, entry, source, visited, imported);
+                imported.push(resolved);
             }
         }
-        
-        if !imported.is_empty() {
-            Some(imported)
-        } else {
-            None
-        }
     }
     
     fn transform_permissions(&self, manifest: &mut Manifest) {
@@ -207,6 +662,63 @@ impl ManifestTransformer {
         }
     }
     
+    /// Extension pages targeted by DNR `redirect.extensionPath` that aren't web-accessible yet
+    pub fn unexposed_redirect_targets(source: &Extension) -> Vec<String> {
+        crate::analyzer::dnr::redirect_extension_paths(source)
+            .into_iter()
+            .filter(|path| !crate::analyzer::web_accessible::is_web_accessible(&source.manifest, path))
+            .collect()
+    }
+    
+    /// Expose DNR redirect target pages to every site, since any page's requests may be redirected
+    fn expose_redirect_targets(manifest: &mut Manifest, source: &Extension) {
+        let targets = Self::unexposed_redirect_targets(source);
+        if !targets.is_empty() {
+            Self::append_web_accessible_entry(manifest, targets, vec!["<all_urls>".to_string()]);
+        }
+    }
+    
+    /// Append an entry exposing each content-script `getURL()` resource that isn't web-accessible
+    fn add_missing_web_accessible_resources(manifest: &mut Manifest, source: &Extension) {
+        let missing = crate::analyzer::web_accessible::missing_web_accessible_resources(source);
+        if missing.is_empty() {
+            return;
+        }
+        
+        let resources: Vec<String> = missing.iter().map(|m| m.resource.clone()).collect();
+        // Resource matches only take origins, so content script paths are dropped
+        let mut matches: Vec<String> = missing.into_iter()
+            .flat_map(|m| m.matches)
+            .map(|pattern| origin_match_pattern(&pattern))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        Self::append_web_accessible_entry(manifest, resources, matches);
+    }
+    
+    fn append_web_accessible_entry(manifest: &mut Manifest, resources: Vec<String>, matches: Vec<String>) {
+        match &mut manifest.web_accessible_resources {
+            Some(WebAccessibleResources::V2(patterns)) => patterns.extend(resources),
+            Some(WebAccessibleResources::V3(entries)) => entries.push(WebAccessibleResourceV3 {
+                resources,
+                matches: Some(matches),
+                extension_ids: None,
+                use_dynamic_url: None,
+            }),
+            None if manifest.manifest_version < 3 => {
+                manifest.web_accessible_resources = Some(WebAccessibleResources::V2(resources));
+            }
+            None => {
+                manifest.web_accessible_resources = Some(WebAccessibleResources::V3(vec![WebAccessibleResourceV3 {
+                    resources,
+                    matches: Some(matches),
+                    extension_ids: None,
+                    use_dynamic_url: None,
+                }]));
+            }
+        }
+    }
+    
     fn transform_csp(&self, manifest: &mut Manifest) {
         // Convert V2 CSP to V3 format
         if let Some(ContentSecurityPolicy::V2(csp_string)) = &manifest.content_security_policy {
@@ -241,35 +753,137 @@ impl ManifestTransformer {
             manifest.browser_action = None;
         }
         
+        // page_action calls are rewritten to action, so the manifest key follows;
+        // when an action already exists, page_action is dropped
+        if let Some(page_action) = manifest.extra.shift_remove("page_action") {
+            if manifest.action.is_none() {
+                manifest.action = serde_json::from_value(page_action).ok();
+            }
+        }
+        
         // Remove browser_style (not supported in MV3)
         if let Some(action) = &mut manifest.action {
             action.browser_style = None;
         }
     }
     
-    fn fix_content_scripts(&self, manifest: &mut Manifest) {
+    fn fix_content_scripts(&self, manifest: &mut Manifest, source: Option<&Extension>) {
+        let has_active_tab = manifest.permissions.iter().any(|p| p == "activeTab");
+        let extension_api_re = Regex::new(r"\b(?:chrome|browser)\.").unwrap();
+        
         // Enable all_frames for content scripts to work in iframes
         for content_script in &mut manifest.content_scripts {
-            // If all_frames is false, enable it to support iframe content
-            if !content_script.all_frames {
+            // If all_frames is false, enable it to support iframe content.
+            // CSS-only entries keep their setting: forcing it would restyle every frame.
+            // All-URL scripts in activeTab extensions aren't widened either (the
+            // analyzer leaves an advisory instead).
+            let widens_active_tab_scope = has_active_tab
+                && content_script.matches.iter().any(|p| crate::analyzer::manifest::is_all_urls_pattern(p));
+            if !content_script.all_frames
+                && !content_script.js.is_empty()
+                && !self.preserve_all_frames
+                && !widens_active_tab_scope
+            {
                 content_script.all_frames = true;
             }
+            
+            // Load prelude scripts first (CSS-only entries stay CSS-only). With the
+            // source at hand, entries whose scripts never touch chrome.*/browser.*
+            // are left alone; unreadable scripts count as using them.
+            let uses_extension_apis = match source {
+                Some(source) => content_script.js.iter().any(|js| {
+                    match source.get_file_content(&std::path::PathBuf::from(js.trim_start_matches('/'))) {
+                        Some(content) => extension_api_re.is_match(&content),
+                        None => true,
+                    }
+                }),
+                None => true,
+            };
+            if !content_script.js.is_empty() && uses_extension_apis {
+                let mut js: Vec<String> = self.content_script_prelude.iter()
+                    .filter(|script| !content_script.js.contains(script))
+                    .cloned()
+                    .collect();
+                js.append(&mut content_script.js);
+                content_script.js = js;
+            }
         }
     }
     
-    fn remove_chrome_specific_fields(&self, manifest: &mut Manifest) {
-        // Remove Chrome-specific fields that Firefox doesn't support
-        let chrome_only_fields = vec![
-            "key",           // Chrome's public key for extension signing
-            "update_url",    // Chrome's auto-update URL
-            "minimum_chrome_version",  // Chrome version requirement
-            "oauth2",        // Chrome's OAuth2 configuration
-            "export",        // Chrome's export configuration
-        ];
+    fn remove_vendor_specific_fields(&self, manifest: &mut Manifest) {
+        for (field, _) in self.vendor_specific_fields(manifest).into_iter().chain(Self::unsupported_fields(manifest)) {
+            manifest.extra.shift_remove(field);
+        }
+    }
+    
+    /// Vendor-specific fields present in `manifest` that the transform removes
+    pub fn vendor_specific_fields(&self, manifest: &Manifest) -> Vec<(&'static str, &'static str)> {
+        NON_FIREFOX_VENDOR_FIELDS.iter()
+            .filter(|(field, _)| !(self.preserve_chrome_key && *field == "key"))
+            .filter(|(field, _)| manifest.extra.contains_key(*field))
+            .copied()
+            .collect()
+    }
+    
+    /// Standard fields present in `manifest` that Firefox doesn't implement
+    pub fn unsupported_fields(manifest: &Manifest) -> Vec<(&'static str, &'static str)> {
+        FIREFOX_UNSUPPORTED_FIELDS.iter()
+            .filter(|(field, _)| manifest.extra.contains_key(*field))
+            .copied()
+            .collect()
+    }
+    
+    fn normalize_version(&self, manifest: &mut Manifest) {
+        if let Some(normalized) = normalize_firefox_version(&manifest.version) {
+            // Keep the original string visible to users via version_name
+            manifest.extra
+                .entry("version_name".to_string())
+                .or_insert_with(|| serde_json::Value::String(manifest.version.clone()));
+            manifest.version = normalized;
+        }
+    }
+    
+    fn ensure_scripting_permission(&self, manifest: &mut Manifest, source: Option<&Extension>) {
+        let Some(source) = source else { return };
+        if manifest.permissions.iter().any(|p| p == "scripting") {
+            return;
+        }
+        
+        let scripting_re = Regex::new(r"\b(?:chrome|browser)\.scripting\.").unwrap();
+        if source.javascript_matches(&scripting_re) {
+            manifest.permissions.push("scripting".to_string());
+        }
+    }
+    
+    /// Keep the theme keys Firefox understands, renaming Chrome-only ones
+    ///
+    /// Chrome's `tints`, `properties`, images other than `theme_frame` and the
+    /// colors in `CHROME_ONLY_THEME_COLORS` have no Firefox equivalent and are
+    /// dropped; other colors, including Firefox-only ones, are kept.
+    fn transform_theme(&self, manifest: &mut Manifest) {
+        let Some(theme) = manifest.extra.get("theme").and_then(|t| t.as_object()) else { return };
+        let mut firefox_theme = serde_json::Map::new();
+        
+        if let Some(frame) = theme.get("images").and_then(|i| i.get("theme_frame")) {
+            firefox_theme.insert("images".to_string(), serde_json::json!({ "theme_frame": frame }));
+        }
         
-        for field in chrome_only_fields {
-            manifest.extra.remove(field);
+        if let Some(colors) = theme.get("colors").and_then(|c| c.as_object()) {
+            let mapped: serde_json::Map<String, serde_json::Value> = colors.iter()
+                .filter(|(key, _)| !CHROME_ONLY_THEME_COLORS.contains(&key.as_str()))
+                .map(|(key, value)| {
+                    let firefox = THEME_COLOR_RENAMES.iter()
+                        .find(|(chrome, _)| chrome == key)
+                        .map_or(key.as_str(), |(_, firefox)| firefox);
+                    (firefox.to_string(), value.clone())
+                })
+                .collect();
+            if !mapped.is_empty() {
+                firefox_theme.insert("colors".to_string(), serde_json::Value::Object(mapped));
+            }
         }
+        
+        manifest.extra.insert("theme".to_string(), serde_json::Value::Object(firefox_theme));
     }
     
     fn _get_decision_value(&self, decision_id: &str) -> Option<String> {
@@ -280,13 +894,58 @@ impl ManifestTransformer {
     }
 }
 
+fn version_major(version: &str) -> u32 {
+    version.split('.').next().and_then(|v| v.parse().ok()).unwrap_or(0)
+}
+
+/// Resolve an importScripts() argument against the worker's entry script
+///
+/// Falls back to the literal name when the resolved file isn't in the package.
+fn resolve_import_path(entry: &str, import: &str, source: &Extension) -> String {
+    use std::path::{Component, Path, PathBuf};
+    
+    let joined = match import.strip_prefix('/') {
+        Some(absolute) => PathBuf::from(absolute),
+        None => Path::new(entry).parent().unwrap_or(Path::new("")).join(import),
+    };
+    
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => resolved.push(part),
+            _ => {}
+        }
+    }
+    
+    if source.has_file(&resolved) {
+        resolved.to_string_lossy().replace('\\', "/")
+    } else {
+        import.to_string()
+    }
+}
+
 fn is_match_pattern(s: &str) -> bool {
     s.contains("://") || s.starts_with('<') || s.starts_with('*')
 }
 
+/// `scheme://host/*` for a match pattern (`<all_urls>` is kept)
+fn origin_match_pattern(pattern: &str) -> String {
+    match pattern.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split('/').next().unwrap_or(rest);
+            format!("{}://{}/*", scheme, host)
+        }
+        None => pattern.to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::PathBuf;
     
     #[test]
     fn test_add_firefox_settings() {
@@ -306,6 +965,7 @@ mod tests {
             browser_specific_settings: None,
             icons: None,
             commands: None,
+            omnibox: None,
             extra: Default::default(),
         };
         
@@ -320,6 +980,167 @@ mod tests {
         assert!(gecko.id.ends_with("@converted-extension.org"));
     }
     
+    #[test]
+    fn test_transform_report() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Report", "version": "1.0", "permissions": ["storage", "https://example.com/*"], "background": {"service_worker": "sw.js"}, "update_url": "https://clients2.google.com/service/update2/crx"}"#
+        ).unwrap();
+        
+        let (transformed, report) = ManifestTransformer::new(&[]).transform_with_report(&manifest, None).unwrap();
+        
+        assert!(transformed.browser_specific_settings.is_some());
+        assert!(report.contains(ManifestTransformKind::GeckoSettingsAdded));
+        assert!(report.contains(ManifestTransformKind::BackgroundConverted));
+        assert!(!report.contains(ManifestTransformKind::ActionRenamed));
+        let kinds: Vec<_> = report.changes.iter().map(|change| change.kind).collect();
+        assert_eq!(kinds, vec![
+            ManifestTransformKind::GeckoSettingsAdded,
+            ManifestTransformKind::BackgroundConverted,
+            ManifestTransformKind::PermissionsMoved,
+            ManifestTransformKind::FieldRemoved,
+        ]);
+        assert_eq!(report.changes[2].description, "Moved https://example.com/* from permissions to host_permissions");
+        assert_eq!(report.changes[3].field, "update_url");
+    }
+    
+    #[test]
+    fn test_transform_report_floors_and_injections() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Report", "version": "1.0", "key": "abc", "minimum_chrome_version": "88", "content_scripts": [{"matches": ["<all_urls>"], "js": ["c.js"]}]}"#
+        ).unwrap();
+        let mut files = std::collections::HashMap::new();
+        files.insert(std::path::PathBuf::from("sw.js"), b"chrome.action.openPopup();".to_vec());
+        let source = Extension::new(manifest.clone(), files);
+        
+        let (_, report) = ManifestTransformer::new(&[])
+            .with_shim_scripts(vec!["shims/browser-polyfill.js".to_string()])
+            .with_content_script_prelude(vec!["shims/browser-polyfill.js".to_string()])
+            .with_preserved_chrome_key(true)
+            .transform_with_report(&manifest, Some(&source))
+            .unwrap();
+        
+        let descriptions: Vec<&str> = report.changes.iter()
+            .filter(|change| matches!(change.kind,
+                ManifestTransformKind::KeyPreserved
+                    | ManifestTransformKind::VersionFloorRaised
+                    | ManifestTransformKind::ScriptsInjected))
+            .map(|change| change.description.as_str())
+            .collect();
+        // Chrome 88 maps to the default floor, so only openPopup raises it
+        assert_eq!(descriptions.len(), 3);
+        assert!(descriptions[0].starts_with("Kept 'key'"));
+        assert_eq!(descriptions[1], "Required Firefox 127+ (strict_min_version) for action.openPopup");
+        assert_eq!(descriptions[2], "Injected shims/browser-polyfill.js ahead of background and content scripts");
+    }
+    
+    #[test]
+    fn test_version_floor_recorded_only_when_raised() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Report", "version": "1.0", "minimum_chrome_version": "111"}"#
+        ).unwrap();
+        let mut files = std::collections::HashMap::new();
+        files.insert(std::path::PathBuf::from("sw.js"), b"chrome.action.openPopup();".to_vec());
+        let source = Extension::new(manifest.clone(), files);
+        
+        let (result, report) = ManifestTransformer::new(&[])
+            .with_target_version(Some(115))
+            .transform_with_report(&manifest, Some(&source))
+            .unwrap();
+        let gecko = result.browser_specific_settings.unwrap().gecko.unwrap();
+        assert_eq!(gecko.strict_min_version.as_deref(), Some("128.0"));
+        
+        // The Chrome 111 floor (128) already covers the target and openPopup
+        let floors: Vec<&str> = report.changes.iter()
+            .filter(|change| change.kind == ManifestTransformKind::VersionFloorRaised)
+            .map(|change| change.description.as_str())
+            .collect();
+        assert_eq!(floors.len(), 1);
+        assert!(floors[0].starts_with("Replaced minimum_chrome_version 111 with Firefox strict_min_version floor 128.0"));
+    }
+    
+    #[test]
+    fn test_page_action_becomes_action() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "page_action": {"default_popup": "popup.html", "default_title": "Page"}}"#
+        ).unwrap();
+        let (result, report) = ManifestTransformer::new(&[]).transform_with_report(&manifest, None).unwrap();
+        
+        let action = result.action.expect("page_action should become action");
+        assert_eq!(action.default_popup.as_deref(), Some("popup.html"));
+        assert_eq!(action.default_title.as_deref(), Some("Page"));
+        assert!(!result.extra.contains_key("page_action"));
+        assert!(report.changes.iter().any(|c| c.field == "page_action" && c.description == "Renamed page_action to action"));
+        
+        // An existing action wins
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "browser_action": {"default_popup": "main.html"}, "page_action": {"default_popup": "popup.html"}}"#
+        ).unwrap();
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        assert_eq!(result.action.unwrap().default_popup.as_deref(), Some("main.html"));
+        assert!(!result.extra.contains_key("page_action"));
+    }
+    
+    #[test]
+    fn test_normalize_version() {
+        let transformer = ManifestTransformer::new(&[]);
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.2.3.4"}"#
+        ).unwrap();
+        let result = transformer.transform(&manifest, None).unwrap();
+        assert_eq!(result.version, "1.2.3.4");
+        assert!(crate::utils::helpers::is_valid_firefox_version(&result.version));
+        assert!(!result.extra.contains_key("version_name"));
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.02.3.4.5"}"#
+        ).unwrap();
+        let result = transformer.transform(&manifest, None).unwrap();
+        assert_eq!(result.version, "1.2.3.4");
+        assert_eq!(result.extra["version_name"], "1.02.3.4.5");
+    }
+    
+    #[test]
+    fn test_scripting_permission_added_when_used() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["storage"]}"#
+        ).unwrap();
+        let mut files = std::collections::HashMap::new();
+        files.insert(
+            std::path::PathBuf::from("background.js"),
+            b"chrome.scripting.registerContentScripts([{ id: 'a', matches: ['<all_urls>'], js: ['a.js'] }]);".to_vec(),
+        );
+        let source = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, Some(&source)).unwrap();
+        assert_eq!(result.permissions, vec!["storage", "scripting"]);
+    }
+    
+    #[test]
+    fn test_minimum_chrome_version_floor() {
+        let transformer = ManifestTransformer::new(&[]);
+        let strict_min = |chrome_version: &str| {
+            let manifest = crate::parser::manifest::parse_manifest(format!(
+                r#"{{"manifest_version": 3, "name": "Test", "version": "1.0", "minimum_chrome_version": "{}"}}"#,
+                chrome_version
+            ).as_bytes()).unwrap();
+            let result = transformer.transform(&manifest, None).unwrap();
+            assert!(!result.extra.contains_key("minimum_chrome_version"));
+            result.browser_specific_settings.unwrap().gecko.unwrap().strict_min_version.unwrap()
+        };
+        
+        // Chrome 110 features are covered by the default floor
+        assert_eq!(strict_min("110"), DEFAULT_STRICT_MIN_VERSION);
+        // Chrome 111 added MAIN world scripting, which Firefox gained in 128
+        assert_eq!(strict_min("111"), "128.0");
+        
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "minimum_chrome_version": "110"}"#
+        ).unwrap();
+        let floor = ManifestTransformer::chrome_version_floor(&manifest).unwrap();
+        assert!(floor.reason.contains("Chrome 110"));
+    }
+    
     #[test]
     fn test_sanitize_extension_name() {
         // Test simple case
@@ -346,4 +1167,364 @@ mod tests {
             "test"
         );
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_symbol_only_name_gets_uuid_id() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            r#"{"manifest_version": 3, "name": "★ !!! ★", "version": "1.0"}"#.as_bytes()
+        ).unwrap();
+        
+        let id = ManifestTransformer::firefox_extension_id(&manifest);
+        assert!(id.starts_with('{') && id.ends_with('}'), "got {}", id);
+        assert!(crate::utils::helpers::is_valid_gecko_id(&id));
+        assert_eq!(id, ManifestTransformer::firefox_extension_id(&manifest));
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        let gecko = result.browser_specific_settings.unwrap().gecko.unwrap();
+        assert_eq!(gecko.id, id);
+        assert!(!crate::utils::helpers::is_valid_gecko_id("@converted-extension.org"));
+    }
+    
+    #[test]
+    fn test_theme_keys_mapped() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Dark", "version": "1.0",
+            "theme": {
+                "images": { "theme_frame": "images/frame.png", "theme_ntp_background": "images/ntp.png" },
+                "colors": { "frame": [30, 30, 30], "omnibox_background": [0, 0, 0], "ntp_link": [1, 2, 3], "popup": [40, 40, 40], "tab_line": [255, 0, 0] },
+                "tints": { "buttons": [0.5, 0.5, 0.5] }
+            }
+        }"#).unwrap();
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        let theme = &result.extra["theme"];
+        
+        assert_eq!(theme["images"], serde_json::json!({ "theme_frame": "images/frame.png" }));
+        assert_eq!(theme["colors"]["frame"], serde_json::json!([30, 30, 30]));
+        assert_eq!(theme["colors"]["toolbar_field"], serde_json::json!([0, 0, 0]));
+        assert!(theme["colors"].get("ntp_link").is_none());
+        // Firefox-native keys pass through
+        assert_eq!(theme["colors"]["popup"], serde_json::json!([40, 40, 40]));
+        assert_eq!(theme["colors"]["tab_line"], serde_json::json!([255, 0, 0]));
+        assert!(theme.get("tints").is_none());
+    }
+    
+    #[test]
+    fn test_transitive_import_scripts() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "background": {"service_worker": "bg/sw.js"}}"#
+        ).unwrap();
+        let files = [
+            ("bg/sw.js", "importScripts('lib/a.js');\nstart();\n"),
+            // Relative to bg/sw.js, not to bg/lib/
+            ("bg/lib/a.js", "importScripts('lib/b.js', '../shared.js');\n"),
+            ("bg/lib/b.js", "importScripts('lib/a.js');\nconst b = 1;\n"),
+            ("shared.js", "const shared = 1;\n"),
+        ];
+        let extension = Extension::new(
+            manifest.clone(),
+            files.iter().map(|(p, c)| (std::path::PathBuf::from(p), c.as_bytes().to_vec())).collect(),
+        );
+        
+        let result = ManifestTransformer::new(&[])
+            .with_shim_scripts(Vec::new())
+            .transform(&manifest, Some(&extension))
+            .unwrap();
+        
+        assert_eq!(
+            result.background.unwrap().scripts.unwrap(),
+            vec!["bg/lib/b.js", "shared.js", "bg/lib/a.js", "bg/sw.js"]
+        );
+    }
+    
+    #[test]
+    fn test_vendor_specific_fields_removed() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Edge Test", "version": "1.0",
+            "update_url": "https://edge.microsoft.com/extensionwebstorebase/v1/crx",
+            "storage": { "managed_schema": "schema.json" },
+            "minimum_opera_version": "80"
+        }"#).unwrap();
+        
+        let removed: Vec<_> = ManifestTransformer::new(&[]).vendor_specific_fields(&manifest).iter().map(|(f, _)| *f).collect();
+        assert_eq!(removed, vec!["update_url", "minimum_opera_version", "storage"]);
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        assert!(!result.extra.contains_key("update_url"));
+        assert!(!result.extra.contains_key("storage"));
+        assert!(!result.extra.contains_key("minimum_opera_version"));
+    }
+    
+    #[test]
+    fn test_externally_connectable_reported_as_unsupported() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Connect", "version": "1.0",
+            "externally_connectable": { "matches": ["https://app.example.com/*"] }
+        }"#).unwrap();
+        
+        let transformer = ManifestTransformer::new(&[]);
+        assert!(transformer.vendor_specific_fields(&manifest).is_empty());
+        
+        let (result, report) = transformer.transform_with_report(&manifest, None).unwrap();
+        assert!(!result.extra.contains_key("externally_connectable"));
+        let change = report.changes.iter().find(|c| c.field == "externally_connectable").unwrap();
+        assert_eq!(change.kind, ManifestTransformKind::UnsupportedFieldRemoved);
+        assert!(!change.description.contains("non-Firefox"));
+    }
+    
+    #[test]
+    fn test_key_kept_when_preserving_chrome_compatibility() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3, "name": "Keyed", "version": "1.0",
+            "key": "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA",
+            "update_url": "https://clients2.google.com/service/update2/crx"
+        }"#).unwrap();
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        assert!(!result.extra.contains_key("key"));
+        
+        let transformer = ManifestTransformer::new(&[]).with_preserved_chrome_key(true);
+        let result = transformer.transform(&manifest, None).unwrap();
+        assert!(result.extra.contains_key("key"));
+        assert!(!result.extra.contains_key("update_url"));
+        assert!(result.browser_specific_settings.and_then(|b| b.gecko).is_some_and(|g| !g.id.is_empty()));
+        assert_eq!(
+            transformer.vendor_specific_fields(&manifest).iter().map(|(f, _)| *f).collect::<Vec<_>>(),
+            vec!["update_url"]
+        );
+    }
+    
+    #[test]
+    fn test_data_collection_permissions() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        let gecko = result.browser_specific_settings.unwrap().gecko.unwrap();
+        assert!(gecko.data_collection_permissions.is_none());
+        
+        let result = ManifestTransformer::new(&[])
+            .with_data_collection_permissions(Some(DataCollectionPermissions::none()))
+            .transform(&manifest, None)
+            .unwrap();
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(
+            json["browser_specific_settings"]["gecko"]["data_collection_permissions"],
+            serde_json::json!({ "required": ["none"] })
+        );
+        assert_eq!(json["browser_specific_settings"]["gecko"]["strict_min_version"], "140.0");
+        
+        let (_, report) = ManifestTransformer::new(&[])
+            .with_data_collection_permissions(Some(DataCollectionPermissions::none()))
+            .transform_with_report(&manifest, None)
+            .unwrap();
+        assert!(report.changes.iter().any(|c| c.kind == ManifestTransformKind::VersionFloorRaised
+            && c.description.contains("data_collection_permissions")));
+        
+        assert!(DataCollectionPermissions::required(vec!["browsingActivity".to_string()]).is_ok());
+        let err = DataCollectionPermissions::required(vec!["browsingHistory".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("'browsingHistory'"));
+    }
+    
+    #[test]
+    fn test_polyfill_prepended_to_content_scripts_using_apis() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [
+                { "matches": ["https://example.com/*"], "js": ["lib/jquery.js", "content.js"] },
+                { "matches": ["https://example.com/*"], "js": ["highlight.js"] }
+            ]
+        }"#).unwrap();
+        let files = [
+            (PathBuf::from("lib/jquery.js"), b"window.$ = function() {};".to_vec()),
+            (PathBuf::from("content.js"), b"chrome.runtime.sendMessage({ type: 'ready' });".to_vec()),
+            (PathBuf::from("highlight.js"), b"document.body.classList.add('hl');".to_vec()),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[])
+            .with_content_script_prelude(vec!["shims/browser-polyfill.js".to_string()])
+            .transform(&manifest, Some(&extension))
+            .unwrap();
+        
+        assert_eq!(result.content_scripts[0].js, vec!["shims/browser-polyfill.js", "lib/jquery.js", "content.js"]);
+        assert_eq!(result.content_scripts[1].js, vec!["highlight.js"]);
+    }
+    
+    #[test]
+    fn test_css_only_content_script() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [
+                { "matches": ["https://example.com/*"], "css": ["style.css"] },
+                { "matches": ["https://example.com/*"], "js": ["content.js"] }
+            ]
+        }"#).unwrap();
+        
+        let result = ManifestTransformer::new(&[])
+            .with_content_script_prelude(vec!["shims/browser-polyfill.js".to_string()])
+            .transform(&manifest, None)
+            .unwrap();
+        
+        let css_only = &result.content_scripts[0];
+        assert_eq!(css_only.css, vec!["style.css"]);
+        assert!(css_only.js.is_empty());
+        assert!(!css_only.all_frames);
+        
+        let with_js = &result.content_scripts[1];
+        assert_eq!(with_js.js, vec!["shims/browser-polyfill.js", "content.js"]);
+        assert!(with_js.all_frames);
+    }
+    
+    #[test]
+    fn test_preserve_all_frames() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [{ "matches": ["https://example.com/*"], "js": ["content.js"] }]
+        }"#).unwrap();
+        
+        let widened = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        assert!(widened.content_scripts[0].all_frames);
+        
+        let preserved = ManifestTransformer::new(&[])
+            .with_preserved_all_frames(true)
+            .transform(&manifest, None)
+            .unwrap();
+        assert!(!preserved.content_scripts[0].all_frames);
+    }
+    
+    #[test]
+    fn test_dnr_redirect_target_made_web_accessible() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "declarative_net_request": {
+                "rule_resources": [{ "id": "rules", "enabled": true, "path": "rules.json" }]
+            }
+        }"#).unwrap();
+        let files = [(
+            PathBuf::from("rules.json"),
+            br#"[{"id": 1, "priority": 1, "action": {"type": "redirect", "redirect": {"extensionPath": "/blocked.html"}}, "condition": {"urlFilter": "||ads.example.com", "resourceTypes": ["main_frame"]}}]"#.to_vec(),
+        )].into_iter().collect();
+        let extension = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, Some(&extension)).unwrap();
+        
+        let Some(WebAccessibleResources::V3(entries)) = result.web_accessible_resources else {
+            panic!("expected V3 web_accessible_resources");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].resources, vec!["blocked.html"]);
+        assert_eq!(entries[0].matches.as_deref(), Some(&["<all_urls>".to_string()][..]));
+    }
+    
+    #[test]
+    fn test_hybrid_background_merged() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "background": {
+                "scripts": ["lib.js", "background.js"],
+                "service_worker": "sw.js"
+            }
+        }"#).unwrap();
+        let files = [
+            (PathBuf::from("sw.js"), b"importScripts('lib.js', 'background.js');".to_vec()),
+            (PathBuf::from("lib.js"), b"var lib = {};".to_vec()),
+            (PathBuf::from("background.js"), b"chrome.runtime.onInstalled.addListener(() => {});".to_vec()),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[])
+            .with_shim_scripts(vec!["shims/runtime-compat.js".to_string()])
+            .transform(&manifest, Some(&extension))
+            .unwrap();
+        let background = result.background.unwrap();
+        
+        assert_eq!(
+            background.scripts.unwrap(),
+            vec!["shims/runtime-compat.js", "lib.js", "background.js", "sw.js"]
+        );
+        assert!(background.service_worker.is_none());
+    }
+    
+    #[test]
+    fn test_web_accessible_additions() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [{ "matches": ["https://example.com/*"], "js": ["content.js"] }]
+        }"#).unwrap();
+        let files = [
+            (PathBuf::from("content.js"), b"img.src = chrome.runtime.getURL('inject.png');".to_vec()),
+            (PathBuf::from("inject.png"), Vec::new()),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[])
+            .with_web_accessible_additions(true)
+            .transform(&manifest, Some(&extension))
+            .unwrap();
+        
+        let Some(WebAccessibleResources::V3(entries)) = result.web_accessible_resources else {
+            panic!("expected V3 web_accessible_resources");
+        };
+        assert_eq!(entries[0].resources, vec!["inject.png"]);
+        assert_eq!(entries[0].matches.as_deref(), Some(&["https://example.com/*".to_string()][..]));
+    }
+    
+    #[test]
+    fn test_web_accessible_matches_drop_paths() {
+        let manifest = crate::parser::manifest::parse_manifest(br#"{
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_scripts": [
+                { "matches": ["https://example.com/app/*", "https://example.com/admin/*"], "js": ["content.js"] },
+                { "matches": ["<all_urls>"], "js": ["content.js"] }
+            ]
+        }"#).unwrap();
+        let files = [
+            (PathBuf::from("content.js"), b"img.src = chrome.runtime.getURL('inject.png');".to_vec()),
+            (PathBuf::from("inject.png"), Vec::new()),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest.clone(), files);
+        
+        let result = ManifestTransformer::new(&[])
+            .with_web_accessible_additions(true)
+            .transform(&manifest, Some(&extension))
+            .unwrap();
+        
+        let Some(WebAccessibleResources::V3(entries)) = result.web_accessible_resources else {
+            panic!("expected V3 web_accessible_resources");
+        };
+        assert_eq!(
+            entries[0].matches.as_deref(),
+            Some(&["<all_urls>".to_string(), "https://example.com/*".to_string()][..])
+        );
+    }
+    
+    #[test]
+    fn test_unknown_fields_keep_source_order() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Order", "version": "1.0", "zz_custom": {"b": 1}, "update_url": "https://clients2.google.com/service/update2/crx", "aa_custom": true}"#
+        ).unwrap();
+        
+        let result = ManifestTransformer::new(&[]).transform(&manifest, None).unwrap();
+        let keys: Vec<_> = result.extra.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["zz_custom", "aa_custom"]);
+        
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.find("\"zz_custom\"").unwrap() < json.find("\"aa_custom\"").unwrap());
+    }
+}
@@ -8,6 +8,8 @@ pub mod tab_groups;
 pub mod offscreen_converter;
 pub mod declarative_content_converter;
 pub mod chrome_only_converter;
+pub mod background_split;
+pub mod capabilities;
 
 pub use manifest::ManifestTransformer;
 pub use javascript::JavaScriptTransformer;
@@ -17,70 +19,217 @@ pub use offscreen_converter::OffscreenConverter;
 pub use declarative_content_converter::DeclarativeContentConverter;
 pub use chrome_only_converter::ChromeOnlyApiConverter;
 
-use crate::models::{ConversionContext, ConversionResult};
+use crate::models::{ChangeKind, ConversionContext, ConversionResult, ReportIssue, ReportWarning};
 use anyhow::Result;
 
 /// Main transformation entry point (simplified pass-through)
 pub fn transform_extension(context: ConversionContext) -> Result<ConversionResult> {
     let mut manifest_changes = Vec::new();
     let mut javascript_changes = Vec::new();
-    let mut chrome_api_count = 0;
-    let mut callback_count = 0;
     
-    // 1. Transform manifest (pass source for importScripts detection)
-    let manifest_transformer = ManifestTransformer::new(&context.selected_decisions);
-    let transformed_manifest = manifest_transformer.transform(&context.source.manifest, Some(&context.source))?;
+    let only_manifest = context.options.only_manifest;
+    let is_theme = context.source.manifest.is_theme();
+    
+    // 1. Generate compatibility shims (skipped in only-manifest mode and for themes)
+    let generated_shims = if only_manifest || is_theme {
+        Vec::new()
+    } else {
+        generate_shims(&context)?
+    };
+    let shims_dir = context.options.shims_dir.as_str();
+    let shim_path = |path: &str| shims::relocate_shim_path(std::path::Path::new(path), shims_dir)
+        .to_string_lossy()
+        .replace('\\', "/");
+    let bundle_shims = context.options.bundle_shims && !generated_shims.is_empty();
+    let polyfill_path = shim_path(shims::BROWSER_POLYFILL_PATH);
+    let mut shims = if bundle_shims {
+        vec![shims::bundle_shims(&generated_shims, shims_dir)]
+    } else {
+        generated_shims.clone()
+    };
+    
+    // 2. Transform manifest (pass source for importScripts detection)
+    // The background only references the shims that are actually shipped
+    let shim_scripts = shims.iter()
+        .map(|shim| shim.path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    let content_script_prelude = if !only_manifest
+        && context.options.effective_namespace_strategy() == crate::NamespaceStrategy::PolyfillOnly
+    {
+        vec![polyfill_path.clone()]
+    } else {
+        Vec::new()
+    };
+    // The bundle carries background-only shims, so content scripts keep the
+    // standalone polyfill
+    if bundle_shims {
+        let polyfill = generated_shims.iter()
+            .find(|shim| shim.path.to_string_lossy().replace('\\', "/") == polyfill_path);
+        shims.extend(polyfill.cloned());
+    }
+    let manifest_transformer = ManifestTransformer::new(&context.selected_decisions)
+        .with_shim_scripts(shim_scripts)
+        .with_content_script_prelude(content_script_prelude)
+        .with_data_collection_permissions(context.options.data_collection_permissions.clone())
+        .with_web_accessible_additions(context.options.add_web_accessible_resources)
+        .with_preserved_all_frames(context.options.preserve_all_frames)
+        .with_preserved_chrome_key(context.options.preserve_chrome_key)
+        .with_target_version(context.options.target_firefox_version);
+    let (mut transformed_manifest, manifest_report) = manifest_transformer
+        .transform_with_report(&context.source.manifest, Some(&context.source))?;
     
     // Track manifest changes
-    if context.source.manifest.browser_specific_settings.is_none() {
-        manifest_changes.push("Added browser_specific_settings.gecko.id for Firefox".to_string());
+    manifest_changes.extend(manifest_report.changes.into_iter().map(|change| change.description));
+    if bundle_shims {
+        manifest_changes.push(format!(
+            "Bundled {} shims into {}",
+            generated_shims.len(),
+            shim_path(shims::COMPAT_BUNDLE_PATH)
+        ));
     }
-    if context.source.manifest.background.as_ref().and_then(|b| b.service_worker.as_ref()).is_some() {
-        manifest_changes.push("Added background.scripts for Firefox event page compatibility".to_string());
+    
+    // 3. Transform JavaScript files (left untouched in only-manifest mode)
+    let background_scripts = transformed_manifest.background.as_ref()
+        .and_then(|b| b.scripts.as_ref())
+        .map(|scripts| scripts.iter().map(std::path::PathBuf::from).collect())
+        .unwrap_or_default();
+    let js_transformer = JavaScriptTransformer::new(&context.selected_decisions)
+        .with_background_scripts(background_scripts);
+    let mut js_files = if only_manifest {
+        javascript_changes.push("JavaScript left untouched (only-manifest mode)".to_string());
+        Vec::new()
+    } else if is_theme {
+        manifest_changes.push("Theme extension: mapped theme keys, skipped JavaScript and shims".to_string());
+        Vec::new()
+    } else {
+        context.source.get_javascript_files()
+    };
+    
+    js_files.sort();
+    
+    let mut modified_files = transform_javascript_files(&js_transformer, &context.source, &js_files, context.options.max_threads)?;
+    for transformed in &modified_files {
+        javascript_changes.push(format!(
+            "{}: {} changes",
+            transformed.path.display(),
+            transformed.changes.len()
+        ));
     }
     
-    // 2. Transform JavaScript files
-    let mut js_transformer = JavaScriptTransformer::new(&context.selected_decisions);
-    let mut modified_files = Vec::new();
-    
-    for js_path in context.source.get_javascript_files() {
-        if let Some(content) = context.source.get_file_content(&js_path) {
-            if let Ok(transformed) = js_transformer.transform(&content, &js_path) {
-                if transformed.new_content != content {
-                    // Count changes
-                    chrome_api_count += transformed.changes.iter()
-                        .filter(|c| c.description.contains("chrome.*"))
-                        .count();
-                    callback_count += transformed.changes.iter()
-                        .filter(|c| c.description.contains("Callback"))
-                        .count();
-                    
-                    javascript_changes.push(format!(
-                        "{}: {} changes",
-                        js_path.display(),
-                        transformed.changes.len()
-                    ));
-                    
-                    modified_files.push(transformed);
+    // 4. Optionally split oversized background scripts into several files
+    let mut new_files = shims;
+    let mut split_warnings = Vec::new();
+    if let Some(threshold) = context.options.split_background_threshold.filter(|_| !only_manifest) {
+        let scripts = transformed_manifest.background.as_ref()
+            .and_then(|b| b.scripts.clone())
+            .unwrap_or_default();
+        let mut split_scripts = Vec::new();
+        
+        for script in scripts {
+            let path = std::path::PathBuf::from(&script);
+            let existing = modified_files.iter().position(|f: &crate::models::ModifiedFile| f.path == path);
+            let content = match existing {
+                Some(index) => Some(modified_files[index].new_content.clone()),
+                None => context.source.get_file_content(&path),
+            };
+            let split = content.as_deref()
+                .and_then(|c| background_split::split_background_script(&path, c, threshold));
+            
+            let Some(split) = split else {
+                split_scripts.push(script);
+                continue;
+            };
+            
+            javascript_changes.push(format!(
+                "{}: split into {} additional file(s)",
+                script,
+                split.parts.len()
+            ));
+            split_warnings.push(ReportWarning::new(
+                format!(
+                    "{} was split into {} files; verify the background behaves identically in Firefox",
+                    script,
+                    split.parts.len() + 1
+                ),
+                Some(script.clone()),
+            ));
+            split_scripts.extend(split.parts.iter().map(|p| p.path.to_string_lossy().replace('\\', "/")));
+            split_scripts.push(script);
+            
+            match existing {
+                Some(index) => {
+                    modified_files[index].new_content = split.main_content;
+                    modified_files[index].changes.extend(split.changes);
                 }
+                None => modified_files.push(crate::models::ModifiedFile {
+                    path: path.clone(),
+                    original_content: content.unwrap_or_default(),
+                    new_content: split.main_content,
+                    changes: split.changes,
+                }),
+            }
+            new_files.extend(split.parts);
+        }
+        
+        if let Some(background) = transformed_manifest.background.as_mut() {
+            if background.scripts.is_some() {
+                background.scripts = Some(split_scripts);
             }
         }
     }
     
-    // 3. Generate compatibility shims
-    let shims = generate_shims(&context)?;
+    // 5. Convert chrome.offscreen documents when a match pattern prompt is set
+    let mut offscreen_actions = Vec::new();
+    if let Some(prompt) = context.options.offscreen_url_prompt.filter(|_| !only_manifest && !is_theme) {
+        let converter = ChromeOnlyApiConverter::new(std::path::PathBuf::new()).with_url_prompt(prompt);
+        match converter.convert_offscreen_apis(&context) {
+            Ok(results) => {
+                let offscreen = ChromeOnlyApiConverter::merge_results(results);
+                for new_file in offscreen.new_files {
+                    let exists = context.source.files.contains_key(&new_file.path)
+                        || new_files.iter().any(|f| f.path == new_file.path);
+                    if exists {
+                        offscreen_actions.push(format!("{} already exists and was not replaced", new_file.path.display()));
+                    } else {
+                        new_files.push(new_file);
+                    }
+                }
+                for change in &offscreen.manifest_changes {
+                    if let Some(description) = apply_chrome_only_manifest_change(&mut transformed_manifest, change) {
+                        manifest_changes.push(description);
+                    }
+                }
+                // The calling code isn't rewritten; surface the suggested replacements
+                for modified in &offscreen.modified_files {
+                    for change in &modified.changes {
+                        offscreen_actions.push(format!(
+                            "{}:{}: {}",
+                            modified.path.display(),
+                            change.line_number,
+                            change.description
+                        ));
+                    }
+                }
+                offscreen_actions.extend(offscreen.instructions);
+            }
+            Err(e) => offscreen_actions.push(format!("Offscreen document conversion skipped: {}", e)),
+        }
+    }
     
-    // 4. Build report
+    // 6. Build report
+    let changes_by_kind = crate::models::ReportSummary::count_changes_by_kind(&modified_files);
+    let count = |kind| changes_by_kind.get(&kind).copied().unwrap_or(0);
     let report = crate::models::ConversionReport {
         summary: crate::models::ReportSummary {
             extension_name: context.source.metadata.name.clone(),
             extension_version: context.source.metadata.version.clone(),
             conversion_successful: !context.has_blockers(),
             files_modified: modified_files.len(),
-            files_added: shims.len(),
+            files_added: new_files.len(),
             total_changes: modified_files.iter().map(|f| f.changes.len()).sum(),
-            chrome_api_calls_converted: chrome_api_count,
-            callback_to_promise_conversions: callback_count,
+            chrome_api_calls_converted: count(ChangeKind::NamespaceRewrite) + count(ChangeKind::ApiRemap),
+            callback_to_promise_conversions: count(ChangeKind::CallbackToPromise),
+            changes_by_kind,
         },
         manifest_changes,
         javascript_changes,
@@ -91,21 +240,160 @@ pub fn transform_extension(context: ConversionContext) -> Result<ConversionResul
         manual_actions: context.incompatibilities.iter()
             .filter(|i| !i.auto_fixable && matches!(i.severity, crate::models::Severity::Major))
             .map(|i| format!("{}: {}", i.location, i.description))
+            .chain(offscreen_actions)
             .collect(),
         warnings: context.warnings.iter()
-            .map(|w| w.message.clone())
+            .map(ReportWarning::from)
+            .chain(split_warnings)
+            .collect(),
+        issues_by_file: context.issues_by_file().into_iter()
+            .map(|(path, issues)| {
+                (path.to_string_lossy().replace('\\', "/"), issues.into_iter().map(ReportIssue::from).collect())
+            })
             .collect(),
+        permission_suggestions: crate::analyzer::permissions::suggest_permission_narrowing(&context.source),
     };
     
+    let provenance = build_provenance(&context, &report, &new_files);
+    
     Ok(ConversionResult {
         source: context.source,
         manifest: transformed_manifest,
         modified_files,
-        new_files: shims,
+        new_files,
         report,
+        provenance: Some(provenance),
+        exclude_patterns: context.options.exclude_patterns,
+        package_provenance: context.options.package_provenance,
+        shims_dir: context.options.shims_dir,
+        fail_on_error: None,
     })
 }
 
+/// Apply a Chrome-only converter's manifest change, describing it for the report
+///
+/// Returns `None` when the manifest already has what the change asks for.
+fn apply_chrome_only_manifest_change(
+    manifest: &mut crate::models::Manifest,
+    change: &crate::models::chrome_only::ManifestChange,
+) -> Option<String> {
+    use crate::models::chrome_only::ManifestChange;
+    
+    match change {
+        ManifestChange::AddContentScript { matches, exclude_matches, js, run_at } => {
+            if manifest.content_scripts.iter().any(|script| script.js == *js) {
+                return None;
+            }
+            manifest.content_scripts.push(crate::models::manifest::ContentScript {
+                matches: matches.clone(),
+                js: js.clone(),
+                css: Vec::new(),
+                exclude_matches: exclude_matches.clone(),
+                include_globs: Vec::new(),
+                exclude_globs: Vec::new(),
+                run_at: Some(run_at.clone()),
+                all_frames: false,
+                match_about_blank: None,
+            });
+            Some(format!("Added content script {} matching {}", js.join(", "), matches.join(", ")))
+        }
+        ManifestChange::AddPermission(permission) => {
+            if manifest.permissions.contains(permission) {
+                return None;
+            }
+            manifest.permissions.push(permission.clone());
+            Some(format!("Added permission '{}'", permission))
+        }
+        ManifestChange::AddBackgroundScript(script) => {
+            let scripts = manifest.background.as_mut()?.scripts.get_or_insert_with(Vec::new);
+            if scripts.contains(script) {
+                return None;
+            }
+            scripts.push(script.clone());
+            Some(format!("Added background script {}", script))
+        }
+        ManifestChange::RemovePermission(permission) => {
+            let before = manifest.permissions.len();
+            manifest.permissions.retain(|p| p != permission);
+            (manifest.permissions.len() != before).then(|| format!("Removed permission '{}'", permission))
+        }
+    }
+}
+
+/// Transform each file, keeping those that changed in `files` order
+///
+/// Files are transformed on a rayon pool of `max_threads` threads (default:
+/// available parallelism); one thread takes the serial path. Results are
+/// identical either way.
+fn transform_javascript_files(
+    transformer: &JavaScriptTransformer,
+    source: &crate::models::Extension,
+    files: &[std::path::PathBuf],
+    max_threads: Option<usize>,
+) -> Result<Vec<crate::models::ModifiedFile>> {
+    let transform_one = |path: &std::path::PathBuf| {
+        let content = source.get_file_content(path)?;
+        transformer.transform(&content, path).ok()
+            .filter(|transformed| transformed.new_content != content)
+    };
+    
+    #[cfg(not(target_arch = "wasm32"))]
+    if max_threads != Some(1) {
+        use rayon::prelude::*;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(max_threads.unwrap_or(0))
+            .build()?;
+        return Ok(pool.install(|| files.par_iter().filter_map(transform_one).collect()));
+    }
+    
+    #[cfg(target_arch = "wasm32")]
+    let _ = max_threads;
+    Ok(files.iter().filter_map(transform_one).collect())
+}
+
+fn build_provenance(
+    context: &ConversionContext,
+    report: &crate::models::ConversionReport,
+    new_files: &[crate::models::NewFile],
+) -> crate::models::Provenance {
+    let mut paths: Vec<_> = context.source.files.keys().collect();
+    paths.sort();
+    // Deferred archive entries contribute their CRC-32 and size rather than their content
+    let deferred: Vec<(&std::path::PathBuf, [u8; 12])> = context.source.deferred.entries.iter()
+        .map(|(path, entry)| {
+            let mut digest = [0u8; 12];
+            digest[..4].copy_from_slice(&entry.crc32.to_le_bytes());
+            digest[4..].copy_from_slice(&entry.size.to_le_bytes());
+            (path, digest)
+        })
+        .collect();
+    let input_hash = crate::utils::helpers::fnv1a_hex(
+        paths.iter()
+            .flat_map(|path| [path.as_os_str().as_encoded_bytes(), context.source.files[*path].as_slice()])
+            .chain(deferred.iter().flat_map(|(path, digest)| [path.as_os_str().as_encoded_bytes(), digest.as_slice()]))
+    );
+    
+    let decisions = context.selected_decisions.iter()
+        .map(|selected| {
+            let label = context.decisions.iter()
+                .find(|d| d.id == selected.decision_id)
+                .and_then(|d| d.options.get(selected.selected_index))
+                .map(|o| o.label.clone())
+                .unwrap_or_else(|| format!("option {}", selected.selected_index));
+            format!("{}: {}", selected.decision_id, label)
+        })
+        .collect();
+    
+    crate::models::Provenance {
+        tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        input_hash,
+        decisions,
+        transforms: report.manifest_changes.iter().chain(&report.javascript_changes).cloned().collect(),
+        shims: new_files.iter().map(|f| f.path.to_string_lossy().replace('\\', "/")).collect(),
+    }
+}
+
 impl Default for crate::models::ConversionReport {
     fn default() -> Self {
         Self {
@@ -118,12 +406,15 @@ impl Default for crate::models::ConversionReport {
                 total_changes: 0,
                 chrome_api_calls_converted: 0,
                 callback_to_promise_conversions: 0,
+                changes_by_kind: Default::default(),
             },
             manifest_changes: Vec::new(),
             javascript_changes: Vec::new(),
             blockers: Vec::new(),
             manual_actions: Vec::new(),
             warnings: Vec::new(),
+            issues_by_file: Default::default(),
+            permission_suggestions: Vec::new(),
         }
     }
 }
\ No newline at end of file
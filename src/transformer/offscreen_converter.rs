@@ -1,14 +1,15 @@
 //! Converter for chrome.offscreen API to Firefox alternatives
 
 use crate::models::chrome_only::*;
-use crate::models::conversion::{NewFile, ModifiedFile, FileChange, ChangeType};
+use crate::models::conversion::{NewFile, ModifiedFile, FileChange, ChangeType, ChangeKind};
 use crate::analyzer::OffscreenAnalyzer;
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use std::path::PathBuf;
 
 pub struct OffscreenConverter {
     _analyzer: OffscreenAnalyzer,
     preferences: ConversionPreferences,
+    url_prompt: Option<UrlPrompt>,
 }
 
 impl OffscreenConverter {
@@ -16,9 +17,16 @@ impl OffscreenConverter {
         Self {
             _analyzer: OffscreenAnalyzer::new(source_dir),
             preferences,
+            url_prompt: None,
         }
     }
 
+    /// Ask for content script match patterns when they can't be inferred
+    pub fn with_url_prompt(mut self, prompt: UrlPrompt) -> Self {
+        self.url_prompt = Some(prompt);
+        self
+    }
+
     /// Determine the conversion strategy based on document analysis
     pub fn determine_strategy(
         &self,
@@ -124,6 +132,7 @@ self.addEventListener('message', async (event) => {
             changes: vec![FileChange {
                 line_number: usage.call_location.line,
                 change_type: ChangeType::Modification,
+                kind: ChangeKind::ApiRemap,
                 description: "Converted offscreen canvas to Web Worker".to_string(),
                 old_code: Some("chrome.offscreen.createDocument(...)".to_string()),
                 new_code: Some(
@@ -206,6 +215,7 @@ self.addEventListener('message', async (event) => {
             changes: vec![FileChange {
                 line_number: usage.call_location.line,
                 change_type: ChangeType::Modification,
+                kind: ChangeKind::ApiRemap,
                 description: "Converted offscreen audio to Web Worker".to_string(),
                 old_code: Some("chrome.offscreen.createDocument(...)".to_string()),
                 new_code: Some(
@@ -270,6 +280,7 @@ browser.runtime.onMessage.addListener((message, sender, sendResponse) => {
             changes: vec![FileChange {
                 line_number: usage.call_location.line,
                 change_type: ChangeType::Modification,
+                kind: ChangeKind::ApiRemap,
                 description: "Converted offscreen network ops to background script".to_string(),
                 old_code: Some("chrome.offscreen.createDocument(...)".to_string()),
                 new_code: Some(
@@ -312,7 +323,7 @@ browser.runtime.sendMessage({
         analysis: &DocumentAnalysis,
         usage: &OffscreenUsage,
     ) -> Result<ChromeOnlyConversionResult> {
-        let target_urls = self.extract_or_prompt_for_urls(analysis)?;
+        let (target_urls, fallback_warning) = self.extract_or_prompt_for_urls(analysis);
 
         let content_script = r#"// Auto-generated Content Script
 // Converted from chrome.offscreen document for DOM parsing
@@ -344,6 +355,7 @@ browser.runtime.sendMessage({
             changes: vec![FileChange {
                 line_number: usage.call_location.line,
                 change_type: ChangeType::Modification,
+                kind: ChangeKind::ApiRemap,
                 description: "Converted offscreen DOM parsing to content script".to_string(),
                 old_code: Some("chrome.offscreen.createDocument(...)".to_string()),
                 new_code: Some(
@@ -370,9 +382,13 @@ browser.runtime.onMessage.addListener((message, sender) => {
                 "DOM parsing moved to content script".to_string(),
                 format!("Content script will run on: {:?}", target_urls),
                 "Add content script to manifest.json".to_string(),
-            ],
+            ]
+            .into_iter()
+            .chain(fallback_warning)
+            .collect(),
             manifest_changes: vec![ManifestChange::AddContentScript {
                 matches: target_urls,
+                exclude_matches: Vec::new(),
                 js: vec!["content-scripts/dom-parser.js".to_string()],
                 run_at: "document_idle".to_string(),
             }],
@@ -410,15 +426,26 @@ browser.runtime.onMessage.addListener((message, sender) => {
         ]
     }
 
-    fn extract_or_prompt_for_urls(&self, analysis: &DocumentAnalysis) -> Result<Vec<String>> {
+    /// Inferred or user-supplied match patterns, else `<all_urls>` with a warning
+    fn extract_or_prompt_for_urls(&self, analysis: &DocumentAnalysis) -> (Vec<String>, Option<String>) {
         let urls = self.extract_target_urls(analysis);
         if !urls.is_empty() {
-            Ok(urls)
-        } else if self.preferences.prompt_for_urls {
-            Err(anyhow!("Interactive: Need URL patterns for content script"))
-        } else {
-            Ok(vec!["<all_urls>".to_string()])
+            return (urls, None);
+        }
+        
+        if self.preferences.prompt_for_urls {
+            if let Some(prompt) = self.url_prompt {
+                let answered = prompt(&self.suggest_urls(analysis)).unwrap_or_default();
+                if !answered.is_empty() {
+                    return (answered, None);
+                }
+            }
         }
+        
+        (
+            vec!["<all_urls>".to_string()],
+            Some("Warning: could not infer target pages for the DOM parsing content script; it runs on <all_urls>. Narrow the matches in manifest.json".to_string()),
+        )
     }
 
     fn strategy_for_purpose(
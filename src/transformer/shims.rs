@@ -4,43 +4,195 @@
 //! for APIs that don't exist in Firefox or have significant behavioral differences.
 
 use crate::models::{ConversionContext, NewFile};
+use crate::transformer::capabilities::Feature;
+use crate::NamespaceStrategy;
 use anyhow::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Directory the shim paths below live in, unless `ConversionOptions::shims_dir` overrides it
+pub const DEFAULT_SHIMS_DIR: &str = "shims";
+
+/// Move a shim path from [`DEFAULT_SHIMS_DIR`] into `shims_dir`
+pub fn relocate_shim_path(path: &Path, shims_dir: &str) -> PathBuf {
+    match path.strip_prefix(DEFAULT_SHIMS_DIR) {
+        Ok(rest) => Path::new(shims_dir).join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// (API pattern, API name, shim constructor)
+type ShimRule = (&'static str, &'static str, fn() -> NewFile);
+
+/// Shims shipped with every conversion
+///
+/// Each one has runtime guards and self-activates, which is simpler than
+/// parsing JS files to detect usage. The pattern only explains the choice.
+/// NOTE: No importScripts polyfill needed - we extract and add scripts to manifest instead!
+const ALWAYS_SHIMS: &[ShimRule] = &[
+    (r"\b(?:chrome|browser)\.storage\.session\b", "storage.session", create_storage_session_compat),
+    (r"\b(?:chrome|browser)\.scripting\.executeScript\b", "scripting.executeScript", create_execute_script_compat),
+    (r"\b(?:chrome|browser)\.sidePanel\.", "sidePanel", create_sidepanel_compat),
+    (r"\b(?:chrome|browser)\.declarativeNetRequest\.", "declarativeNetRequest", create_declarative_net_request_stub),
+    (r"\b(?:chrome|browser)\.userScripts\.", "userScripts", create_user_scripts_compat),
+    (r"\b(?:chrome|browser)\.(?:tabs|windows)\.", "tabs/windows", create_tabs_windows_compat),
+    (r"\b(?:chrome|browser)\.runtime\.", "runtime", create_runtime_compat),
+    (r"\b(?:chrome|browser)\.downloads\.", "downloads", create_downloads_compat),
+    (r"\b(?:chrome|browser)\.privacy\.", "privacy", create_privacy_stub),
+    (r"\b(?:chrome|browser)\.notifications\.", "notifications", create_notifications_compat),
+];
+
+/// Shims shipped only when the code uses their API
+const USAGE_GATED_SHIMS: &[ShimRule] = &[
+    (r"\b(?:chrome|browser)\.browserAction\.(?:setPopup|getPopup)\s*\(", "browserAction.setPopup/getPopup", create_action_compat),
+    (r"\b(?:chrome|browser)\.proxy\.settings\.set\s*\(", "proxy.settings.set", create_proxy_settings_compat),
+    (r"\b(?:chrome|browser)\.storage\.(?:local|sync)\.getBytesInUse\s*\(", "storage.getBytesInUse", create_storage_bytes_in_use_compat),
+    (r"\b(?:chrome|browser)\.gcm\.", "gcm", create_gcm_stub),
+    (r"\b(?:chrome|browser)\.system\.", "system", create_system_stub),
+    (r"\b(?:chrome|browser)\.contextMenus\.onClicked\b", "contextMenus.onClicked", create_context_menus_compat),
+    (r"\b(?:chrome|browser)\.tts\.", "tts", create_tts_compat),
+    (r"\b(?:chrome|browser)\.cookies\.", "cookies", create_cookies_compat),
+];
+
+/// Shims made redundant by a native feature of the targeted Firefox: (API label, feature)
+const NATIVE_FEATURES: &[(&str, Feature)] = &[
+    ("declarativeNetRequest", Feature::NativeDnr),
+    ("storage.session", Feature::StorageSession),
+];
+
+/// A shim `generate_shims` will ship and why
+#[derive(Debug, Clone)]
+pub struct PlannedShim {
+    pub file: NewFile,
+    pub reason: String,
+}
+
+/// Decide which shims an extension gets, with the reason for each
+pub fn plan_shims(context: &ConversionContext) -> Vec<PlannedShim> {
+    let mut planned = Vec::new();
+    
+    // The namespace polyfill must load before everything else
+    if context.options.effective_namespace_strategy() == NamespaceStrategy::PolyfillOnly {
+        planned.push(PlannedShim {
+            file: create_browser_polyfill(),
+            reason: "polyfill-only namespace strategy (Chrome compatibility preserved)".to_string(),
+        });
+    }
+    
+    for (pattern, api, create) in ALWAYS_SHIMS {
+        if NATIVE_FEATURES.iter().any(|(native, feature)| native == api && context.supports(*feature)) {
+            continue;
+        }
+        let used = context.source.javascript_matches(&regex::Regex::new(pattern).unwrap());
+        planned.push(PlannedShim {
+            file: create(),
+            reason: if used {
+                format!("uses {}", api)
+            } else {
+                format!("always included (self-activating guard for {})", api)
+            },
+        });
+    }
+    
+    for (pattern, api, create) in USAGE_GATED_SHIMS {
+        if context.source.javascript_matches(&regex::Regex::new(pattern).unwrap()) {
+            planned.push(PlannedShim { file: create(), reason: format!("uses {}", api) });
+        }
+    }
+    
+    planned.sort_by_key(|shim| shim_load_order(&shim.file.path));
+    for shim in &mut planned {
+        shim.file.path = relocate_shim_path(&shim.file.path, &context.options.shims_dir);
+    }
+    planned
+}
+
+/// Shims that load ahead of the API shims, in this order
+const LOAD_FIRST: &[&str] = &[BROWSER_POLYFILL_PATH];
+
+/// Sort key for shim load order: [`LOAD_FIRST`] entries first, then the rest by file name
+///
+/// The manifest lists shims in this order ahead of the background scripts.
+pub fn shim_load_order(path: &Path) -> (usize, String) {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let priority = LOAD_FIRST.iter()
+        .position(|first| Path::new(first).file_name().is_some_and(|n| n.to_string_lossy() == name))
+        .unwrap_or(LOAD_FIRST.len());
+    (priority, name)
+}
 
 /// Generate compatibility shims for cross-browser support
 ///
-/// **Simplified approach**: Always include ALL shims with runtime guards.
-/// Each shim checks if it's needed and self-activates. This eliminates
-/// the need for JS parsing and is more maintainable.
+/// **Simplified approach**: Always include the core shims with runtime guards,
+/// plus the usage-gated ones whose API the code calls (see [`plan_shims`]).
 ///
 /// Shims make extensions work in BOTH Chrome and Firefox:
 /// - Runtime interception for API differences
 /// - Polyfills for missing APIs
 /// - Cross-browser compatibility layer
-pub fn generate_shims(_context: &ConversionContext) -> Result<Vec<NewFile>> {
-    let mut shims = Vec::new();
-    
-    // Always include all shims - they have runtime guards and self-activate
-    // This is simpler than parsing JS files to detect usage
-    // NOTE: No importScripts polyfill needed - we extract and add scripts to manifest instead!
-    shims.push(create_storage_session_compat());
-    shims.push(create_execute_script_compat());  // NEW: Runtime interceptor
-    shims.push(create_sidepanel_compat());
-    shims.push(create_declarative_net_request_stub());
-    shims.push(create_user_scripts_compat());
-    shims.push(create_tabs_windows_compat());
-    shims.push(create_runtime_compat());
-    shims.push(create_downloads_compat());
-    shims.push(create_privacy_stub());
-    shims.push(create_notifications_compat());
-    
-    Ok(shims)
+pub fn generate_shims(context: &ConversionContext) -> Result<Vec<NewFile>> {
+    Ok(plan_shims(context).into_iter().map(|shim| shim.file).collect())
 }
 
-// NOTE: We removed browser-polyfill.js, promise-wrapper.js, action-compat.js, and import-scripts-polyfill.js
-// because:
+/// Path of the single file `ConversionOptions::bundle_shims` concatenates the shims into
+pub const COMPAT_BUNDLE_PATH: &str = "shims/compat-bundle.js";
+
+/// Concatenate `shims` in load order into one file with a section comment per shim
+///
+/// Every shim is a self-contained IIFE, so concatenation keeps their scopes apart.
+pub fn bundle_shims(shims: &[NewFile], shims_dir: &str) -> NewFile {
+    let mut content = String::from("// Compatibility shim bundle\n// Generated by chrome2moz from the shims below, in load order\n");
+    for shim in shims {
+        content.push_str(&format!(
+            "\n// ==== {} ====\n// {}\n\n",
+            shim.path.to_string_lossy().replace('\\', "/"),
+            shim.purpose
+        ));
+        content.push_str(shim.content.trim_end());
+        content.push('\n');
+    }
+    
+    NewFile {
+        path: relocate_shim_path(Path::new(COMPAT_BUNDLE_PATH), shims_dir),
+        content,
+        purpose: format!("Bundles {} compatibility shims into one script", shims.len()),
+    }
+}
+
+// NOTE: We removed promise-wrapper.js and import-scripts-polyfill.js because:
 // - Firefox natively supports chrome.* namespace and handles promises automatically
 // - importScripts() is handled by extracting calls and adding scripts directly to manifest (SAFE!)
+// action-compat.js is only generated when popups are swapped with browserAction.setPopup/getPopup;
+// browser-polyfill.js only for NamespaceStrategy::PolyfillOnly.
+
+/// Path of the namespace polyfill generated for `NamespaceStrategy::PolyfillOnly`
+pub const BROWSER_POLYFILL_PATH: &str = "shims/browser-polyfill.js";
+
+fn create_browser_polyfill() -> NewFile {
+    let content = r#"// Browser namespace polyfill
+// Makes both chrome.* and browser.* available so identical code runs in Chrome and Firefox
+// chrome.* calls are left untouched - this only aliases the missing namespace
+
+(function() {
+  'use strict';
+  
+  const root = typeof globalThis !== 'undefined' ? globalThis : self;
+  
+  if (typeof root.browser === 'undefined' && typeof root.chrome !== 'undefined') {
+    root.browser = root.chrome;
+  }
+  
+  if (typeof root.chrome === 'undefined' && typeof root.browser !== 'undefined') {
+    root.chrome = root.browser;
+  }
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(BROWSER_POLYFILL_PATH),
+        content: content.to_string(),
+        purpose: "Aliases chrome.* and browser.* namespaces (polyfill-only mode)".to_string(),
+    }
+}
 
 fn create_storage_session_compat() -> NewFile {
     let content = r#"// Storage session compatibility shim
@@ -120,8 +272,17 @@ fn create_storage_session_compat() -> NewFile {
     },
     
     getBytesInUse: async function(keys) {
-      console.warn('⚠️ storage.session.getBytesInUse is not implemented in polyfill');
-      return 0;
+      // Same estimate as Chrome: UTF-8 length of each key plus its JSON value
+      const selected = keys === null || keys === undefined
+        ? Array.from(sessionStore.keys())
+        : (Array.isArray(keys) ? keys : [keys]);
+      const encoder = new TextEncoder();
+      return selected.reduce((total, key) => {
+        if (!sessionStore.has(key)) {
+          return total;
+        }
+        return total + encoder.encode(key + JSON.stringify(sessionStore.get(key))).length;
+      }, 0);
     },
     
     getKeys: async function() {
@@ -937,17 +1098,33 @@ fn create_tabs_windows_compat() -> NewFile {
   
   if (api && api.windows && api.windows.create) {
     // Wrap windows.create to handle focused parameter
+    // Firefox honors `focused` from version 86; older versions reject it
     const originalCreate = api.windows.create;
+    let focusedSupported = null;
+    const supportsFocused = async function() {
+      if (focusedSupported === null) {
+        focusedSupported = true;
+        if (api.runtime && api.runtime.getBrowserInfo) {
+          try {
+            const info = await api.runtime.getBrowserInfo();
+            focusedSupported = parseInt(info.version, 10) >= 86;
+          } catch (e) {
+            // Keep assuming support
+          }
+        }
+      }
+      return focusedSupported;
+    };
+    
     api.windows.create = async function(createData, callback) {
-      console.info('⚙️ windows.create: handling focused parameter');
-      
-      // Firefox supports focused parameter differently
       const data = { ...createData };
-      if (data.focused !== undefined) {
-        // Convert to state parameter for Firefox
+      if (data.focused !== undefined && !(await supportsFocused())) {
+        console.info('⚙️ windows.create: focused is unsupported, falling back to state');
+        // Never override an explicitly provided state
         if (data.focused === false && !data.state) {
           data.state = 'minimized';
         }
+        delete data.focused;
       }
       
       try {
@@ -1047,6 +1224,24 @@ fn create_downloads_compat() -> NewFile {
       };
     }
     
+    // downloads.onDeterminingFilename stub (event never fires in Firefox)
+    if (!api.downloads.onDeterminingFilename) {
+      const listeners = new Set();
+      api.downloads.onDeterminingFilename = {
+        addListener: function(callback) {
+          console.warn('⚠️ downloads.onDeterminingFilename is not supported in Firefox - listener will never fire');
+          console.info('💡 Pass the desired filename in downloads.download({ url, filename }) instead');
+          listeners.add(callback);
+        },
+        removeListener: function(callback) {
+          listeners.delete(callback);
+        },
+        hasListener: function(callback) {
+          return listeners.has(callback);
+        }
+      };
+    }
+    
     // Wrap downloads.download to filter unsupported options
     const originalDownload = api.downloads.download;
     api.downloads.download = async function(options) {
@@ -1255,6 +1450,16 @@ fn create_notifications_compat() -> NewFile {
       return await originalCreate.call(this, notificationId, adaptedOptions);
     };
     
+    // Firefox has no per-extension permission level; notifications are always allowed
+    if (!api.notifications.getPermissionLevel) {
+      api.notifications.getPermissionLevel = async function(callback) {
+        console.warn('⚠️ notifications.getPermissionLevel is not supported in Firefox, reporting granted');
+        const level = 'granted';
+        if (callback) callback(level);
+        return level;
+      };
+    }
+    
     console.info('✅ Notifications API compatibility loaded (cross-browser)');
   }
 })();
@@ -1267,15 +1472,779 @@ fn create_notifications_compat() -> NewFile {
     }
 }
 
+/// Path of the browserAction alias loaded when popups are swapped at runtime
+pub const ACTION_COMPAT_PATH: &str = "shims/action-compat.js";
+
+fn create_action_compat() -> NewFile {
+    let content = r#"// browserAction compatibility shim
+// Manifest V3 has no browserAction; direct calls are rewritten to action, and
+// this aliases the namespace for references the rewrite can't see (e.g.
+// const ba = chrome['browserAction']; ba.setPopup({ popup }))
+
+(function() {
+  'use strict';
+  
+  for (const api of [typeof browser !== 'undefined' ? browser : null, typeof chrome !== 'undefined' ? chrome : null]) {
+    if (api && api.action && !api.browserAction) {
+      api.browserAction = api.action;
+    }
+  }
+  
+  console.info('✅ browserAction compatibility loaded (aliased to action)');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(ACTION_COMPAT_PATH),
+        content: content.to_string(),
+        purpose: "Aliases browserAction to action for dynamic popup swapping".to_string(),
+    }
+}
+
+/// Path of the storage.local/sync getBytesInUse wrapper
+pub const STORAGE_BYTES_IN_USE_PATH: &str = "shims/storage-bytes-in-use-compat.js";
+
+fn create_storage_bytes_in_use_compat() -> NewFile {
+    let content = r#"// storage getBytesInUse compatibility shim
+// Firefox's storage.local has no (or an inexact) getBytesInUse and chrome.*
+// callers may pass a callback; this always returns a promise, invokes the
+// callback when given, and falls back to Chrome's estimate when needed.
+// Firefox's chrome and browser namespaces are separate objects, so the
+// storage areas are wrapped on both.
+
+(function() {
+  'use strict';
+  
+  const namespaces = [
+    typeof browser !== 'undefined' ? browser : null,
+    typeof chrome !== 'undefined' ? chrome : null
+  ].filter((api, index, all) => api && api.storage && all.indexOf(api) === index);
+  if (namespaces.length === 0) {
+    return;
+  }
+  
+  // Chrome's estimate: UTF-8 length of each key plus its JSON value
+  function estimate(items) {
+    const encoder = new TextEncoder();
+    return Object.keys(items).reduce((total, key) => {
+      return total + encoder.encode(key + JSON.stringify(items[key])).length;
+    }, 0);
+  }
+  
+  namespaces.forEach((api) => {
+    ['local', 'sync'].forEach((areaName) => {
+      const area = api.storage[areaName];
+      if (!area || area.__getBytesInUseWrapped) {
+        return;
+      }
+      
+      const native = typeof area.getBytesInUse === 'function' ? area.getBytesInUse.bind(area) : null;
+      
+      area.getBytesInUse = function(keys, callback) {
+        if (typeof keys === 'function') {
+          callback = keys;
+          keys = null;
+        }
+      
+        const promise = (async () => {
+          if (native) {
+            try {
+              return await native(keys === undefined ? null : keys);
+            } catch (error) {
+              // Fall through to the estimate
+            }
+          }
+          return estimate(await area.get(keys === undefined ? null : keys));
+        })();
+      
+        if (typeof callback === 'function') {
+          promise.then(callback, (error) => {
+            console.warn('⚠️ storage.' + areaName + '.getBytesInUse failed:', error.message);
+            callback(0);
+          });
+        }
+        return promise;
+      };
+      area.__getBytesInUseWrapped = true;
+    });
+  });
+  
+  console.info('✅ storage getBytesInUse compatibility loaded');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(STORAGE_BYTES_IN_USE_PATH),
+        content: content.to_string(),
+        purpose: "Promisifies storage.local/sync.getBytesInUse and estimates usage where Firefox lacks it".to_string(),
+    }
+}
+
+/// Path of the stub that makes chrome.gcm fail loudly
+pub const GCM_STUB_PATH: &str = "shims/gcm-stub.js";
+
+fn create_gcm_stub() -> NewFile {
+    let content = r#"// chrome.gcm stub
+// Firefox has no gcm API; calls throw a descriptive error instead of
+// failing with "chrome.gcm is undefined". Migrate to the W3C Push API.
+
+(function() {
+  'use strict';
+  
+  const api = typeof browser !== 'undefined' ? browser : chrome;
+  if (!api || api.gcm) {
+    return;
+  }
+  
+  const message = 'chrome.gcm is not supported in Firefox - use the Push API (registration.pushManager) instead';
+  const unsupported = function() {
+    throw new Error(message);
+  };
+  const event = {
+    addListener() { console.warn('⚠️ ' + message); },
+    removeListener() {},
+    hasListener() { return false; }
+  };
+  
+  const gcm = {
+    MAX_MESSAGE_SIZE: 4096,
+    register: unsupported,
+    unregister: unsupported,
+    send: unsupported,
+    onMessage: event,
+    onMessagesDeleted: event,
+    onSendError: event
+  };
+  
+  api.gcm = gcm;
+  if (typeof chrome !== 'undefined' && chrome !== api && !chrome.gcm) {
+    chrome.gcm = gcm;
+  }
+  
+  console.info('✅ chrome.gcm stub loaded (push messaging unavailable)');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(GCM_STUB_PATH),
+        content: content.to_string(),
+        purpose: "Makes chrome.gcm calls throw a clear error (no gcm API in Firefox)".to_string(),
+    }
+}
+
+/// Path of the stub that makes chrome.system.* calls reject clearly
+pub const SYSTEM_STUB_PATH: &str = "shims/system-stub.js";
+
+fn create_system_stub() -> NewFile {
+    let content = r#"// chrome.system stub
+// Firefox has no system.cpu/memory/storage/display APIs; calls reject (or
+// report through the callback) with a descriptive error instead of failing
+// with "undefined is not a function".
+
+(function() {
+  'use strict';
+  
+  const api = typeof browser !== 'undefined' ? browser : chrome;
+  if (!api || api.system) {
+    return;
+  }
+  
+  function unsupported(name) {
+    const message = `chrome.system.${name} is not supported in Firefox`;
+    return function(...args) {
+      const callback = typeof args[args.length - 1] === 'function' ? args.pop() : null;
+      console.warn('⚠️ ' + message);
+      if (callback) {
+        callback(undefined);
+        return undefined;
+      }
+      return Promise.reject(new Error(message));
+    };
+  }
+  const event = {
+    addListener() {},
+    removeListener() {},
+    hasListener() { return false; }
+  };
+  
+  const system = {
+    cpu: { getInfo: unsupported('cpu.getInfo') },
+    memory: { getInfo: unsupported('memory.getInfo') },
+    storage: {
+      getInfo: unsupported('storage.getInfo'),
+      ejectDevice: unsupported('storage.ejectDevice'),
+      getAvailableCapacity: unsupported('storage.getAvailableCapacity'),
+      onAttached: event,
+      onDetached: event
+    },
+    display: {
+      getInfo: unsupported('display.getInfo'),
+      getDisplayLayout: unsupported('display.getDisplayLayout'),
+      onDisplayChanged: event
+    }
+  };
+  
+  api.system = system;
+  if (typeof chrome !== 'undefined' && chrome !== api && !chrome.system) {
+    chrome.system = system;
+  }
+  
+  console.info('✅ chrome.system stub loaded (system information unavailable)');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(SYSTEM_STUB_PATH),
+        content: content.to_string(),
+        purpose: "Makes chrome.system.* calls reject with a clear error (no system API in Firefox)".to_string(),
+    }
+}
+
+/// Path of the shim routing contextMenus.onClicked through menus.onClicked
+pub const CONTEXT_MENUS_COMPAT_PATH: &str = "shims/context-menus-compat.js";
+
+fn create_context_menus_compat() -> NewFile {
+    let content = r#"// contextMenus.onClicked compatibility shim
+// Routes contextMenus.onClicked listeners through Firefox's menus.onClicked and
+// hands them Chrome's OnClickData shape: Firefox-only fields (button,
+// modifiers, targetElementId, viewType, linkText, bookmarkId) are dropped and
+// editable is always a boolean.
+
+(function() {
+  'use strict';
+  
+  if (typeof browser === 'undefined' || !browser.menus || !browser.menus.onClicked) {
+    return;
+  }
+  
+  const FIREFOX_ONLY_FIELDS = ['button', 'modifiers', 'targetElementId', 'viewType', 'linkText', 'bookmarkId'];
+  
+  function normalizeInfo(info) {
+    const normalized = Object.assign({}, info);
+    for (const field of FIREFOX_ONLY_FIELDS) {
+      delete normalized[field];
+    }
+    normalized.editable = Boolean(info.editable);
+    return normalized;
+  }
+  
+  const wrappers = new Map();
+  const onClicked = {
+    addListener(listener) {
+      if (wrappers.has(listener)) {
+        return;
+      }
+      const wrapper = (info, tab) => listener(normalizeInfo(info), tab);
+      wrappers.set(listener, wrapper);
+      browser.menus.onClicked.addListener(wrapper);
+    },
+    removeListener(listener) {
+      const wrapper = wrappers.get(listener);
+      if (wrapper) {
+        browser.menus.onClicked.removeListener(wrapper);
+        wrappers.delete(listener);
+      }
+    },
+    hasListener(listener) {
+      return wrappers.has(listener);
+    }
+  };
+  
+  for (const api of [typeof chrome !== 'undefined' ? chrome : null, browser]) {
+    if (api && api.contextMenus) {
+      try {
+        Object.defineProperty(api.contextMenus, 'onClicked', { value: onClicked, configurable: true });
+      } catch (e) {
+        console.warn('⚠️ Could not wrap contextMenus.onClicked:', e);
+      }
+    }
+  }
+  
+  console.info('✅ contextMenus.onClicked compatibility shim loaded');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(CONTEXT_MENUS_COMPAT_PATH),
+        content: content.to_string(),
+        purpose: "Routes contextMenus.onClicked through menus.onClicked with Chrome's OnClickData shape".to_string(),
+    }
+}
+
+pub const TTS_COMPAT_PATH: &str = "shims/tts-compat.js";
+
+fn create_tts_compat() -> NewFile {
+    let content = r#"// tts compatibility shim
+// Firefox has no tts API; speak/stop/pause/resume/isSpeaking/getVoices are
+// mapped onto the Web Speech API (speechSynthesis). Options without a Web
+// Speech equivalent (gender, extensionId, required/desiredEventTypes) are
+// ignored with a warning.
+
+(function() {
+  'use strict';
+  
+  if (typeof browser === 'undefined' || browser.tts) {
+    return;
+  }
+  if (typeof speechSynthesis === 'undefined' || typeof SpeechSynthesisUtterance === 'undefined') {
+    console.warn('⚠️ tts shim: speechSynthesis is not available in this context');
+    return;
+  }
+  
+  const UNSUPPORTED_OPTIONS = ['gender', 'extensionId', 'requiredEventTypes', 'desiredEventTypes'];
+  
+  function findVoice(options) {
+    if (!options.voiceName) {
+      return null;
+    }
+    return speechSynthesis.getVoices().find((voice) => voice.name === options.voiceName) || null;
+  }
+  
+  function speak(utterance, options, callback) {
+    if (typeof options === 'function') {
+      callback = options;
+      options = {};
+    }
+    options = options || {};
+    for (const option of UNSUPPORTED_OPTIONS) {
+      if (option in options) {
+        console.warn(`⚠️ tts.speak: option '${option}' is not supported in Firefox and was ignored`);
+      }
+    }
+    
+    const speech = new SpeechSynthesisUtterance(utterance);
+    if (options.lang) speech.lang = options.lang;
+    if (typeof options.rate === 'number') speech.rate = options.rate;
+    if (typeof options.pitch === 'number') speech.pitch = options.pitch;
+    if (typeof options.volume === 'number') speech.volume = options.volume;
+    const voice = findVoice(options);
+    if (voice) speech.voice = voice;
+    
+    if (typeof options.onEvent === 'function') {
+      const emit = (type, event) => options.onEvent({ type, charIndex: event && event.charIndex });
+      speech.onstart = (event) => emit('start', event);
+      speech.onend = (event) => emit('end', event);
+      speech.onpause = (event) => emit('pause', event);
+      speech.onresume = (event) => emit('resume', event);
+      speech.onboundary = (event) => emit(event.name === 'sentence' ? 'sentence' : 'word', event);
+      speech.onerror = (event) => {
+        const type = event.error === 'interrupted' ? 'interrupted' : event.error === 'canceled' ? 'cancelled' : 'error';
+        options.onEvent({ type, charIndex: event.charIndex, errorMessage: event.error });
+      };
+    }
+    
+    if (!options.enqueue) {
+      speechSynthesis.cancel();
+    }
+    speechSynthesis.speak(speech);
+    
+    if (typeof callback === 'function') {
+      callback();
+      return undefined;
+    }
+    return Promise.resolve();
+  }
+  
+  function withResult(value, callback) {
+    if (typeof callback === 'function') {
+      callback(value);
+      return undefined;
+    }
+    return Promise.resolve(value);
+  }
+  
+  const tts = {
+    speak,
+    stop() {
+      speechSynthesis.cancel();
+    },
+    pause() {
+      speechSynthesis.pause();
+    },
+    resume() {
+      speechSynthesis.resume();
+    },
+    isSpeaking(callback) {
+      return withResult(speechSynthesis.speaking, callback);
+    },
+    getVoices(callback) {
+      const voices = speechSynthesis.getVoices().map((voice) => ({
+        voiceName: voice.name,
+        lang: voice.lang,
+        remote: !voice.localService,
+        eventTypes: ['start', 'end', 'word', 'sentence', 'interrupted', 'cancelled', 'error', 'pause', 'resume']
+      }));
+      return withResult(voices, callback);
+    }
+  };
+  
+  browser.tts = tts;
+  if (typeof chrome !== 'undefined' && !chrome.tts) {
+    chrome.tts = tts;
+  }
+  
+  console.info('✅ tts compatibility shim loaded (speechSynthesis)');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(TTS_COMPAT_PATH),
+        content: content.to_string(),
+        purpose: "Implements tts.speak/stop/getVoices on top of the Web Speech speechSynthesis API".to_string(),
+    }
+}
+
+pub const COOKIES_COMPAT_PATH: &str = "shims/cookies-compat.js";
+
+fn create_cookies_compat() -> NewFile {
+    let content = r#"// cookies compatibility shim
+// Adapts Chrome cookie details to Firefox:
+// - partitionKey keeps only topLevelSite; hasCrossSiteAncestor has no Firefox
+//   equivalent and is dropped with a warning
+// - when first-party isolation is on, Firefox rejects calls without
+//   firstPartyDomain, so they are retried with one derived from partitionKey
+// - sameSite 'unspecified' is omitted from set() so Firefox applies its default
+
+(function() {
+  'use strict';
+  
+  if (typeof browser === 'undefined' || !browser.cookies) {
+    return;
+  }
+  
+  const METHODS = ['get', 'getAll', 'set', 'remove'];
+  const original = {};
+  for (const method of METHODS) {
+    original[method] = browser.cookies[method].bind(browser.cookies);
+  }
+  
+  function siteDomain(site) {
+    try {
+      return new URL(site).hostname;
+    } catch (e) {
+      return '';
+    }
+  }
+  
+  function adaptDetails(method, details) {
+    const adapted = Object.assign({}, details);
+    if (adapted.partitionKey) {
+      const { topLevelSite, ...unmapped } = adapted.partitionKey;
+      for (const field of Object.keys(unmapped)) {
+        console.warn(`⚠️ cookies.${method}: partitionKey.${field} is not supported in Firefox and was ignored`);
+      }
+      adapted.partitionKey = topLevelSite !== undefined ? { topLevelSite } : {};
+    }
+    if (method === 'set' && adapted.sameSite === 'unspecified') {
+      delete adapted.sameSite;
+    }
+    return adapted;
+  }
+  
+  function withFirstPartyDomain(method, details) {
+    if (details.firstPartyDomain !== undefined) {
+      return null;
+    }
+    const retried = Object.assign({}, details);
+    const topLevelSite = details.partitionKey && details.partitionKey.topLevelSite;
+    if (topLevelSite) {
+      retried.firstPartyDomain = siteDomain(topLevelSite);
+    } else if (method === 'getAll') {
+      // null matches cookies from every first-party domain
+      retried.firstPartyDomain = null;
+    } else if (details.url) {
+      retried.firstPartyDomain = siteDomain(details.url);
+    } else {
+      return null;
+    }
+    return retried;
+  }
+  
+  async function call(method, details) {
+    const adapted = adaptDetails(method, details || {});
+    try {
+      return await original[method](adapted);
+    } catch (error) {
+      const retried = /firstPartyDomain/.test(String(error && error.message)) && withFirstPartyDomain(method, adapted);
+      if (!retried) {
+        throw error;
+      }
+      return original[method](retried);
+    }
+  }
+  
+  for (const api of [typeof chrome !== 'undefined' ? chrome : null, browser]) {
+    if (!api || !api.cookies) {
+      continue;
+    }
+    for (const method of METHODS) {
+      api.cookies[method] = function(details, callback) {
+        const result = call(method, details);
+        if (typeof callback !== 'function') {
+          return result;
+        }
+        result.then(callback, (error) => {
+          console.error(`cookies.${method} failed:`, error);
+          callback(undefined);
+        });
+      };
+    }
+  }
+  
+  console.info('✅ cookies compatibility shim loaded');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from(COOKIES_COMPAT_PATH),
+        content: content.to_string(),
+        purpose: "Maps cookies partitionKey/sameSite details to Firefox and retries with firstPartyDomain under first-party isolation".to_string(),
+    }
+}
+
+fn create_proxy_settings_compat() -> NewFile {
+    let content = r#"// proxy.settings compatibility shim
+// Translates Chrome's ProxyConfig ({ mode, rules, pacScript }) to Firefox's
+// proxy settings shape ({ proxyType, http, ssl, socks, passthrough, autoConfigUrl })
+
+(function() {
+  'use strict';
+  
+  const api = typeof browser !== 'undefined' ? browser : chrome;
+  if (!api || !api.proxy || !api.proxy.settings || !api.proxy.settings.set) {
+    return;
+  }
+  
+  function hostPort(server) {
+    return server.port ? server.host + ':' + server.port : server.host;
+  }
+  
+  function translate(config) {
+    switch (config.mode) {
+      case 'direct':
+        return { proxyType: 'none' };
+      case 'system':
+        return { proxyType: 'system' };
+      case 'auto_detect':
+        return { proxyType: 'autoDetect' };
+      case 'pac_script': {
+        const pac = config.pacScript || {};
+        const url = pac.url || 'data:application/x-ns-proxy-autoconfig,' + encodeURIComponent(pac.data || '');
+        return { proxyType: 'autoConfig', autoConfigUrl: url };
+      }
+      case 'fixed_servers': {
+        const rules = config.rules || {};
+        const result = { proxyType: 'manual' };
+        const single = rules.singleProxy;
+        
+        if (single) {
+          const scheme = single.scheme || 'http';
+          if (scheme === 'socks4' || scheme === 'socks5') {
+            result.socks = hostPort(single);
+            result.socksVersion = scheme === 'socks4' ? 4 : 5;
+          } else {
+            result.http = hostPort(single);
+            result.httpProxyAll = true;
+          }
+        }
+        if (rules.proxyForHttp) {
+          result.http = hostPort(rules.proxyForHttp);
+        }
+        if (rules.proxyForHttps) {
+          result.ssl = hostPort(rules.proxyForHttps);
+        }
+        if (rules.bypassList) {
+          result.passthrough = rules.bypassList.join(', ');
+        }
+        return result;
+      }
+      default:
+        console.warn('⚠️ proxy.settings: unsupported Chrome proxy mode "' + config.mode + '"');
+        return config;
+    }
+  }
+  
+  const originalSet = api.proxy.settings.set.bind(api.proxy.settings);
+  api.proxy.settings.set = function(details, callback) {
+    const value = details && details.value;
+    // Only Chrome-shaped configs carry a `mode`; Firefox-shaped ones pass through
+    const translated = value && value.mode
+      ? { ...details, value: translate(value) }
+      : details;
+    
+    const promise = originalSet(translated);
+    if (typeof callback === 'function') {
+      promise.then(() => callback(), (error) => {
+        console.error('proxy.settings.set failed:', error);
+        callback();
+      });
+    }
+    return promise;
+  };
+  
+  console.info('✅ proxy.settings compatibility loaded');
+})();
+"#;
+    
+    NewFile {
+        path: PathBuf::from("shims/proxy-settings-compat.js"),
+        content: content.to_string(),
+        purpose: "Translates Chrome ProxyConfig objects to Firefox proxy settings".to_string(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::path::Path;
+    
+    #[test]
+    fn test_polyfill_only_generated_for_polyfill_strategy() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        let mut context = ConversionContext::new(crate::models::Extension::new(manifest, Default::default()));
+        
+        // Firefox has `browser` natively, so default conversions don't ship the polyfill
+        let shims = generate_shims(&context).unwrap();
+        assert!(shims.iter().all(|s| s.path != Path::new(BROWSER_POLYFILL_PATH)));
+        
+        context.options.namespace_strategy = NamespaceStrategy::PolyfillOnly;
+        let shims = generate_shims(&context).unwrap();
+        assert_eq!(shims[0].path, PathBuf::from(BROWSER_POLYFILL_PATH));
+        
+        context.options.namespace_strategy = NamespaceStrategy::Native;
+        context.options.preserve_chrome_compatibility = true;
+        let shims = generate_shims(&context).unwrap();
+        assert_eq!(shims[0].path, PathBuf::from(BROWSER_POLYFILL_PATH));
+    }
+    
+    #[test]
+    fn test_plan_shims_reports_reasons() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["declarativeNetRequest"]}"#
+        ).unwrap();
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.declarativeNetRequest.updateDynamicRules({ addRules: [] });".to_vec(),
+        )].into_iter().collect();
+        let context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        
+        let planned = plan_shims(&context);
+        let dnr = planned.iter()
+            .find(|shim| shim.file.path == Path::new("shims/declarative-net-request-stub.js"))
+            .expect("DNR shim not planned");
+        assert_eq!(dnr.reason, "uses declarativeNetRequest");
+        assert!(!dnr.file.purpose.is_empty());
+        
+        let privacy = planned.iter().find(|shim| shim.file.path == Path::new("shims/privacy-stub.js")).unwrap();
+        assert!(privacy.reason.starts_with("always included"));
+        assert_eq!(
+            planned.into_iter().map(|shim| shim.file).collect::<Vec<_>>(),
+            generate_shims(&context).unwrap()
+        );
+    }
+    
+    #[test]
+    fn test_native_dnr_target_skips_dnr_shim() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["declarativeNetRequest"]}"#
+        ).unwrap();
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.declarativeNetRequest.updateDynamicRules({ addRules: [] });".to_vec(),
+        )].into_iter().collect();
+        let mut context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        let dnr_path = Path::new("shims/declarative-net-request-stub.js");
+        
+        // No target: nothing is assumed native
+        assert!(plan_shims(&context).iter().any(|shim| shim.file.path == dnr_path));
+        
+        context.options.target_firefox_version = Some(112);
+        assert!(!context.supports(Feature::NativeDnr));
+        assert!(plan_shims(&context).iter().any(|shim| shim.file.path == dnr_path));
+        
+        context.options.target_firefox_version = Some(128);
+        assert!(context.supports(Feature::NativeDnr));
+        assert!(!plan_shims(&context).iter().any(|shim| shim.file.path == dnr_path));
+    }
+    
+    #[test]
+    fn test_shim_order_is_deterministic() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.tts.speak('hi'); chrome.cookies.getAll({}); chrome.action.openPopup();".to_vec(),
+        )].into_iter().collect();
+        let mut context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        context.options.preserve_chrome_compatibility = true;
+        
+        let paths = |shims: Vec<NewFile>| shims.into_iter().map(|s| s.path).collect::<Vec<_>>();
+        let first = paths(generate_shims(&context).unwrap());
+        assert_eq!(first, paths(generate_shims(&context).unwrap()));
+        assert_eq!(first[0], PathBuf::from(BROWSER_POLYFILL_PATH));
+        
+        let rest = &first[1..];
+        assert!(rest.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(rest.contains(&PathBuf::from(TTS_COMPAT_PATH)));
+    }
     
     #[test]
     fn test_storage_session_shim_generation() {
         let shim = create_storage_session_compat();
         assert!(shim.content.contains("sessionStore"));
         assert_eq!(shim.path, PathBuf::from("shims/storage-session-compat.js"));
+        assert!(shim.content.contains("encoder.encode(key + JSON.stringify(sessionStore.get(key))).length"));
+        assert!(!shim.content.contains("getBytesInUse is not implemented"));
+    }
+    
+    #[test]
+    fn test_storage_bytes_in_use_shim_gated_on_usage() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+        ).unwrap();
+        let context = ConversionContext::new(crate::models::Extension::new(manifest.clone(), Default::default()));
+        let shims = generate_shims(&context).unwrap();
+        assert!(shims.iter().all(|s| s.path != Path::new(STORAGE_BYTES_IN_USE_PATH)));
+        
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.storage.local.getBytesInUse(null, (bytes) => console.log(bytes));".to_vec(),
+        )].into_iter().collect();
+        let context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        let shim = generate_shims(&context).unwrap()
+            .into_iter()
+            .find(|s| s.path == Path::new(STORAGE_BYTES_IN_USE_PATH))
+            .expect("getBytesInUse shim should be generated");
+        assert!(shim.content.contains("area.getBytesInUse = function(keys, callback)"));
+        assert!(shim.content.contains("return promise;"));
+    }
+    
+    #[test]
+    fn test_storage_bytes_in_use_shim_wraps_chrome_namespace() {
+        let shim = create_storage_bytes_in_use_compat();
+        assert!(shim.content.contains("typeof chrome !== 'undefined' ? chrome : null"));
+        
+        // In Firefox chrome.storage and browser.storage are distinct objects;
+        // run the shim against both when node is available
+        let script = format!(
+            "const area = () => ({{ get: async () => ({{ a: 1 }}) }});\n\
+             globalThis.browser = {{ storage: {{ local: area() }} }};\n\
+             globalThis.chrome = {{ storage: {{ local: area() }} }};\n\
+             {}\n\
+             chrome.storage.local.getBytesInUse(null, (bytes) => console.log('chrome=' + bytes));\n\
+             browser.storage.local.getBytesInUse().then((bytes) => console.log('browser=' + bytes));\n",
+            shim.content
+        );
+        let Ok(output) = std::process::Command::new("node").arg("-e").arg(&script).output() else {
+            println!("⚠️  node not installed, skipping shim execution");
+            return;
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("chrome=2"), "{}", stdout);
+        assert!(stdout.contains("browser=2"), "{}", stdout);
     }
     
     #[test]
@@ -1298,4 +2267,42 @@ mod tests {
         assert!(shim.content.contains("executeScript"));
         assert!(shim.content.contains("cross-browser"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_context_menus_shim_wires_on_clicked() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["contextMenus"]}"#
+        ).unwrap();
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.contextMenus.onClicked.addListener((info, tab) => console.log(info.menuItemId, tab.id));".to_vec(),
+        )].into_iter().collect();
+        let context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        
+        let shim = generate_shims(&context).unwrap()
+            .into_iter()
+            .find(|s| s.path == Path::new(CONTEXT_MENUS_COMPAT_PATH))
+            .expect("contextMenus shim should be generated");
+        assert!(shim.content.contains("browser.menus.onClicked.addListener(wrapper)"));
+        assert!(shim.content.contains("normalizeInfo(info)"));
+    }
+    
+    #[test]
+    fn test_cookies_shim_maps_first_party_domain() {
+        let manifest = crate::parser::manifest::parse_manifest(
+            br#"{"manifest_version": 3, "name": "Test", "version": "1.0", "permissions": ["cookies"]}"#
+        ).unwrap();
+        let files = [(
+            PathBuf::from("background.js"),
+            b"chrome.cookies.get({ url, name: 'sid', partitionKey: { topLevelSite: 'https://example.com' } });".to_vec(),
+        )].into_iter().collect();
+        let context = ConversionContext::new(crate::models::Extension::new(manifest, files));
+        
+        let shim = generate_shims(&context).unwrap()
+            .into_iter()
+            .find(|s| s.path == Path::new(COOKIES_COMPAT_PATH))
+            .expect("cookies shim should be generated");
+        assert!(shim.content.contains("retried.firstPartyDomain = siteDomain(topLevelSite)"));
+        assert!(shim.content.contains("partitionKey.${field} is not supported in Firefox"));
+    }
+}
@@ -5,6 +5,42 @@ pub fn is_match_pattern(s: &str) -> bool {
     s.contains("://") || s.starts_with('<') || s.starts_with('*')
 }
 
+/// Check an add-on ID against the pattern Firefox accepts
+///
+/// Either an email-style `name@domain` (ASCII letters, digits, `-`, `.`, `_`;
+/// both parts non-empty) or a braced UUID.
+pub fn is_valid_gecko_id(id: &str) -> bool {
+    let id_re = regex::Regex::new(
+        r"^(?i:\{[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\}|[a-z0-9\-._]+@[a-z0-9\-._]+)$"
+    ).unwrap();
+    id.len() <= 80 && id_re.is_match(id)
+}
+
+/// Check that a match pattern is well-formed for Firefox
+///
+/// Accepts `<all_urls>` and `scheme://host/path` where the scheme is `*` or a
+/// supported scheme, the host is `*`, `*.domain` or a plain domain (`file`
+/// URLs have no host), and the path starts with `/`.
+pub fn is_valid_match_pattern(pattern: &str) -> bool {
+    if pattern == "<all_urls>" {
+        return true;
+    }
+    
+    let Some((scheme, rest)) = pattern.split_once("://") else { return false };
+    if !matches!(scheme, "*" | "http" | "https" | "ws" | "wss" | "ftp" | "file") {
+        return false;
+    }
+    
+    let Some(slash) = rest.find('/') else { return false };
+    let host = &rest[..slash];
+    if scheme == "file" {
+        return host.is_empty();
+    }
+    
+    let domain = host.strip_prefix("*.").unwrap_or(host);
+    host == "*" || (!domain.is_empty() && !domain.contains('*'))
+}
+
 /// Sanitize extension name for use in filenames
 pub fn sanitize_name(name: &str) -> String {
     name.to_lowercase()
@@ -19,6 +55,104 @@ pub fn generate_extension_id(name: &str) -> String {
     format!("{}@converted.extension", sanitize_name(name))
 }
 
+/// Check if a version string is accepted by Firefox
+///
+/// Firefox expects 1-4 dot-separated integers, each at most 9 digits and
+/// without leading zeros (e.g. `1.2.3.4`, but not `1.02` or `1.0-beta`).
+pub fn is_valid_firefox_version(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('.').collect();
+    (1..=4).contains(&parts.len()) && parts.iter().all(|part| {
+        !part.is_empty()
+            && part.len() <= 9
+            && part.chars().all(|c| c.is_ascii_digit())
+            && (part.len() == 1 || !part.starts_with('0'))
+    })
+}
+
+/// Normalize a Chrome-style version string into a Firefox-acceptable one
+///
+/// Returns `None` when the version is already valid. Otherwise leading zeros
+/// are dropped, non-numeric suffixes removed and extra segments truncated.
+pub fn normalize_firefox_version(version: &str) -> Option<String> {
+    if is_valid_firefox_version(version) {
+        return None;
+    }
+    
+    let mut parts = Vec::new();
+    for part in version.split('.').take(4) {
+        let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if digits.is_empty() {
+            break;
+        }
+        
+        let trimmed = digits.trim_start_matches('0');
+        parts.push(if trimmed.is_empty() { "0".to_string() } else { trimmed.chars().take(9).collect() });
+        
+        // Stop at a segment with a suffix such as "1-beta"
+        if digits.len() != part.len() {
+            break;
+        }
+    }
+    
+    if parts.is_empty() {
+        Some("0.0.0".to_string())
+    } else {
+        Some(parts.join("."))
+    }
+}
+
+/// Match a relative path against a glob (`*`, `?` within a segment, `**` across segments)
+///
+/// Patterns without a `/` match the file name anywhere in the tree, so
+/// `*.min.js` behaves like `**/*.min.js`.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let path = path.replace('\\', "/");
+    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    
+    if !pattern.contains('/') {
+        return path_segments.last().is_some_and(|name| segment_match(pattern.as_bytes(), name.as_bytes()));
+    }
+    
+    let pattern_segments: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    segments_match(&pattern_segments, &path_segments)
+}
+
+fn segments_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(rest, &path[skip..])),
+        Some((first, rest)) => path.split_first().is_some_and(|(segment, path_rest)| {
+            segment_match(first.as_bytes(), segment.as_bytes()) && segments_match(rest, path_rest)
+        }),
+    }
+}
+
+fn segment_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| segment_match(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && segment_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && segment_match(rest, &text[1..]),
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, hex encoded
+pub fn fnv1a_hex<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for chunk in chunks {
+        for byte in chunk {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// 1-based line number of a byte offset in `content`
+pub fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -32,6 +166,27 @@ mod tests {
         assert!(!is_match_pattern("tabs"));
     }
     
+    #[test]
+    fn test_is_valid_match_pattern() {
+        assert!(is_valid_match_pattern("<all_urls>"));
+        assert!(is_valid_match_pattern("*://*.example.com/*"));
+        assert!(is_valid_match_pattern("https://example.com/path/*"));
+        assert!(is_valid_match_pattern("file:///home/*"));
+        assert!(!is_valid_match_pattern("https://example.com"));
+        assert!(!is_valid_match_pattern("https://www.*.com/*"));
+        assert!(!is_valid_match_pattern("chrome://settings/*"));
+        assert!(!is_valid_match_pattern("data://example.com/*"));
+        assert!(!is_valid_match_pattern("example.com/*"));
+    }
+    
+    #[test]
+    fn test_line_of() {
+        let content = "first\nsecond\n\nfourth";
+        assert_eq!(line_of(content, 0), 1);
+        assert_eq!(line_of(content, content.find("second").unwrap()), 2);
+        assert_eq!(line_of(content, content.find("fourth").unwrap()), 4);
+    }
+    
     #[test]
     fn test_sanitize_name() {
         assert_eq!(sanitize_name("My Extension"), "my-extension");
@@ -45,4 +200,25 @@ mod tests {
             "my-extension@converted.extension"
         );
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_firefox_version_normalization() {
+        assert!(is_valid_firefox_version("1.2.3.4"));
+        assert_eq!(normalize_firefox_version("1.2.3.4"), None);
+        assert_eq!(normalize_firefox_version("1.02.3"), Some("1.2.3".to_string()));
+        assert_eq!(normalize_firefox_version("1.2.3.4.5"), Some("1.2.3.4".to_string()));
+        assert_eq!(normalize_firefox_version("2.0.1-beta.3"), Some("2.0.1".to_string()));
+        assert_eq!(normalize_firefox_version("beta"), Some("0.0.0".to_string()));
+    }
+    
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("vendor/**", "vendor/lib/jquery.js"));
+        assert!(glob_match("*.min.js", "lib/jquery.min.js"));
+        assert!(glob_match("**/*.map", "dist/app.js.map"));
+        assert!(glob_match(".chrome2moz-*.json", ".chrome2moz-provenance.json"));
+        assert!(glob_match("src/?.js", "src/a.js"));
+        assert!(!glob_match("vendor/**", "src/vendor.js"));
+        assert!(!glob_match("src/*.js", "src/lib/a.js"));
+    }
+}
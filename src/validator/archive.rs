@@ -0,0 +1,91 @@
+//! Packaged XPI validation
+
+use anyhow::{Context, Result};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Re-open a built XPI file and check that Firefox can install it
+pub fn validate_xpi_file(xpi_path: &Path) -> Result<()> {
+    let data = std::fs::read(xpi_path)
+        .with_context(|| format!("Failed to read {}", xpi_path.display()))?;
+    validate_xpi(&data)
+        .with_context(|| format!("Invalid XPI: {}", xpi_path.display()))
+}
+
+/// Check an in-memory XPI: intact archive, safe entry names, root manifest.json
+pub fn validate_xpi(xpi_data: &[u8]) -> Result<()> {
+    let mut archive = ZipArchive::new(Cursor::new(xpi_data))
+        .context("Corrupt ZIP central directory")?;
+    
+    let mut has_root_manifest = false;
+    let mut nested_manifest = None;
+    
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)
+            .with_context(|| format!("Unreadable archive entry #{}", i))?;
+        let name = entry.name().to_string();
+        
+        if name.starts_with('/') || name.starts_with('\\') || name.get(1..2) == Some(":") {
+            anyhow::bail!("Archive entry uses an absolute path: {}", name);
+        }
+        if name.split(['/', '\\']).any(|component| component == "..") {
+            anyhow::bail!("Archive entry escapes the extension root: {}", name);
+        }
+        
+        // Reading the whole entry verifies its CRC
+        let mut sink = Vec::new();
+        entry.read_to_end(&mut sink)
+            .with_context(|| format!("Corrupt archive entry: {}", name))?;
+        
+        if name == "manifest.json" {
+            has_root_manifest = true;
+        } else if name.ends_with("/manifest.json") && nested_manifest.is_none() {
+            nested_manifest = Some(name);
+        }
+    }
+    
+    if !has_root_manifest {
+        match nested_manifest {
+            Some(name) => anyhow::bail!("manifest.json must be at the archive root, found {}", name),
+            None => anyhow::bail!("Archive has no manifest.json"),
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+    
+    fn zip_with(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+    
+    #[test]
+    fn test_well_formed_xpi_passes() {
+        let xpi = zip_with(&[("manifest.json", "{}"), ("shims/a.js", "//")]);
+        assert!(validate_xpi(&xpi).is_ok());
+    }
+    
+    #[test]
+    fn test_invalid_xpi_rejected() {
+        let nested = zip_with(&[("my-extension/manifest.json", "{}")]);
+        let err = validate_xpi(&nested).unwrap_err().to_string();
+        assert!(err.contains("my-extension/manifest.json"));
+        
+        let escaping = zip_with(&[("manifest.json", "{}"), ("../evil.js", "")]);
+        assert!(validate_xpi(&escaping).unwrap_err().to_string().contains("../evil.js"));
+        
+        let truncated = zip_with(&[("manifest.json", "{}")]);
+        assert!(validate_xpi(&truncated[..truncated.len() - 10]).is_err());
+    }
+}
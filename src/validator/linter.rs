@@ -0,0 +1,116 @@
+//! Validation of already-converted output with addons-linter
+//!
+//! Mozilla's addons-linter is what AMO runs on submission. When it isn't
+//! installed, the built-in structural checks run instead.
+
+use crate::models::{Incompatibility, IncompatibilityCategory, Location, NewFile, Severity};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Findings for a converted extension
+#[derive(Debug, Clone)]
+pub struct OutputValidation {
+    pub issues: Vec<Incompatibility>,
+    /// Whether addons-linter ran; otherwise only `check_structure` did
+    pub linter_ran: bool,
+}
+
+/// Validate a converted directory or XPI, preferring addons-linter
+pub fn validate_output(path: &Path) -> Result<OutputValidation> {
+    validate_output_with(path, "addons-linter")
+}
+
+/// [`validate_output`] with the linter executable to look for
+pub fn validate_output_with(path: &Path, linter: &str) -> Result<OutputValidation> {
+    let Ok(output) = Command::new(linter).arg(path).arg("--output").arg("json").output() else {
+        return Ok(OutputValidation { issues: validate_structure_only(path)?, linter_ran: false });
+    };
+
+    // The linter exits non-zero when it finds errors, so only the output matters
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&stdout)
+        .with_context(|| format!("{} produced unreadable output: {}", linter, String::from_utf8_lossy(&output.stderr).trim()))?;
+    Ok(OutputValidation { issues: parse_linter_output(&json), linter_ran: true })
+}
+
+/// Convert addons-linter `--output json` into issues
+///
+/// Errors fail AMO submission, so they are blockers; warnings are minor and
+/// notices informational.
+pub fn parse_linter_output(json: &serde_json::Value) -> Vec<Incompatibility> {
+    let mut issues = Vec::new();
+    for (key, severity) in [("errors", Severity::Blocker), ("warnings", Severity::Minor), ("notices", Severity::Info)] {
+        let Some(entries) = json[key].as_array() else { continue };
+        for entry in entries {
+            let location = match (entry["file"].as_str(), entry["line"].as_u64()) {
+                (Some("manifest.json"), _) => Location::Manifest,
+                (Some(file), Some(line)) => Location::FileLocation(PathBuf::from(file), line as usize),
+                (Some(file), None) => Location::File(PathBuf::from(file)),
+                (None, _) => Location::Manifest,
+            };
+            let message = entry["message"].as_str().unwrap_or("Unknown linter finding");
+            let description = match entry["code"].as_str() {
+                Some(code) => format!("{} ({})", message, code),
+                None => message.to_string(),
+            };
+            let mut issue = Incompatibility::new(
+                severity.clone(),
+                IncompatibilityCategory::LinterFinding,
+                location,
+                description,
+            );
+            if let Some(detail) = entry["description"].as_str().filter(|d| !d.is_empty()) {
+                issue = issue.with_suggestion(detail);
+            }
+            issues.push(issue);
+        }
+    }
+    issues
+}
+
+fn validate_structure_only(path: &Path) -> Result<Vec<Incompatibility>> {
+    let extension = crate::packager::load_extension(path)?;
+    // Shims may live in any --shims-dir, but they always load as background
+    // scripts, so every background script gets the shim CSP check
+    let shims: Vec<NewFile> = extension.get_background_scripts().iter()
+        .filter_map(|file| {
+            Some(NewFile {
+                path: file.clone(),
+                content: extension.get_file_content(file)?,
+                purpose: String::new(),
+            })
+        })
+        .collect();
+
+    let issues = match super::structure::check_structure(&extension.manifest, &extension, &shims) {
+        Ok(()) => Vec::new(),
+        Err(e) => vec![Incompatibility::new(
+            Severity::Blocker,
+            IncompatibilityCategory::ManifestStructure,
+            Location::Manifest,
+            e.to_string(),
+        )],
+    };
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_linter_output() {
+        let json = serde_json::json!({
+            "errors": [{ "code": "MANIFEST_FIELD_REQUIRED", "message": "\"/name\" is a required property", "file": "manifest.json" }],
+            "warnings": [{ "code": "UNSAFE_VAR_ASSIGNMENT", "message": "Unsafe assignment to innerHTML", "description": "Use textContent", "file": "popup.js", "line": 12 }],
+            "notices": []
+        });
+        let issues = parse_linter_output(&json);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].severity, Severity::Blocker);
+        assert!(issues[0].description.contains("MANIFEST_FIELD_REQUIRED"));
+        assert!(matches!(issues[1].location, Location::FileLocation(_, 12)));
+        assert_eq!(issues[1].suggestion.as_deref(), Some("Use textContent"));
+    }
+}
@@ -1,10 +1,15 @@
 //! Validation module
 
 pub mod structure;
+pub mod archive;
+pub mod linter;
 
 use crate::models::ConversionResult;
 use anyhow::Result;
 
+pub use archive::{validate_xpi, validate_xpi_file};
+pub use linter::{validate_output, OutputValidation};
+
 pub fn validate_extension(result: &ConversionResult) -> Result<()> {
     structure::validate_structure(result)
 }
\ No newline at end of file
@@ -1,15 +1,47 @@
 //! Structural validation
 
-use crate::models::ConversionResult;
+use crate::analyzer::csp::{effective_extension_pages_policy, policy_allows};
+use crate::models::{ConversionResult, Extension, Manifest, NewFile};
 use anyhow::Result;
 
+/// Sources a shim needs from the extension_pages CSP: `(marker in the shim, directive, source)`
+///
+/// None of the generated shims use these constructs; this guards third-party or
+/// hand-edited scripts in the shims directory, which `validate` treats as shims.
+const SHIM_CSP_NEEDS: &[(&str, &str, &str)] = &[
+    ("WebAssembly.", "script-src", "'wasm-unsafe-eval'"),
+    ("new Worker(URL.createObjectURL", "worker-src", "blob:"),
+    ("importScripts('data:", "script-src", "data:"),
+];
+
 pub fn validate_structure(result: &ConversionResult) -> Result<()> {
-    // Validate manifest
-    validate_manifest(&result.manifest)?;
-    
     // Validate files exist
     validate_files(result)?;
     
+    let shims: Vec<_> = result.new_files.iter()
+        .filter(|file| result.is_shim(&file.path))
+        .cloned()
+        .collect();
+    check_structure(&result.manifest, &result.source, &shims)
+}
+
+/// Structural checks shared by conversion and `validate` on converted output
+pub fn check_structure(manifest: &Manifest, extension: &Extension, shims: &[NewFile]) -> Result<()> {
+    // Validate manifest
+    validate_manifest(manifest)?;
+    
+    // Validate locale messages (Firefox refuses to load malformed ones)
+    let locale_errors = locale_message_errors(extension);
+    if !locale_errors.is_empty() {
+        anyhow::bail!("Malformed locale messages:\n  {}", locale_errors.join("\n  "));
+    }
+    
+    // Validate the CSP lets injected shims run
+    let conflicts = shim_csp_conflicts(manifest, shims);
+    if !conflicts.is_empty() {
+        anyhow::bail!("Content security policy blocks injected shims:\n  {}", conflicts.join("\n  "));
+    }
+    
     Ok(())
 }
 
@@ -38,4 +70,136 @@ fn validate_manifest(manifest: &crate::models::Manifest) -> Result<()> {
 fn validate_files(_result: &ConversionResult) -> Result<()> {
     // TODO: Validate that referenced files exist
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Entries of `_locales/*/messages.json` that Firefox would reject, as `<file>: <problem>`
+///
+/// Every message needs a `message` string, and `placeholders` must map names
+/// to objects with a `content` string.
+pub fn locale_message_errors(extension: &Extension) -> Vec<String> {
+    let mut paths: Vec<_> = extension.files.keys()
+        .filter(|path| {
+            let mut components = path.components().map(|c| c.as_os_str());
+            components.next().is_some_and(|c| c == "_locales")
+                && components.nth(1).is_some_and(|c| c == "messages.json")
+                && components.next().is_none()
+        })
+        .collect();
+    paths.sort();
+    
+    let mut errors = Vec::new();
+    for path in paths {
+        let file = path.display().to_string().replace('\\', "/");
+        let Some(content) = extension.get_file_content(path) else { continue };
+        let messages = match serde_json::from_str::<serde_json::Value>(content.trim_start_matches('\u{feff}')) {
+            Ok(serde_json::Value::Object(messages)) => messages,
+            Ok(_) => {
+                errors.push(format!("{}: expected an object of messages", file));
+                continue;
+            }
+            Err(e) => {
+                errors.push(format!("{}: invalid JSON ({})", file, e));
+                continue;
+            }
+        };
+        
+        for (key, entry) in &messages {
+            if !entry.get("message").is_some_and(|m| m.is_string()) {
+                errors.push(format!("{}: message \"{}\" has no \"message\" string", file, key));
+            }
+            let Some(placeholders) = entry.get("placeholders") else { continue };
+            let Some(placeholders) = placeholders.as_object() else {
+                errors.push(format!("{}: message \"{}\" has non-object \"placeholders\"", file, key));
+                continue;
+            };
+            for (name, placeholder) in placeholders {
+                if !placeholder.get("content").is_some_and(|c| c.is_string()) {
+                    errors.push(format!(
+                        "{}: placeholder \"{}\" of message \"{}\" has no \"content\" string",
+                        file, name, key
+                    ));
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Shims whose script sources the effective `extension_pages` policy does not allow
+///
+/// Generated shims never conflict; see `SHIM_CSP_NEEDS`.
+pub fn shim_csp_conflicts(manifest: &Manifest, shims: &[NewFile]) -> Vec<String> {
+    let policy = effective_extension_pages_policy(manifest);
+    
+    let mut conflicts = Vec::new();
+    for shim in shims {
+        for (marker, directive, source) in SHIM_CSP_NEEDS {
+            if shim.content.contains(marker) && !policy_allows(policy, directive, source) {
+                conflicts.push(format!(
+                    "{} needs {} in {}, which the extension_pages policy does not allow",
+                    shim.path.display(), source, directive
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    fn manifest_with_csp(policy: &str) -> Manifest {
+        let json = serde_json::json!({
+            "manifest_version": 3,
+            "name": "Test",
+            "version": "1.0",
+            "content_security_policy": { "extension_pages": policy }
+        });
+        crate::parser::manifest::parse_manifest(json.to_string().as_bytes()).unwrap()
+    }
+    
+    fn shim(content: &str) -> NewFile {
+        NewFile {
+            path: PathBuf::from("shims/wasm-compat.js"),
+            content: content.to_string(),
+            purpose: "test".to_string(),
+        }
+    }
+    
+    #[test]
+    fn test_malformed_locale_messages() {
+        let manifest = manifest_with_csp("script-src 'self'");
+        let files = [
+            (
+                PathBuf::from("_locales/en/messages.json"),
+                br#"{"appName": {"message": "App"}, "greeting": {"message": "Hi $NAME$", "placeholders": {"name": {"content": "$1"}}}}"#.to_vec(),
+            ),
+            (
+                PathBuf::from("_locales/de/messages.json"),
+                br#"{"appName": {"description": "Name"}, "greeting": {"message": "Hallo $NAME$", "placeholders": {"name": {"example": "Otso"}}}}"#.to_vec(),
+            ),
+        ].into_iter().collect();
+        let extension = Extension::new(manifest, files);
+        
+        let errors = locale_message_errors(&extension);
+        assert_eq!(errors, vec![
+            "_locales/de/messages.json: message \"appName\" has no \"message\" string",
+            "_locales/de/messages.json: placeholder \"name\" of message \"greeting\" has no \"content\" string",
+        ]);
+    }
+    
+    #[test]
+    fn test_restrictive_csp_blocks_wasm_shim() {
+        let shims = [shim("WebAssembly.instantiate(bytes);")];
+        
+        let conflicts = shim_csp_conflicts(&manifest_with_csp("script-src 'self'; object-src 'self'"), &shims);
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].contains("shims/wasm-compat.js needs 'wasm-unsafe-eval' in script-src"));
+        
+        let relaxed = manifest_with_csp("script-src 'self' 'wasm-unsafe-eval'; object-src 'self'");
+        assert!(shim_csp_conflicts(&relaxed, &shims).is_empty());
+        assert!(shim_csp_conflicts(&relaxed, &[shim("browser.runtime.getURL('x');")]).is_empty());
+    }
+}
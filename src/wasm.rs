@@ -1,9 +1,7 @@
 //! WebAssembly bindings for Chrome to Firefox converter
 
 use wasm_bindgen::prelude::*;
-use std::io::{Cursor, Write};
 use std::collections::HashMap;
-use std::path::PathBuf;
 
 #[wasm_bindgen]
 extern "C" {
@@ -178,88 +176,10 @@ pub fn analyze_extension_zip(zip_data: &[u8]) -> Result<String, JsValue> {
 
 /// Load extension from raw ZIP bytes
 fn load_extension_from_bytes(zip_data: &[u8]) -> anyhow::Result<crate::models::Extension> {
-    use zip::ZipArchive;
-    use crate::parser::manifest::parse_manifest;
-    
-    let cursor = Cursor::new(zip_data);
-    let mut archive = ZipArchive::new(cursor)?;
-    
-    let mut files = HashMap::new();
-    let mut manifest_content = None;
-    
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        
-        if file.is_file() {
-            let path = PathBuf::from(file.name());
-            let mut content = Vec::new();
-            std::io::copy(&mut file, &mut content)?;
-            
-            // Save manifest separately
-            if path.file_name().and_then(|n| n.to_str()) == Some("manifest.json") {
-                manifest_content = Some(content.clone());
-            }
-            
-            files.insert(path, content);
-        }
-    }
-    
-    // Parse manifest
-    let manifest = manifest_content
-        .ok_or_else(|| anyhow::anyhow!("manifest.json not found in ZIP"))
-        .and_then(|content| parse_manifest(&content))?;
-    
-    Ok(crate::models::Extension::new(manifest, files))
+    crate::packager::extractor::load_from_bytes(zip_data)
 }
 
 /// Create ZIP file from conversion result
 fn create_zip_from_result(result: &crate::models::ConversionResult) -> anyhow::Result<Vec<u8>> {
-    use zip::write::{FileOptions, ZipWriter};
-    use zip::CompressionMethod;
-    use std::collections::HashSet;
-    
-    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
-    
-    // FileOptions without time feature (WASM compatible)
-    let options = FileOptions::default()
-        .compression_method(CompressionMethod::Deflated)
-        .unix_permissions(0o755);
-    
-    // Track which files have been written
-    let mut written_files = HashSet::new();
-    
-    // 1. Write manifest.json
-    let manifest_json = serde_json::to_string_pretty(&result.manifest)?;
-    zip.start_file("manifest.json", options)?;
-    zip.write_all(manifest_json.as_bytes())?;
-    written_files.insert(PathBuf::from("manifest.json"));
-    
-    // 2. Write modified files
-    for modified in &result.modified_files {
-        let path_str: String = modified.path.to_string_lossy().into_owned();
-        zip.start_file(path_str, options)?;
-        zip.write_all(modified.new_content.as_bytes())?;
-        written_files.insert(modified.path.clone());
-    }
-    
-    // 3. Write new files (shims)
-    for new_file in &result.new_files {
-        let path_str: String = new_file.path.to_string_lossy().into_owned();
-        zip.start_file(path_str, options)?;
-        zip.write_all(new_file.content.as_bytes())?;
-        written_files.insert(new_file.path.clone());
-    }
-    
-    // 4. Copy all other original files
-    for (path, content) in &result.source.files {
-        if !written_files.contains(path) {
-            let path_str: String = path.to_string_lossy().into_owned();
-            zip.start_file(path_str, options)?;
-            zip.write_all(content)?;
-        }
-    }
-    
-    let cursor = zip.finish()?;
-    Ok(cursor.into_inner())
-}
\ No newline at end of file
+    crate::packager::builder::build_xpi_bytes(result)
+}
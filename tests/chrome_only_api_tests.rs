@@ -6,7 +6,7 @@ use chrome2moz::transformer::{
     OffscreenConverter, DeclarativeContentConverter, TabGroupsConverter,
 };
 use chrome2moz::analyzer::{OffscreenAnalyzer, DeclarativeContentAnalyzer};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[test]
 fn test_offscreen_purpose_names() {
@@ -192,6 +192,53 @@ fn test_offscreen_canvas_conversion() {
     assert!(result.instructions.len() > 0);
 }
 
+fn dom_parsing_usage() -> (DocumentAnalysis, OffscreenUsage) {
+    let analysis = DocumentAnalysis {
+        primary_purpose: OffscreenPurpose::DomParsing,
+        ..Default::default()
+    };
+    let usage = OffscreenUsage {
+        call_location: FileLocation::new(PathBuf::from("background.js"), 3, 0),
+        document_url: "offscreen.html".to_string(),
+        reasons: vec!["DOM_SCRAPING".to_string()],
+        justification: None,
+    };
+    (analysis, usage)
+}
+
+#[test]
+fn test_dom_conversion_without_prompt_falls_back_to_all_urls() {
+    let converter = OffscreenConverter::new(std::env::temp_dir(), ConversionPreferences::default());
+    let (analysis, usage) = dom_parsing_usage();
+    
+    let result = converter.convert_dom_to_content_script(&analysis, &usage).unwrap();
+    
+    match &result.manifest_changes[0] {
+        ManifestChange::AddContentScript { matches, .. } => assert_eq!(matches, &vec!["<all_urls>".to_string()]),
+        other => panic!("Expected AddContentScript, got {:?}", other),
+    }
+    assert!(result.instructions.iter().any(|i| i.starts_with("Warning:") && i.contains("<all_urls>")));
+}
+
+#[test]
+fn test_dom_conversion_uses_prompted_urls() {
+    fn answer(suggested: &[String]) -> Option<Vec<String>> {
+        assert!(!suggested.is_empty());
+        Some(vec!["https://news.example.com/*".to_string()])
+    }
+    let converter = OffscreenConverter::new(std::env::temp_dir(), ConversionPreferences::default())
+        .with_url_prompt(answer);
+    let (analysis, usage) = dom_parsing_usage();
+    
+    let result = converter.convert_dom_to_content_script(&analysis, &usage).unwrap();
+    
+    match &result.manifest_changes[0] {
+        ManifestChange::AddContentScript { matches, .. } => assert_eq!(matches, &vec!["https://news.example.com/*".to_string()]),
+        other => panic!("Expected AddContentScript, got {:?}", other),
+    }
+    assert!(!result.instructions.iter().any(|i| i.starts_with("Warning:")));
+}
+
 #[test]
 fn test_declarative_content_converter_simple() {
     let converter = DeclarativeContentConverter::new();
@@ -213,6 +260,7 @@ fn test_declarative_content_converter_simple() {
                 query_suffix: None,
                 url_matches: None,
                 schemes: None,
+                excluded_hosts: Vec::new(),
             },
             css: Some(vec!["video".to_string()]),
             is_bookmarked: None,
@@ -273,16 +321,67 @@ fn test_url_filter_to_match_pattern() {
         query_suffix: None,
         url_matches: None,
         schemes: None,
+        excluded_hosts: Vec::new(),
     };
     
     let pattern = filter.to_match_pattern();
     assert!(pattern.contains("example.com"));
 }
 
+#[test]
+fn test_url_filter_path_prefix() {
+    let filter = UrlFilter {
+        host_suffix: Some(".example.com".to_string()),
+        path_prefix: Some("/watch".to_string()),
+        schemes: Some(vec!["https".to_string()]),
+        ..Default::default()
+    };
+    assert_eq!(filter.to_match_pattern(), "https://*.example.com/watch*");
+    
+    let filter = UrlFilter {
+        path_contains: Some("checkout".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(filter.to_match_pattern(), "*://*/*checkout*");
+}
+
+#[test]
+fn test_url_filter_excluded_domains() {
+    let converter = DeclarativeContentConverter::new();
+    let rules = vec![DeclarativeContentRule {
+        conditions: vec![PageCondition::PageStateMatcher {
+            page_url: UrlFilter {
+                path_suffix: Some(".pdf".to_string()),
+                excluded_hosts: vec!["intranet.example.com".to_string(), ".ads.example.net".to_string()],
+                ..Default::default()
+            },
+            css: None,
+            is_bookmarked: None,
+        }],
+        actions: vec![PageAction::ShowPageAction],
+        location: FileLocation {
+            file: PathBuf::from("background.js"),
+            line: 1,
+            column: 0,
+        },
+    }];
+    
+    let result = converter.convert(&rules).unwrap();
+    let ManifestChange::AddContentScript { matches, exclude_matches, .. } = &result.manifest_changes[0] else {
+        panic!("expected a content script change");
+    };
+    assert_eq!(matches, &vec!["*://*/*.pdf".to_string()]);
+    assert_eq!(
+        exclude_matches,
+        &vec!["*://*.ads.example.net/*".to_string(), "*://intranet.example.com/*".to_string()]
+    );
+}
+
 #[test]
 fn test_manifest_change_variants() {
     let change1 = ManifestChange::AddContentScript {
         matches: vec!["*://example.com/*".to_string()],
+        exclude_matches: Vec::new(),
         js: vec!["content.js".to_string()],
         run_at: "document_idle".to_string(),
     };
@@ -350,4 +449,94 @@ fn test_conversion_result_merge() {
     
     assert_eq!(merged.new_files.len(), 2);
     assert_eq!(merged.instructions.len(), 2);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_request_content_script_injected_by_handler() {
+    let code = r#"
+chrome.declarativeContent.onPageChanged.addRules([{
+  conditions: [new chrome.declarativeContent.PageStateMatcher({
+    pageUrl: { hostSuffix: 'example.com' },
+    css: ['video']
+  })],
+  actions: [new chrome.declarativeContent.RequestContentScript({
+    js: ['player.js'],
+    css: ['player.css']
+  })]
+}]);
+"#;
+    let rules = DeclarativeContentAnalyzer::new()
+        .analyze_usage(code, Path::new("background.js"))
+        .unwrap();
+    assert_eq!(rules.len(), 1);
+    let PageCondition::PageStateMatcher { css, .. } = &rules[0].conditions[0];
+    assert_eq!(css.as_deref(), Some(&["video".to_string()][..]));
+    assert!(rules[0].actions.iter().any(|action| matches!(
+        action,
+        PageAction::RequestContentScript { js, css } if js == &["player.js"] && css == &["player.css"]
+    )));
+    
+    let result = DeclarativeContentConverter::new().convert(&rules).unwrap();
+    let handler = result.new_files.iter()
+        .find(|f| f.path.ends_with("background_declarative_content_handler.js"))
+        .unwrap();
+    assert!(handler.content.contains("message.type === 'page_condition_met'"));
+    assert!(handler.content.contains(r#"const ruleContentScripts = {"0":{"css":["player.css"],"js":["player.js"]}};"#));
+    assert!(handler.content.contains("browser.scripting.executeScript({ target, files: files.js })"));
+    assert!(handler.content.contains("browser.scripting.insertCSS({ target, files: files.css })"));
+    assert!(result.manifest_changes.iter().any(|c| matches!(c, ManifestChange::AddPermission(p) if p == "scripting")));
+}
+
+#[test]
+fn test_request_content_script_injects_only_matched_rule() {
+    let rule = |host: &str, script: &str| DeclarativeContentRule {
+        conditions: vec![PageCondition::PageStateMatcher {
+            page_url: UrlFilter { host_equals: Some(host.to_string()), ..Default::default() },
+            css: None,
+            is_bookmarked: None,
+        }],
+        actions: vec![PageAction::RequestContentScript { js: vec![script.to_string()], css: vec![] }],
+        location: FileLocation::new(PathBuf::from("background.js"), 1, 0),
+    };
+    let rules = vec![rule("video.example.com", "player.js"), rule("docs.example.com", "reader.js")];
+    
+    let result = DeclarativeContentConverter::new().convert(&rules).unwrap();
+    let file = |name: &str| &result.new_files.iter().find(|f| f.path.ends_with(name)).unwrap().content;
+    
+    let handler = file("background_declarative_content_handler.js");
+    assert!(handler.contains(r#""0":{"css":[],"js":["player.js"]}"#));
+    assert!(handler.contains(r#""1":{"css":[],"js":["reader.js"]}"#));
+    assert!(handler.contains("ruleContentScripts[message.ruleId]"));
+    
+    let checker = file("page-condition-checker.js");
+    assert!(checker.contains(r#"if (url.hostname === "video.example.com") {"#));
+    assert!(checker.contains("ruleId: 0"));
+    assert!(checker.contains("ruleId: 1"));
+}
+
+#[test]
+fn test_page_condition_checks_full_url_filter() {
+    let code = r#"
+chrome.declarativeContent.onPageChanged.addRules([{
+  conditions: [new chrome.declarativeContent.PageStateMatcher({
+    pageUrl: { hostContains: 'shop', pathPrefix: '/cart', queryContains: 'step=pay', schemes: ['https'] }
+  })],
+  actions: [new chrome.declarativeContent.ShowPageAction()]
+}]);
+"#;
+    let rules = DeclarativeContentAnalyzer::new()
+        .analyze_usage(code, Path::new("background.js"))
+        .unwrap();
+    let result = DeclarativeContentConverter::new().convert(&rules).unwrap();
+    
+    let ManifestChange::AddContentScript { matches, .. } = &result.manifest_changes[0] else {
+        panic!("expected a content script change");
+    };
+    assert_eq!(matches, &vec!["https://*/cart*".to_string()]);
+    
+    let checker = &result.new_files.iter().find(|f| f.path.ends_with("page-condition-checker.js")).unwrap().content;
+    assert!(checker.contains("const url = new URL(location.href);"));
+    assert!(checker.contains(
+        r#"if (url.hostname.includes("shop") && url.pathname.startsWith("/cart") && url.search.slice(1).includes("step=pay") && ["https"].includes(url.protocol.slice(0, -1))) {"#
+    ));
+}
@@ -3,7 +3,7 @@
 //! These tests use real Chrome extension examples and validate output
 //! using Mozilla's addons-linter.
 
-use chrome2moz::{convert_extension, ConversionOptions, CalculatorType};
+use chrome2moz::{convert_extension, ConversionOptions, NamespaceStrategy};
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
@@ -222,10 +222,9 @@ fn test_storage_session_conversion() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -257,10 +256,9 @@ fn test_sidepanel_conversion() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -294,10 +292,9 @@ fn test_dnr_conversion() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -332,10 +329,9 @@ fn test_userscripts_conversion() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -366,10 +362,9 @@ fn test_legacy_tabs_conversion() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -391,6 +386,58 @@ fn test_legacy_tabs_conversion() {
     let _ = validate_with_linter(&temp_output.path().to_path_buf());
 }
 
+#[test]
+fn test_windows_create_focused_feature_detection() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Windows", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.windows.create({ url: 'popup.html', focused: false, state: 'normal' });\n",
+    ).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: true,
+        ..Default::default()
+    };
+    convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let shim = fs::read_to_string(temp_output.path().join("shims/tabs-windows-compat.js")).unwrap();
+    let check = shim.find("!(await supportsFocused())").expect("focused support is not checked");
+    let remap = shim.find("data.state = 'minimized'").unwrap();
+    assert!(check < remap, "state is remapped before checking focused support");
+    assert!(shim.contains("data.focused === false && !data.state"), "explicit state is overridden");
+}
+
+#[test]
+fn test_notifications_get_permission_level_shim() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Notify", "version": "1.0", "permissions": ["notifications"], "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.notifications.getPermissionLevel((level) => {\n  if (level === 'granted') chrome.notifications.create({ type: 'basic', title: 'Hi', message: 'There' });\n});\n",
+    ).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: true,
+        ..Default::default()
+    };
+    convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let shim = fs::read_to_string(temp_output.path().join("shims/notifications-compat.js")).unwrap();
+    assert!(shim.contains("api.notifications.getPermissionLevel = async function(callback)"));
+    assert!(shim.contains("const level = 'granted';"));
+}
+
 #[test]
 fn test_all_shims_together() {
     // Create an extension that uses multiple APIs
@@ -420,10 +467,9 @@ chrome.declarativeNetRequest.getDynamicRules();
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: true,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -507,10 +553,9 @@ fn test_real_world_latex_to_calc() {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: true,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -612,10 +657,9 @@ chrome.storage.local.get("key", (result) => {
     
     let options = ConversionOptions {
         interactive: false,
-        target_calculator: CalculatorType::Both,
         preserve_chrome_compatibility: true,
         generate_report: false,
-        
+        ..Default::default()
     };
     
     let result = convert_extension(
@@ -636,4 +680,1237 @@ chrome.storage.local.get("key", (result) => {
             "Manifest should have Firefox-specific settings");
     
     let _ = validate_with_linter(&temp_output.path().to_path_buf());
-}
\ No newline at end of file
+}
+#[test]
+fn test_only_manifest_mode() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Manifest Only Test",
+  "version": "1.0.0",
+  "background": {
+    "service_worker": "background.js"
+  },
+  "permissions": ["storage"]
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    
+    let background = r#"importScripts('lib.js');
+chrome.storage.session.set({ key: 'value' });
+"#;
+    fs::write(temp_input.path().join("background.js"), background).unwrap();
+    fs::write(temp_input.path().join("lib.js"), "const lib = 1;\n").unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: true,
+        generate_report: false,
+        only_manifest: true,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(
+        temp_input.path(),
+        temp_output.path(),
+        options
+    ).expect("Conversion failed");
+    
+    // JavaScript is copied byte-for-byte (importScripts is not commented out)
+    let background_content = fs::read_to_string(temp_output.path().join("background.js")).unwrap();
+    assert_eq!(background_content, background);
+    assert!(result.modified_files.is_empty());
+    
+    // No shims are generated or referenced
+    assert!(result.new_files.is_empty());
+    assert!(!temp_output.path().join("shims").exists());
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    assert_eq!(scripts, vec!["lib.js".to_string(), "background.js".to_string()]);
+    
+    // The manifest is still transformed
+    let manifest_content = fs::read_to_string(temp_output.path().join("manifest.json")).unwrap();
+    assert!(manifest_content.contains("browser_specific_settings"));
+    assert!(result.report.javascript_changes.iter().any(|c| c.contains("untouched")));
+}
+
+#[test]
+fn test_polyfill_only_namespace_strategy() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Polyfill Test",
+  "version": "1.0.0",
+  "background": {
+    "service_worker": "background.js"
+  },
+  "content_scripts": [{
+    "matches": ["https://example.com/*"],
+    "js": ["content.js"]
+  }, {
+    "matches": ["https://example.com/*"],
+    "css": ["style.css"]
+  }],
+  "permissions": ["storage"]
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.storage.local.set({ a: 1 });\n").unwrap();
+    fs::write(temp_input.path().join("content.js"), "chrome.storage.local.get('a');\n").unwrap();
+    fs::write(temp_input.path().join("style.css"), "body { color: red; }\n").unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: false,
+        generate_report: false,
+        namespace_strategy: NamespaceStrategy::PolyfillOnly,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(
+        temp_input.path(),
+        temp_output.path(),
+        options
+    ).expect("Conversion failed");
+    
+    // chrome.* is left intact
+    let background_content = fs::read_to_string(temp_output.path().join("background.js")).unwrap();
+    assert!(background_content.contains("chrome.storage.local.set"));
+    
+    // The polyfill is shipped and loaded first everywhere
+    assert!(temp_output.path().join("shims/browser-polyfill.js").exists());
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    assert_eq!(scripts[0], "shims/browser-polyfill.js");
+    assert_eq!(result.manifest.content_scripts[0].js, vec!["shims/browser-polyfill.js", "content.js"]);
+    assert!(result.manifest.content_scripts[1].js.is_empty());
+}
+
+#[test]
+fn test_source_archive_generation() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    let output_path = temp_output.path().join("converted");
+    
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    let original_background = fs::read(temp_input.path().join("background.js")).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: true,
+        generate_report: false,
+        generate_source_archive: true,
+        exclude_patterns: vec!["docs/*.md".to_string()],
+        bundle_shims: true,
+        target_firefox_version: Some(128),
+        ..Default::default()
+    };
+    
+    convert_extension(temp_input.path(), &output_path, options).expect("Conversion failed");
+    
+    let sources_path = chrome2moz::packager::source_archive_path(&output_path);
+    assert!(sources_path.exists(), "sources archive not created");
+    assert!(output_path.with_extension("xpi").exists());
+    
+    let mut archive = zip::ZipArchive::new(fs::File::open(&sources_path).unwrap()).unwrap();
+    let mut background = Vec::new();
+    std::io::copy(&mut archive.by_name("background.js").unwrap(), &mut background).unwrap();
+    assert_eq!(background, original_background, "sources must contain the pre-transform background.js");
+    use std::io::Read;
+    let mut readme = String::new();
+    archive.by_name("README.md").unwrap().read_to_string(&mut readme).unwrap();
+    assert!(readme.contains(
+        "3. Run `chrome2moz convert --input source --output converted --yes --preserve-chrome --exclude 'docs/*.md' --bundle-shims --since-version 128`"
+    ), "{}", readme);
+}
+
+#[test]
+fn test_split_oversized_background() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Split Background Test",
+  "version": "1.0.0",
+  "background": {
+    "service_worker": "background.js"
+  }
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    
+    let mut background = String::from("const counters = {};\n\n");
+    for i in 0..50 {
+        background.push_str(&format!(
+            "function count{i}(key) {{\n  counters[key] = (counters[key] || 0) + {i};\n  return counters[key];\n}}\n\n"
+        ));
+    }
+    background.push_str("chrome.runtime.onMessage.addListener((msg) => count0(msg.key));\n");
+    fs::write(temp_input.path().join("background.js"), &background).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        split_background_threshold: Some(1024),
+        ..Default::default()
+    };
+    
+    let result = convert_extension(
+        temp_input.path(),
+        temp_output.path(),
+        options
+    ).expect("Conversion failed");
+    
+    // Parts are listed ahead of the main script, which keeps the listener
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    let parts: Vec<_> = scripts.iter().filter(|s| s.starts_with("background.part")).collect();
+    assert!(parts.len() > 1);
+    assert_eq!(scripts.last().unwrap(), "background.js");
+    assert!(parts.iter().all(|p| temp_output.path().join(p).exists()));
+    
+    let main = fs::read_to_string(temp_output.path().join("background.js")).unwrap();
+    assert!(main.contains("onMessage.addListener"));
+    assert!(!main.contains("function count10("));
+    assert!(result.report.warnings.iter().any(|w| w.message.contains("was split into")));
+}
+
+#[test]
+fn test_progress_callbacks() {
+    use chrome2moz::{convert_extension_with_progress, ConversionStage, ProgressSink};
+    use std::path::Path;
+    
+    #[derive(Default)]
+    struct RecordingSink {
+        stages: Vec<ConversionStage>,
+        files: Vec<(usize, usize)>,
+    }
+    
+    impl ProgressSink for RecordingSink {
+        fn on_stage(&mut self, stage: ConversionStage) {
+            self.stages.push(stage);
+        }
+        
+        fn on_file(&mut self, _path: &Path, index: usize, total: usize) {
+            self.files.push((index, total));
+        }
+    }
+    
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    let mut sink = RecordingSink::default();
+    convert_extension_with_progress(temp_input.path(), temp_output.path(), options, &mut sink)
+        .expect("Conversion failed");
+    
+    assert_eq!(sink.stages, vec![
+        ConversionStage::Loading,
+        ConversionStage::Analyzing,
+        ConversionStage::Transforming,
+        ConversionStage::Validating,
+        ConversionStage::Packaging,
+        ConversionStage::Reporting,
+    ]);
+    assert_eq!(sink.files, vec![(1, 1)]);
+}
+
+#[test]
+fn test_action_open_popup_raises_version_floor() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Open Popup Test",
+  "version": "1.0.0",
+  "action": { "default_popup": "popup.html" },
+  "background": {
+    "service_worker": "background.js"
+  }
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.runtime.onMessage.addListener(() => chrome.action.openPopup());\n"
+    ).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(
+        temp_input.path(),
+        temp_output.path(),
+        options
+    ).expect("Conversion failed");
+    
+    // The raised floor has action.openPopup natively, so no fallback shim ships
+    assert!(!temp_output.path().join("shims/action-open-popup-compat.js").exists());
+    
+    let gecko = result.manifest.browser_specific_settings.as_ref().unwrap().gecko.as_ref().unwrap();
+    assert_eq!(gecko.strict_min_version.as_deref(), Some("127.0"));
+}
+
+#[test]
+fn test_theme_extension_conversion() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Midnight Theme",
+  "version": "1.0",
+  "theme": {
+    "images": { "theme_frame": "images/frame.png" },
+    "colors": { "frame": [20, 20, 40], "omnibox_text": [255, 255, 255], "button_background": [0, 0, 0, 0] },
+    "properties": { "ntp_background_alignment": "bottom" }
+  }
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::create_dir(temp_input.path().join("images")).unwrap();
+    fs::write(temp_input.path().join("images/frame.png"), [0x89, b'P', b'N', b'G']).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(
+        temp_input.path(),
+        temp_output.path(),
+        options
+    ).expect("Conversion failed");
+    
+    // No background scripts or shims for a theme
+    assert!(result.manifest.background.is_none());
+    assert!(result.new_files.is_empty());
+    assert!(!temp_output.path().join("shims").exists());
+    assert!(result.manifest.browser_specific_settings.is_some());
+    
+    let theme = &result.manifest.extra["theme"];
+    assert_eq!(theme["colors"]["toolbar_field_text"], serde_json::json!([255, 255, 255]));
+    assert!(theme["colors"].get("button_background").is_none());
+    assert!(theme.get("properties").is_none());
+}
+
+#[test]
+fn test_quiet_mode_prints_nothing_on_success() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_chrome2moz"))
+        .args(["convert", "--yes", "--quiet", "-i"])
+        .arg(temp_input.path())
+        .arg("-o")
+        .arg(temp_output.path().join("out"))
+        .output()
+        .expect("Failed to run chrome2moz");
+    
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+    assert!(temp_output.path().join("out/manifest.json").exists());
+}
+
+#[test]
+fn test_watch_writes_report() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    let output = temp_output.path().join("out");
+    
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chrome2moz"))
+        .args(["convert", "--yes", "--quiet", "--watch", "--report", "-i"])
+        .arg(temp_input.path())
+        .arg("-o")
+        .arg(&output)
+        .spawn()
+        .expect("Failed to run chrome2moz");
+    
+    let report = output.with_extension("md");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(20);
+    while !report.exists() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(report.exists(), "watch mode should write the report like a normal convert");
+}
+
+#[test]
+fn test_quiet_mode_keeps_analyze_results() {
+    let temp_input = TempDir::new().unwrap();
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_chrome2moz"))
+        .args(["analyze", "--quiet", "-i"])
+        .arg(temp_input.path())
+        .output()
+        .expect("Failed to run chrome2moz");
+    
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Analyzing extension..."), "stdout: {}", stdout);
+    assert!(stdout.contains("Analysis Results"), "stdout: {}", stdout);
+}
+
+#[test]
+fn test_quiet_mode_keeps_summary_report() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    create_storage_session_extension(&temp_input.path().to_path_buf());
+    
+    let output = Command::new(env!("CARGO_BIN_EXE_chrome2moz"))
+        .args(["convert", "--yes", "--quiet", "--report", "--report-format", "summary", "-i"])
+        .arg(temp_input.path())
+        .arg("-o")
+        .arg(temp_output.path().join("out"))
+        .output()
+        .expect("Failed to run chrome2moz");
+    
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1, "stdout: {}", stdout);
+    assert!(stdout.starts_with("Modified ") && stdout.contains("(confidence "), "stdout: {}", stdout);
+}
+
+#[test]
+fn test_downloads_on_determining_filename_shim() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Downloader",
+  "version": "1.0.0",
+  "permissions": ["downloads"],
+  "background": {
+    "service_worker": "background.js"
+  }
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::write(temp_input.path().join("background.js"), r#"
+chrome.downloads.onDeterminingFilename.addListener((item, suggest) => {
+  suggest({ filename: 'renamed/' + item.filename });
+});
+"#).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let shim = fs::read_to_string(temp_output.path().join("shims/downloads-compat.js")).unwrap();
+    assert!(shim.contains("api.downloads.onDeterminingFilename = {"));
+    assert!(shim.contains("addListener"));
+    assert!(shim.contains("filename"));
+}
+
+#[test]
+fn test_content_script_globs_preserved() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Globs Test",
+  "version": "1.0.0",
+  "content_scripts": [{
+    "matches": ["https://*.example.com/*", "https://example.com"],
+    "exclude_matches": ["https://admin.example.com/*"],
+    "include_globs": ["*example.com/app/*"],
+    "exclude_globs": ["*?debug=1*"],
+    "match_about_blank": true,
+    "js": ["content.js"]
+  }]
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::write(temp_input.path().join("content.js"), "console.log('hi');\n").unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let written: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(temp_output.path().join("manifest.json")).unwrap()
+    ).unwrap();
+    let content_script = &written["content_scripts"][0];
+    assert_eq!(content_script["include_globs"], serde_json::json!(["*example.com/app/*"]));
+    assert_eq!(content_script["exclude_globs"], serde_json::json!(["*?debug=1*"]));
+    assert_eq!(content_script["exclude_matches"], serde_json::json!(["https://admin.example.com/*"]));
+    assert_eq!(content_script["match_about_blank"], serde_json::json!(true));
+    
+    // "https://example.com" has no path and is reported
+    assert!(result.report.manual_actions.iter().any(|a| a.contains("'https://example.com'")));
+}
+
+#[test]
+fn test_edge_update_url_removed_and_recorded() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Edge Extension",
+  "version": "1.0.0",
+  "update_url": "https://edge.microsoft.com/extensionwebstorebase/v1/crx"
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    assert!(!result.manifest.extra.contains_key("update_url"));
+    assert!(result.report.manifest_changes.iter().any(|c| c.contains("'update_url'")));
+}
+
+#[test]
+fn test_chrome_key_dropped_unless_requested() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Keyed", "version": "1.0", "key": "MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA"}"#,
+    ).unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("default"), options).expect("Conversion failed");
+    assert!(!result.manifest.extra.contains_key("key"));
+    
+    let options = ConversionOptions { interactive: false, preserve_chrome_key: true, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("keyed"), options).expect("Conversion failed");
+    assert!(result.manifest.extra.contains_key("key"));
+    assert!(result.manifest.browser_specific_settings.is_some());
+}
+
+#[test]
+fn test_provenance_file_written_and_excludable() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    
+    let manifest = r#"{
+  "manifest_version": 3,
+  "name": "Provenance Test",
+  "version": "1.0.0",
+  "background": { "service_worker": "background.js" }
+}"#;
+    fs::write(temp_input.path().join("manifest.json"), manifest).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.runtime.onInstalled.addListener(() => {});").unwrap();
+    
+    let output = temp_output.path().join("out");
+    let options = ConversionOptions {
+        interactive: false,
+        ..Default::default()
+    };
+    convert_extension(temp_input.path(), &output, options).expect("Conversion failed");
+    
+    let provenance = fs::read_to_string(output.join(".chrome2moz-provenance.json"))
+        .expect("provenance file should be written");
+    let provenance: serde_json::Value = serde_json::from_str(&provenance).unwrap();
+    assert_eq!(provenance["tool_version"], env!("CARGO_PKG_VERSION"));
+    assert!(!provenance["input_hash"].as_str().unwrap().is_empty());
+    // Written next to the output but not packed unless asked for
+    let mut xpi = zip::ZipArchive::new(fs::File::open(output.with_extension("xpi")).unwrap()).unwrap();
+    assert!(xpi.by_name(".chrome2moz-provenance.json").is_err());
+    assert!(xpi.by_name("background.js").is_ok());
+    
+    let packaged = temp_output.path().join("packaged");
+    let options = ConversionOptions {
+        interactive: false,
+        package_provenance: true,
+        ..Default::default()
+    };
+    convert_extension(temp_input.path(), &packaged, options).expect("Conversion failed");
+    let mut xpi = zip::ZipArchive::new(fs::File::open(packaged.with_extension("xpi")).unwrap()).unwrap();
+    assert!(xpi.by_name(".chrome2moz-provenance.json").is_ok());
+    
+    let release = temp_output.path().join("release");
+    let options = ConversionOptions {
+        interactive: false,
+        exclude_patterns: vec![".chrome2moz-*.json".to_string()],
+        ..Default::default()
+    };
+    convert_extension(temp_input.path(), &release, options).expect("Conversion failed");
+    assert!(!release.join(".chrome2moz-provenance.json").exists());
+    assert!(release.join("background.js").exists());
+}
+
+#[test]
+fn test_issues_grouped_by_file() {
+    use chrome2moz::models::{Incompatibility, IncompatibilityCategory, Location, Severity, MANIFEST_ISSUES_KEY};
+    
+    let manifest = chrome2moz::parser::manifest::parse_manifest(
+        br#"{"manifest_version": 3, "name": "Test", "version": "1.0"}"#
+    ).unwrap();
+    let mut context = chrome2moz::ConversionContext::new(chrome2moz::Extension::new(manifest, Default::default()));
+    
+    let issue = |location| Incompatibility::new(Severity::Minor, IncompatibilityCategory::ChromeOnlyApi, location, "issue");
+    context.add_incompatibility(issue(Location::FileLocation(PathBuf::from("src/background.js"), 3)));
+    context.add_incompatibility(issue(Location::ManifestField("permissions".to_string())));
+    context.add_incompatibility(issue(Location::FileLocation(PathBuf::from("src/background.js"), 10)));
+    context.add_incompatibility(issue(Location::File(PathBuf::from("content.js"))));
+    context.add_incompatibility(issue(Location::Manifest));
+    
+    let grouped = context.issues_by_file();
+    let keys: Vec<_> = grouped.keys().cloned().collect();
+    assert_eq!(keys, vec![PathBuf::from("content.js"), PathBuf::from(MANIFEST_ISSUES_KEY), PathBuf::from("src/background.js")]);
+    assert_eq!(grouped[&PathBuf::from("src/background.js")].len(), 2);
+    assert_eq!(grouped[&PathBuf::from(MANIFEST_ISSUES_KEY)].len(), 2);
+}
+
+#[test]
+fn test_reports_group_issues_by_file() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Issues", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "// setup\nchrome.runtime.requestUpdateCheck();\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    let issues = &result.report.issues_by_file["background.js"];
+    assert!(issues.iter().any(|i| i.position.as_deref() == Some("Line 2") && i.description.contains("requestUpdateCheck")));
+    
+    let markdown = chrome2moz::report::generate_report(&result).unwrap();
+    assert!(markdown.contains("## Issues by File"));
+    assert!(markdown.contains(&format!("### background.js ({})", issues.len())));
+    
+    // The section survives a JSON round trip, which carries no source files
+    let reloaded = chrome2moz::ConversionResult::from_json(&result.to_json().unwrap()).unwrap();
+    let html = chrome2moz::report::generate_html_report(&reloaded).unwrap();
+    assert!(html.contains("<summary>Issues by file"));
+    assert!(html.contains("<span class=\"line\">Line 2</span>"));
+}
+
+#[test]
+fn test_html_report_permission_suggestions() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Narrow", "version": "1.0", "host_permissions": ["<all_urls>"], "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "fetch('https://api.example.com/data');\n").unwrap();
+
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+
+    // Suggestions are read from the report, so they survive a JSON round trip
+    let reloaded = chrome2moz::ConversionResult::from_json(&result.to_json().unwrap()).unwrap();
+    let html = chrome2moz::report::generate_html_report(&reloaded).unwrap();
+    assert!(html.contains(
+        "<li>Replace <code>&lt;all_urls&gt;</code> with <code>https://api.example.com/*</code>: "
+    ));
+    assert!(!html.contains('`'));
+    let markdown = chrome2moz::report::generate_report(&reloaded).unwrap();
+    assert!(markdown.contains("- Replace `<all_urls>` with `https://api.example.com/*`"));
+}
+
+#[test]
+fn test_transform_single_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("popup.js");
+    let output = temp_dir.path().join("popup.out.js");
+    fs::write(&input, "chrome.storage.local.get(['theme'], (items) => console.log(items.theme));\n").unwrap();
+    
+    let result = Command::new(env!("CARGO_BIN_EXE_chrome2moz"))
+        .args(["transform", "-i"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .output()
+        .expect("Failed to run chrome2moz");
+    
+    assert!(result.status.success(), "stderr: {}", String::from_utf8_lossy(&result.stderr));
+    // chrome.* passes through unchanged; Firefox supports the namespace natively
+    assert_eq!(fs::read_to_string(&output).unwrap(), fs::read_to_string(&input).unwrap());
+    assert!(String::from_utf8_lossy(&result.stdout).contains("No changes needed"));
+}
+
+#[test]
+fn test_changes_recorded_by_kind() {
+    use chrome2moz::models::ChangeKind;
+    
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Kinds", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "importScripts('lib.js');\n\
+         chrome.pageAction.show(1);\n\
+         chrome.pageAction.hide(1);\n\
+         chrome.webRequest.onBeforeSendHeaders.addListener(fn, { urls: ['<all_urls>'] }, ['requestHeaders', 'extraHeaders']);\n",
+    ).unwrap();
+    fs::write(temp_input.path().join("lib.js"), "var lib = {};\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    
+    let summary = &result.report.summary;
+    assert_eq!(summary.changes_by_kind.get(&ChangeKind::ApiRemap), Some(&3));
+    assert_eq!(summary.changes_by_kind.get(&ChangeKind::Other), Some(&1));
+    assert!(!summary.changes_by_kind.contains_key(&ChangeKind::NamespaceRewrite));
+    assert_eq!(summary.chrome_api_calls_converted, 3);
+    assert_eq!(summary.total_changes, 4);
+}
+
+#[test]
+fn test_html_report() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Report <Test>", "version": "1.2", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.pageAction.show(1);\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    let html = chrome2moz::report::generate_html_report(&result).unwrap();
+    
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("Report &lt;Test&gt; v1.2"));
+    assert!(html.contains("<style>"));
+    assert_eq!(html.matches(" changes)</summary>").count(), 1);
+    assert!(html.contains("<code>background.js</code>"));
+}
+
+#[test]
+fn test_single_and_multi_thread_outputs_match() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Threads", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "importScripts('lib0.js');\nchrome.pageAction.show(1);\n").unwrap();
+    for i in 0..16 {
+        fs::write(
+            temp_input.path().join(format!("lib{}.js", i)),
+            format!("chrome.pageAction.setTitle({{ tabId: {}, title: 't' }});\nchrome.pageAction.hide({});\n", i, i),
+        ).unwrap();
+    }
+    
+    let convert = |threads, name: &str| {
+        let options = ConversionOptions { interactive: false, max_threads: Some(threads), ..Default::default() };
+        convert_extension(temp_input.path(), &temp_output.path().join(name), options).expect("Conversion failed")
+    };
+    let serial = convert(1, "serial");
+    let parallel = convert(4, "parallel");
+    
+    let files = |result: &chrome2moz::ConversionResult| -> Vec<(PathBuf, String, Vec<String>)> {
+        result.modified_files.iter()
+            .map(|f| (f.path.clone(), f.new_content.clone(), f.changes.iter().map(|c| c.description.clone()).collect()))
+            .collect()
+    };
+    assert_eq!(serial.modified_files.len(), 17);
+    assert_eq!(files(&serial), files(&parallel));
+    assert_eq!(serial.report.javascript_changes, parallel.report.javascript_changes);
+    for entry in fs::read_dir(temp_output.path().join("serial")).unwrap() {
+        let name = entry.unwrap().file_name();
+        let parallel_path = temp_output.path().join("parallel").join(&name);
+        // The provenance file records the conversion time
+        if parallel_path.is_file() && name != chrome2moz::models::PROVENANCE_FILE {
+            assert_eq!(fs::read(temp_output.path().join("serial").join(&name)).unwrap(), fs::read(parallel_path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_omnibox_preserved() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Omnibox", "version": "1.0", "omnibox": {"keyword": "go"}, "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.omnibox.onInputEntered.addListener((text) => chrome.tabs.create({ url: text }));\n",
+    ).unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    
+    assert_eq!(result.manifest.omnibox.as_ref().map(|o| o.keyword.as_str()), Some("go"));
+    let written = fs::read_to_string(temp_output.path().join("out").join("manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&written).unwrap();
+    assert_eq!(manifest["omnibox"]["keyword"], "go");
+}
+
+#[test]
+fn test_offscreen_dom_document_converted_before_packaging() {
+    use std::io::Read;
+    
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Scraper", "version": "1.0", "permissions": ["offscreen"], "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.offscreen.createDocument({ url: 'offscreen.html', reasons: ['DOM_SCRAPING'], justification: 'parse pages' });\n",
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("offscreen.html"),
+        "<html><body>\n<script>\nconst heading = document.querySelector('h1');\n</script>\n</body></html>\n",
+    ).unwrap();
+    
+    fn answer(_suggested: &[String]) -> Option<Vec<String>> {
+        Some(vec!["https://news.example.com/*".to_string()])
+    }
+    let options = ConversionOptions { interactive: false, offscreen_url_prompt: Some(answer), ..Default::default() };
+    let output = temp_output.path().join("out");
+    let result = convert_extension(temp_input.path(), &output, options).expect("Conversion failed");
+    
+    let script = result.manifest.content_scripts.iter()
+        .find(|script| script.js == ["content-scripts/dom-parser.js"])
+        .expect("dom-parser content script should be registered");
+    assert_eq!(script.matches, ["https://news.example.com/*"]);
+    assert!(result.report.manual_actions.iter().any(|action| action.contains("background.js:1")));
+    
+    let mut xpi = zip::ZipArchive::new(fs::File::open(output.with_extension("xpi")).unwrap()).unwrap();
+    assert!(xpi.by_name("content-scripts/dom-parser.js").is_ok());
+    let mut manifest = String::new();
+    xpi.by_name("manifest.json").unwrap().read_to_string(&mut manifest).unwrap();
+    assert!(manifest.contains("content-scripts/dom-parser.js"));
+}
+
+#[test]
+fn test_archive_assets_streamed_without_loading() {
+    use std::io::{Read, Write};
+    
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    let archive_path = temp_input.path().join("large.zip");
+    // Not valid UTF-8, so it can only survive as raw bytes
+    let asset: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8 | 0x80).collect();
+    
+    let mut zip = zip::ZipWriter::new(fs::File::create(&archive_path).unwrap());
+    let options = zip::write::FileOptions::default();
+    zip.start_file("manifest.json", options).unwrap();
+    zip.write_all(br#"{"manifest_version": 3, "name": "Large", "version": "1.0", "background": {"service_worker": "background.js"}}"#).unwrap();
+    zip.start_file("background.js", options).unwrap();
+    zip.write_all(b"chrome.runtime.onInstalled.addListener(() => {});\n").unwrap();
+    zip.start_file("assets/video.bin", options).unwrap();
+    zip.write_all(&asset).unwrap();
+    zip.finish().unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let output = temp_output.path().join("out");
+    let result = convert_extension(&archive_path, &output, options).expect("Conversion failed");
+    
+    let asset_path = PathBuf::from("assets/video.bin");
+    assert!(!result.source.files.contains_key(&asset_path));
+    assert!(result.source.deferred.entries.contains_key(&asset_path));
+    assert!(result.source.files.contains_key(&PathBuf::from("background.js")));
+    assert_eq!(result.source.metadata.file_count, 3);
+    
+    assert_eq!(fs::read(output.join("assets/video.bin")).unwrap(), asset);
+    let mut xpi = zip::ZipArchive::new(fs::File::open(output.with_extension("xpi")).unwrap()).unwrap();
+    let mut packaged = Vec::new();
+    xpi.by_name("assets/video.bin").unwrap().read_to_end(&mut packaged).unwrap();
+    assert_eq!(packaged, asset);
+}
+
+#[test]
+fn test_conversion_result_json_round_trip() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Persisted", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.pageAction.show(1);\nchrome.storage.session.set({ a: 1 });\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    assert!(!result.modified_files.is_empty());
+    assert!(!result.new_files.is_empty());
+    
+    let json = result.to_json().unwrap();
+    let document: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(document["schema_version"], chrome2moz::models::RESULT_SCHEMA_VERSION);
+    
+    let restored = chrome2moz::ConversionResult::from_json(&json).unwrap();
+    assert_eq!(restored.manifest, result.manifest);
+    assert_eq!(restored.source.manifest, result.source.manifest);
+    assert_eq!(restored.modified_files, result.modified_files);
+    assert_eq!(restored.new_files, result.new_files);
+    assert_eq!(restored.report, result.report);
+    assert_eq!(restored.provenance, result.provenance);
+    assert_eq!(restored.exclude_patterns, result.exclude_patterns);
+    
+    let future = json.replacen(
+        &format!("\"schema_version\": {}", chrome2moz::models::RESULT_SCHEMA_VERSION),
+        "\"schema_version\": 999",
+        1,
+    );
+    assert!(chrome2moz::ConversionResult::from_json(&future).is_err());
+}
+
+#[test]
+fn test_analyze_ignore_skips_vendored_files() {
+    let temp_input = TempDir::new().unwrap();
+    fs::create_dir_all(temp_input.path().join("vendor")).unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Monorepo", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.storage.session.set({ a: 1 });\n").unwrap();
+    fs::write(
+        temp_input.path().join("vendor/lib.js"),
+        "const render = new Function('data', 'return data.name');\nchrome.tabs.sendMessage(1, {});\n",
+    ).unwrap();
+    fs::write(temp_input.path().join("vendor/widget.html"), "<button onclick=\"chrome.runtime.reload()\">Reload</button>\n").unwrap();
+    
+    let analyze = |ignore: Vec<String>| {
+        let extension = chrome2moz::packager::load_extension(temp_input.path()).unwrap();
+        let options = ConversionOptions { analyze_ignore: ignore, ..Default::default() };
+        chrome2moz::analyzer::analyze_extension_with_options(extension, options, &mut chrome2moz::progress::NoProgress).unwrap()
+    };
+    let vendor_files_with_issues = |context: &chrome2moz::models::ConversionContext| {
+        context.issues_by_file().keys().filter(|path| path.starts_with("vendor")).count()
+    };
+    
+    let unfiltered = analyze(Vec::new());
+    assert_eq!(vendor_files_with_issues(&unfiltered), 2);
+    
+    let filtered = analyze(vec!["vendor/**".to_string()]);
+    assert_eq!(vendor_files_with_issues(&filtered), 0);
+    assert!(filtered.issues_by_file().contains_key(&PathBuf::from("background.js")));
+    // Manifest analysis still runs
+    assert!(filtered.issues_by_file().contains_key(&PathBuf::from(chrome2moz::models::MANIFEST_ISSUES_KEY)));
+}
+
+#[test]
+fn test_tts_shim() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Reader", "version": "1.0", "permissions": ["tts"], "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.tts.speak('Hello', { rate: 1.2, gender: 'female' });\n",
+    ).unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let shim = fs::read_to_string(temp_output.path().join("shims/tts-compat.js")).expect("tts-compat.js shim not created");
+    assert!(shim.contains("speechSynthesis.speak(speech)"));
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    assert!(scripts.contains(&"shims/tts-compat.js".to_string()));
+}
+
+#[test]
+fn test_fail_on_major_threshold() {
+    let temp_input = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Templates", "version": "1.0", "action": {"default_popup": "popup.html"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("popup.html"), "<script src=\"popup.js\"></script>").unwrap();
+    fs::write(
+        temp_input.path().join("popup.js"),
+        "const render = new Function('data', 'return data.title');\n",
+    ).unwrap();
+    
+    let temp_output = TempDir::new().unwrap();
+    let options = ConversionOptions {
+        interactive: false,
+        fail_on: Some(chrome2moz::models::Severity::Major),
+        ..Default::default()
+    };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    let error = result.fail_on_error.as_deref().expect("major issue not reported");
+    assert!(error.contains("at or above major severity"), "{}", error);
+    assert!(temp_output.path().with_extension("xpi").exists());
+    assert!(chrome2moz::report::generate_report(&result).is_ok());
+    
+    let options = ConversionOptions {
+        interactive: false,
+        fail_on: Some(chrome2moz::models::Severity::Blocker),
+        ..Default::default()
+    };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    assert!(result.fail_on_error.is_none());
+}
+
+#[test]
+fn test_summary_report() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Summary", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.pageAction.show(1);\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let mut result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    result.report.blockers = vec!["blocker".to_string()];
+    result.report.manual_actions = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+    result.report.warnings.clear();
+    
+    let summary = chrome2moz::report::generate_summary(&result);
+    assert!(summary.starts_with("Modified 1 file, added "));
+    assert!(summary.ends_with(", 1 blocker, 3 manual actions (confidence 60%)"));
+}
+
+#[test]
+fn test_confidence_weighting() {
+    let mut report = chrome2moz::models::ConversionReport::default();
+    assert_eq!(report.confidence(), 100);
+    
+    report.warnings = vec![chrome2moz::models::ReportWarning::new("warning", None); 3];
+    assert_eq!(report.confidence(), 97);
+    report.manual_actions = vec!["manual".to_string(); 2];
+    assert_eq!(report.confidence(), 87);
+    report.blockers = vec!["blocker".to_string()];
+    assert_eq!(report.confidence(), 62);
+    
+    // Four blockers alone leave no confidence, and the score never goes negative
+    report.blockers = vec!["blocker".to_string(); 5];
+    assert_eq!(report.confidence(), 0);
+}
+
+#[test]
+fn test_custom_shims_dir() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Shim Dir", "version": "1.0", "background": {"service_worker": "background.js"}, "content_scripts": [{"matches": ["<all_urls>"], "js": ["content.js"]}]}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.tts.speak('hi');\n").unwrap();
+    fs::write(temp_input.path().join("content.js"), "chrome.runtime.sendMessage({});\n").unwrap();
+    
+    let options = ConversionOptions {
+        interactive: false,
+        preserve_chrome_compatibility: true,
+        shims_dir: "vendor/compat".to_string(),
+        ..Default::default()
+    };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    assert!(!temp_output.path().join("shims").exists());
+    assert!(temp_output.path().join("vendor/compat/browser-polyfill.js").exists());
+    assert!(temp_output.path().join("vendor/compat/tts-compat.js").exists());
+    assert!(result.new_files.iter().all(|f| f.path.starts_with("vendor/compat")));
+    
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    assert_eq!(scripts.first().map(String::as_str), Some("vendor/compat/browser-polyfill.js"));
+    assert!(scripts.iter().filter(|s| s.as_str() != "background.js").all(|s| s.starts_with("vendor/compat/")));
+    assert_eq!(result.manifest.content_scripts[0].js[0], "vendor/compat/browser-polyfill.js");
+    assert!(chrome2moz::report::generate_summary(&result).contains(&format!("added {} shims", result.new_files.len())));
+}
+
+#[test]
+fn test_bundle_shims() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Bundle", "version": "1.0", "background": {"service_worker": "background.js"}, "content_scripts": [{"matches": ["<all_urls>"], "js": ["content.js"]}]}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.storage.session.set({ a: 1 });\n").unwrap();
+    fs::write(temp_input.path().join("content.js"), "chrome.runtime.sendMessage({});\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, preserve_chrome_compatibility: true, bundle_shims: true, ..Default::default() };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let bundle = fs::read_to_string(temp_output.path().join("shims/compat-bundle.js")).expect("compat-bundle.js not created");
+    assert!(bundle.contains("// ==== shims/storage-session-compat.js ===="));
+    assert!(bundle.find("shims/browser-polyfill.js").unwrap() < bundle.find("shims/storage-session-compat.js").unwrap());
+    assert_eq!(fs::read_dir(temp_output.path().join("shims")).unwrap().count(), 2);
+    
+    let scripts = result.manifest.background.as_ref().unwrap().scripts.clone().unwrap();
+    assert_eq!(scripts, vec!["shims/compat-bundle.js", "background.js"]);
+    // Background-only shims stay out of pages; content scripts get the polyfill alone
+    assert_eq!(result.manifest.content_scripts[0].js, vec!["shims/browser-polyfill.js", "content.js"]);
+    assert!(!fs::read_to_string(temp_output.path().join("shims/browser-polyfill.js")).unwrap().contains("storage.session"));
+}
+
+#[test]
+fn test_permission_narrowing_report() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Narrow", "version": "1.0", "background": {"service_worker": "background.js"}, "host_permissions": ["<all_urls>"]}"#,
+    ).unwrap();
+    fs::write(
+        temp_input.path().join("background.js"),
+        "chrome.alarms.onAlarm.addListener(async () => {\n  const res = await fetch('https://api.example.com/v1/status');\n  console.log(await res.json());\n});\n",
+    ).unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    let report = chrome2moz::report::generate_report(&result).unwrap();
+    
+    assert!(report.contains("## 🔒 Permission Narrowing Suggestions"));
+    assert!(report.contains("Replace `<all_urls>` with `https://api.example.com/*`"));
+}
+
+#[test]
+fn test_validate_output_without_linter() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Validate", "version": "1.0", "default_locale": "en", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.runtime.onInstalled.addListener(() => {});\n").unwrap();
+    fs::create_dir_all(temp_input.path().join("_locales/en")).unwrap();
+    fs::write(temp_input.path().join("_locales/en/messages.json"), r#"{"title": {"message": "Validate"}}"#).unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let missing_linter = "chrome2moz-test-no-such-linter";
+    let validation = chrome2moz::validator::linter::validate_output_with(temp_output.path(), missing_linter).unwrap();
+    assert!(!validation.linter_ran);
+    assert!(validation.issues.is_empty(), "{:?}", validation.issues);
+    
+    // Structure checks catch damage to the converted output
+    fs::write(temp_output.path().join("_locales/en/messages.json"), r#"{"title": {}}"#).unwrap();
+    let validation = chrome2moz::validator::linter::validate_output_with(temp_output.path(), missing_linter).unwrap();
+    assert_eq!(validation.issues.len(), 1);
+    assert!(validation.issues[0].description.contains("Malformed locale messages"));
+}
+
+#[test]
+fn test_validate_output_checks_relocated_shims() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Validate", "version": "1.0", "background": {"service_worker": "background.js"},
+            "content_security_policy": {"extension_pages": "script-src 'self'; object-src 'self'"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.runtime.onInstalled.addListener(() => {});\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, shims_dir: "vendor/compat".to_string(), ..Default::default() };
+    convert_extension(temp_input.path(), temp_output.path(), options).expect("Conversion failed");
+    
+    let shim = temp_output.path().join("vendor/compat/runtime-compat.js");
+    let content = fs::read_to_string(&shim).unwrap();
+    fs::write(&shim, format!("{}\nconst worker = new Worker(URL.createObjectURL(blob));\n", content)).unwrap();
+    
+    let validation = chrome2moz::validator::linter::validate_output_with(temp_output.path(), "chrome2moz-test-no-such-linter").unwrap();
+    assert_eq!(validation.issues.len(), 1, "{:?}", validation.issues);
+    assert!(validation.issues[0].description.contains("vendor/compat/runtime-compat.js needs blob: in worker-src"));
+}
+
+#[test]
+fn test_directory_output_format_skips_xpi() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Dir Only", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.storage.session.set({ a: 1 });\n").unwrap();
+    
+    let output = temp_output.path().join("out");
+    let options = ConversionOptions {
+        interactive: false,
+        package_format: chrome2moz::PackageFormat::Directory,
+        ..Default::default()
+    };
+    let result = convert_extension(temp_input.path(), &output, options).expect("Conversion failed");
+    
+    assert!(!output.with_extension("xpi").exists(), "XPI created in directory-only mode");
+    for file in &result.new_files {
+        assert!(output.join(&file.path).exists(), "{} missing", file.path.display());
+    }
+    let loaded = chrome2moz::packager::load_extension(&output).unwrap();
+    assert!(loaded.manifest.browser_specific_settings.is_some());
+    assert!(loaded.has_file(std::path::Path::new("background.js")));
+    let validation = chrome2moz::validator::linter::validate_output_with(&output, "chrome2moz-test-no-such-linter").unwrap();
+    assert!(validation.issues.is_empty(), "{:?}", validation.issues);
+}
+
+#[test]
+fn test_option_enums_from_str() {
+    use chrome2moz::models::Severity;
+    use chrome2moz::report::ReportFormat;
+    use chrome2moz::{NamespaceStrategy, PackageFormat};
+    
+    assert_eq!("xpi".parse::<PackageFormat>().unwrap(), PackageFormat::Xpi);
+    assert_eq!("dir".parse::<PackageFormat>().unwrap(), PackageFormat::Directory);
+    assert_eq!("Major".parse::<Severity>().unwrap(), Severity::Major);
+    assert_eq!("polyfill-only".parse::<NamespaceStrategy>().unwrap(), NamespaceStrategy::PolyfillOnly);
+    assert_eq!("md".parse::<ReportFormat>().unwrap(), ReportFormat::Markdown);
+    
+    // Display output parses back to the same value
+    for format in [PackageFormat::Xpi, PackageFormat::Directory] {
+        assert_eq!(format.to_string().parse::<PackageFormat>().unwrap(), format);
+    }
+    for severity in [Severity::Info, Severity::Minor, Severity::Major, Severity::Blocker] {
+        assert_eq!(severity.to_string().parse::<Severity>().unwrap(), severity);
+    }
+    for format in [ReportFormat::Markdown, ReportFormat::Html, ReportFormat::Summary] {
+        assert_eq!(format.to_string().parse::<ReportFormat>().unwrap(), format);
+    }
+    
+    let err = "zip".parse::<PackageFormat>().unwrap_err();
+    assert_eq!(err.to_string(), "unknown output format 'zip' (expected xpi or dir)");
+    assert!("critical".parse::<Severity>().is_err());
+    assert!("pdf".parse::<ReportFormat>().is_err());
+}
+
+#[test]
+fn test_located_warning_round_trips_through_json() {
+    let temp_input = TempDir::new().unwrap();
+    let temp_output = TempDir::new().unwrap();
+    fs::write(
+        temp_input.path().join("manifest.json"),
+        r#"{"manifest_version": 3, "name": "Located", "version": "1.0", "background": {"service_worker": "background.js"}}"#,
+    ).unwrap();
+    fs::write(temp_input.path().join("background.js"), "chrome.runtime.onInstalled.addListener(() => {});\n").unwrap();
+    
+    let options = ConversionOptions { interactive: false, ..Default::default() };
+    let result = convert_extension(temp_input.path(), &temp_output.path().join("out"), options).expect("Conversion failed");
+    let located = result.report.warnings.iter()
+        .find(|w| w.location.is_some())
+        .expect("no located warning");
+    
+    let json = result.to_json().unwrap();
+    let document: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let warnings = document["report"]["warnings"].as_array().unwrap();
+    assert!(warnings.iter().any(|w| w["message"] == located.message.as_str() && w["location"] == "manifest.json"));
+    
+    let restored = chrome2moz::ConversionResult::from_json(&json).unwrap();
+    assert_eq!(restored.report.warnings, result.report.warnings);
+    
+    // Version 1 documents stored warnings as plain strings
+    let mut legacy = document.clone();
+    legacy["schema_version"] = 1.into();
+    legacy["report"]["warnings"] = serde_json::json!(["Something to check"]);
+    let restored = chrome2moz::ConversionResult::from_json(&legacy.to_string()).unwrap();
+    assert_eq!(restored.report.warnings, vec![chrome2moz::models::ReportWarning::new("Something to check", None)]);
+}